@@ -0,0 +1,19 @@
+//! Generate the C header from the exported functions into `OUT_DIR`.
+//! The committed `include/fast_stoi.h` is checked against it by the tests.
+
+use std::{env, path::PathBuf};
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("Unable to generate the C header")
+        .write_to_file(PathBuf::from(env::var("OUT_DIR").unwrap()).join("fast_stoi.h"));
+
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+}
@@ -0,0 +1,107 @@
+#ifndef FAST_STOI_H
+#define FAST_STOI_H
+
+/* Generated by cbindgen from fast-stoi-capi/src/lib.rs. Do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// Error codes, mirroring the Rust error type
+typedef enum FastStoiError {
+  // Success
+  FAST_STOI_ERROR_OK = 0,
+  // Not enough STFT frames to compute the measure after removing silent frames
+  FAST_STOI_ERROR_NOT_ENOUGH_FRAMES = 1,
+  // A required pointer is null
+  FAST_STOI_ERROR_NULL_POINTER = 2,
+  // Invalid argument, such as a zero sampling frequency
+  FAST_STOI_ERROR_INVALID_ARGUMENT = 3,
+} FastStoiError;
+
+// Reusable STOI engine
+typedef struct FastStoiEngine FastStoiEngine;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Human-readable message of an error code, as a static null-terminated string
+const char *fast_stoi_error_message(enum FastStoiError error);
+
+// Compute the classic STOI measure between a clean signal `x` and a processed signal `y`
+// of `len` samples at `fs_sig` Hz, and write it to `score`.
+//
+// # Safety
+// `x` and `y` must point to `len` floats, and `score` to a writable float.
+enum FastStoiError fast_stoi_score(const float *x,
+                                   const float *y,
+                                   size_t len,
+                                   size_t fs_sig,
+                                   bool extended,
+                                   float *score);
+
+// Compute the classic STOI measure for a batch of `batch` signal pairs at `fs_sig` Hz.
+// Pair `i` is made of `x[i]` and `y[i]`, of `lengths[i]` samples. Its score is written to
+// `scores[i]`, and its error code to `errors[i]` if `errors` is not null.
+// Pairs that cannot be scored get a NaN score, and `FAST_STOI_ERROR_NOT_ENOUGH_FRAMES` is returned.
+//
+// # Safety
+// `x`, `y`, `lengths` and `scores` must point to `batch` elements, `x[i]` and `y[i]` to
+// `lengths[i]` floats, and `errors` must be null or point to `batch` elements.
+enum FastStoiError fast_stoi_batch_score(const float *const *x,
+                                         const float *const *y,
+                                         const size_t *lengths,
+                                         size_t batch,
+                                         size_t fs_sig,
+                                         bool extended,
+                                         float *scores,
+                                         enum FastStoiError *errors);
+
+// Create an engine for the classic STOI configuration.
+// It must be released with `fast_stoi_engine_destroy`.
+struct FastStoiEngine *fast_stoi_engine_create(void);
+
+// Create an engine for the non-standard wideband STOI at an internal rate `fs` of at least 16kHz,
+// or return null for lower rates. It must be released with `fast_stoi_engine_destroy`.
+struct FastStoiEngine *fast_stoi_engine_create_wideband(size_t fs);
+
+// Release an engine. Null engines are ignored.
+//
+// # Safety
+// `engine` must be null or come from an engine constructor, and must not be used afterwards.
+void fast_stoi_engine_destroy(struct FastStoiEngine *engine);
+
+// Compute the STOI measure with an engine. See `fast_stoi_score`.
+//
+// # Safety
+// `engine` must be a live engine, and the other pointers must be valid as in `fast_stoi_score`.
+enum FastStoiError fast_stoi_engine_score(const struct FastStoiEngine *engine,
+                                          const float *x,
+                                          const float *y,
+                                          size_t len,
+                                          size_t fs_sig,
+                                          bool extended,
+                                          float *score);
+
+// Compute the STOI measure for a batch of signal pairs with an engine.
+// See `fast_stoi_batch_score`.
+//
+// # Safety
+// `engine` must be a live engine, and the other pointers must be valid as in
+// `fast_stoi_batch_score`.
+enum FastStoiError fast_stoi_engine_batch_score(const struct FastStoiEngine *engine,
+                                                const float *const *x,
+                                                const float *const *y,
+                                                const size_t *lengths,
+                                                size_t batch,
+                                                size_t fs_sig,
+                                                bool extended,
+                                                float *scores,
+                                                enum FastStoiError *errors);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FAST_STOI_H */
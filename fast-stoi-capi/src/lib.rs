@@ -0,0 +1,274 @@
+//! C interface to the fast-stoi library
+//!
+//! Functions return a `FastStoiError` code and write their results through output pointers.
+//! Invalid arguments are reported as error codes and never unwind across the C boundary.
+//! Engines hold precomputed windows, FFT plans and band tables, and can be shared between threads.
+
+use std::{
+    ffi::{CString, c_char},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+    sync::LazyLock,
+};
+
+use lib_fast_stoi::{Config, Engine, NotEnoughFramesError, Result};
+
+/// Error codes, mirroring the Rust error type
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FastStoiError {
+    /// Success
+    Ok = 0,
+    /// Not enough STFT frames to compute the measure after removing silent frames
+    NotEnoughFrames = 1,
+    /// A required pointer is null
+    NullPointer = 2,
+    /// Invalid argument, such as a zero sampling frequency
+    InvalidArgument = 3,
+}
+
+/// Reusable STOI engine
+pub struct FastStoiEngine(Engine);
+
+static NOT_ENOUGH_FRAMES: LazyLock<CString> =
+    LazyLock::new(|| CString::new(NotEnoughFramesError.to_string()).unwrap());
+
+/// Human-readable message of an error code, as a static null-terminated string
+#[unsafe(no_mangle)]
+pub extern "C" fn fast_stoi_error_message(error: FastStoiError) -> *const c_char {
+    match error {
+        FastStoiError::Ok => c"Success".as_ptr(),
+        FastStoiError::NotEnoughFrames => NOT_ENOUGH_FRAMES.as_ptr(),
+        FastStoiError::NullPointer => c"A required pointer is null".as_ptr(),
+        FastStoiError::InvalidArgument => c"Invalid argument".as_ptr(),
+    }
+}
+
+/// Compute the classic STOI measure between a clean signal `x` and a processed signal `y`
+/// of `len` samples at `fs_sig` Hz, and write it to `score`.
+///
+/// # Safety
+/// `x` and `y` must point to `len` floats, and `score` to a writable float.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fast_stoi_score(
+    x: *const f32,
+    y: *const f32,
+    len: usize,
+    fs_sig: usize,
+    extended: bool,
+    score: *mut f32,
+) -> FastStoiError {
+    unsafe {
+        score_with(x, y, len, fs_sig, score, |x, y| {
+            lib_fast_stoi::stoi(x, y, fs_sig, extended)
+        })
+    }
+}
+
+/// Compute the classic STOI measure for a batch of `batch` signal pairs at `fs_sig` Hz.
+/// Pair `i` is made of `x[i]` and `y[i]`, of `lengths[i]` samples. Its score is written to
+/// `scores[i]`, and its error code to `errors[i]` if `errors` is not null.
+/// Pairs that cannot be scored get a NaN score, and `FAST_STOI_ERROR_NOT_ENOUGH_FRAMES` is returned.
+///
+/// # Safety
+/// `x`, `y`, `lengths` and `scores` must point to `batch` elements, `x[i]` and `y[i]` to
+/// `lengths[i]` floats, and `errors` must be null or point to `batch` elements.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fast_stoi_batch_score(
+    x: *const *const f32,
+    y: *const *const f32,
+    lengths: *const usize,
+    batch: usize,
+    fs_sig: usize,
+    extended: bool,
+    scores: *mut f32,
+    errors: *mut FastStoiError,
+) -> FastStoiError {
+    unsafe {
+        batch_score_with(x, y, lengths, batch, fs_sig, scores, errors, |x, y| {
+            lib_fast_stoi::batch_stoi(x, y, fs_sig, extended)
+        })
+    }
+}
+
+/// Create an engine for the classic STOI configuration.
+/// It must be released with `fast_stoi_engine_destroy`.
+#[unsafe(no_mangle)]
+pub extern "C" fn fast_stoi_engine_create() -> *mut FastStoiEngine {
+    Box::into_raw(Box::new(FastStoiEngine(Engine::new(Config::classic()))))
+}
+
+/// Create an engine for the non-standard wideband STOI at an internal rate `fs` of at least 16kHz,
+/// or return null for lower rates. It must be released with `fast_stoi_engine_destroy`.
+#[unsafe(no_mangle)]
+pub extern "C" fn fast_stoi_engine_create_wideband(fs: usize) -> *mut FastStoiEngine {
+    if fs < 16_000 {
+        return ptr::null_mut();
+    }
+
+    Box::into_raw(Box::new(FastStoiEngine(Engine::new(Config::wideband(fs)))))
+}
+
+/// Release an engine. Null engines are ignored.
+///
+/// # Safety
+/// `engine` must be null or come from an engine constructor, and must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fast_stoi_engine_destroy(engine: *mut FastStoiEngine) {
+    if !engine.is_null() {
+        drop(unsafe { Box::from_raw(engine) });
+    }
+}
+
+/// Compute the STOI measure with an engine. See `fast_stoi_score`.
+///
+/// # Safety
+/// `engine` must be a live engine, and the other pointers must be valid as in `fast_stoi_score`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fast_stoi_engine_score(
+    engine: *const FastStoiEngine,
+    x: *const f32,
+    y: *const f32,
+    len: usize,
+    fs_sig: usize,
+    extended: bool,
+    score: *mut f32,
+) -> FastStoiError {
+    let Some(engine) = (unsafe { engine.as_ref() }) else {
+        return FastStoiError::NullPointer;
+    };
+
+    unsafe {
+        score_with(x, y, len, fs_sig, score, |x, y| {
+            engine.0.stoi(x, y, fs_sig, extended)
+        })
+    }
+}
+
+/// Compute the STOI measure for a batch of signal pairs with an engine.
+/// See `fast_stoi_batch_score`.
+///
+/// # Safety
+/// `engine` must be a live engine, and the other pointers must be valid as in
+/// `fast_stoi_batch_score`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fast_stoi_engine_batch_score(
+    engine: *const FastStoiEngine,
+    x: *const *const f32,
+    y: *const *const f32,
+    lengths: *const usize,
+    batch: usize,
+    fs_sig: usize,
+    extended: bool,
+    scores: *mut f32,
+    errors: *mut FastStoiError,
+) -> FastStoiError {
+    let Some(engine) = (unsafe { engine.as_ref() }) else {
+        return FastStoiError::NullPointer;
+    };
+
+    unsafe {
+        batch_score_with(x, y, lengths, batch, fs_sig, scores, errors, |x, y| {
+            engine.0.batch_stoi(x, y, fs_sig, extended)
+        })
+    }
+}
+
+/// Score a signal pair, checking the arguments
+unsafe fn score_with(
+    x: *const f32,
+    y: *const f32,
+    len: usize,
+    fs_sig: usize,
+    score: *mut f32,
+    stoi: impl Fn(&[f32], &[f32]) -> Result<f32>,
+) -> FastStoiError {
+    if x.is_null() || y.is_null() || score.is_null() {
+        return FastStoiError::NullPointer;
+    }
+    if fs_sig == 0 {
+        return FastStoiError::InvalidArgument;
+    }
+
+    let (x, y) = unsafe { (slice::from_raw_parts(x, len), slice::from_raw_parts(y, len)) };
+
+    match catch(|| stoi(x, y)) {
+        Ok(Ok(value)) => {
+            unsafe { *score = value };
+            FastStoiError::Ok
+        }
+        Ok(Err(NotEnoughFramesError)) => FastStoiError::NotEnoughFrames,
+        Err(error) => error,
+    }
+}
+
+/// Score a batch of signal pairs, checking the arguments
+#[allow(clippy::too_many_arguments)]
+unsafe fn batch_score_with(
+    x: *const *const f32,
+    y: *const *const f32,
+    lengths: *const usize,
+    batch: usize,
+    fs_sig: usize,
+    scores: *mut f32,
+    errors: *mut FastStoiError,
+    batch_stoi: impl Fn(&[&[f32]], &[&[f32]]) -> Vec<Result<f32>>,
+) -> FastStoiError {
+    if x.is_null() || y.is_null() || lengths.is_null() || scores.is_null() {
+        return FastStoiError::NullPointer;
+    }
+    if fs_sig == 0 {
+        return FastStoiError::InvalidArgument;
+    }
+
+    let (x, y, lengths) = unsafe {
+        (
+            slice::from_raw_parts(x, batch),
+            slice::from_raw_parts(y, batch),
+            slice::from_raw_parts(lengths, batch),
+        )
+    };
+    if x.iter().chain(y).any(|signal| signal.is_null()) {
+        return FastStoiError::NullPointer;
+    }
+
+    let (x, y): (Vec<_>, Vec<_>) = x
+        .iter()
+        .zip(y)
+        .zip(lengths)
+        .map(|((&x, &y), &len)| unsafe {
+            (slice::from_raw_parts(x, len), slice::from_raw_parts(y, len))
+        })
+        .unzip();
+
+    let results = match catch(|| batch_stoi(&x, &y)) {
+        Ok(results) => results,
+        Err(error) => return error,
+    };
+
+    let scores = unsafe { slice::from_raw_parts_mut(scores, batch) };
+    let mut status = FastStoiError::Ok;
+    for (i, result) in results.into_iter().enumerate() {
+        let error = match result {
+            Ok(value) => {
+                scores[i] = value;
+                FastStoiError::Ok
+            }
+            Err(NotEnoughFramesError) => {
+                scores[i] = f32::NAN;
+                status = FastStoiError::NotEnoughFrames;
+                FastStoiError::NotEnoughFrames
+            }
+        };
+        if !errors.is_null() {
+            unsafe { *errors.add(i) = error };
+        }
+    }
+
+    status
+}
+
+/// Run a computation, reporting the assertions on invalid inputs as `InvalidArgument`
+fn catch<T>(f: impl FnOnce() -> T) -> std::result::Result<T, FastStoiError> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|_| FastStoiError::InvalidArgument)
+}
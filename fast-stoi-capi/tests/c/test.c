@@ -0,0 +1,89 @@
+// Exercise the C interface: scores, batches, engines and error codes
+
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "fast_stoi.h"
+
+#define FS 16000
+#define LEN (3 * FS)
+// Too short for a single 30-frame segment
+#define SHORT (FS / 10)
+
+#define CHECK(condition)                                                       \
+  do {                                                                         \
+    if (!(condition)) {                                                        \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__,        \
+              #condition);                                                     \
+      return 1;                                                                \
+    }                                                                          \
+  } while (0)
+
+static float noise(unsigned long long *state) {
+  *state = *state * 6364136223846793005ULL + 1442695040888963407ULL;
+  return (float)(*state >> 40) / (float)(1 << 24) - 0.5f;
+}
+
+int main(void) {
+  static float x[LEN], y[LEN];
+  unsigned long long state = 3;
+
+  for (size_t i = 0; i < LEN; i++) {
+    float t = (float)i / FS;
+    x[i] = 0.3f * fabsf(sinf(4.0f * t) * sinf(1.3f * t)) *
+           (sinf(900.0f * t) + 0.5f * sinf(2300.0f * t));
+    y[i] = x[i] + 0.05f * noise(&state);
+  }
+
+  // Single score
+  float score = 0.0f;
+  CHECK(fast_stoi_score(x, x, LEN, FS, false, &score) == FAST_STOI_ERROR_OK);
+  CHECK(fabsf(score - 1.0f) < 1e-3f);
+  CHECK(fast_stoi_score(x, y, LEN, FS, false, &score) == FAST_STOI_ERROR_OK);
+  CHECK(score > 0.0f && score < 1.0f);
+
+  // Errors
+  CHECK(fast_stoi_score(x, y, SHORT, FS, false, &score) ==
+        FAST_STOI_ERROR_NOT_ENOUGH_FRAMES);
+  CHECK(fast_stoi_score(NULL, y, LEN, FS, false, &score) ==
+        FAST_STOI_ERROR_NULL_POINTER);
+  CHECK(fast_stoi_score(x, y, LEN, 0, false, &score) ==
+        FAST_STOI_ERROR_INVALID_ARGUMENT);
+  CHECK(fast_stoi_error_message(FAST_STOI_ERROR_NOT_ENOUGH_FRAMES)[0] != '\0');
+
+  // Batch, matching single scores
+  const float *xs[3] = {x, x, x};
+  const float *ys[3] = {y, x, y};
+  size_t lengths[3] = {LEN, LEN / 2, SHORT};
+  float scores[3];
+  FastStoiError errors[3];
+  CHECK(fast_stoi_batch_score(xs, ys, lengths, 3, FS, false, scores, errors) ==
+        FAST_STOI_ERROR_NOT_ENOUGH_FRAMES);
+  CHECK(errors[0] == FAST_STOI_ERROR_OK && errors[1] == FAST_STOI_ERROR_OK);
+  CHECK(errors[2] == FAST_STOI_ERROR_NOT_ENOUGH_FRAMES && isnan(scores[2]));
+  CHECK(fast_stoi_score(x, y, LEN, FS, false, &score) == FAST_STOI_ERROR_OK);
+  CHECK(scores[0] == score);
+
+  // Reusable engines
+  FastStoiEngine *engine = fast_stoi_engine_create();
+  CHECK(engine != NULL);
+  float engine_score = 0.0f;
+  CHECK(fast_stoi_engine_score(engine, x, y, LEN, FS, false, &engine_score) ==
+        FAST_STOI_ERROR_OK);
+  CHECK(engine_score == score);
+  CHECK(fast_stoi_engine_batch_score(engine, xs, ys, lengths, 2, FS, true,
+                                     scores, NULL) == FAST_STOI_ERROR_OK);
+  fast_stoi_engine_destroy(engine);
+
+  CHECK(fast_stoi_engine_create_wideband(8000) == NULL);
+  engine = fast_stoi_engine_create_wideband(FS);
+  CHECK(engine != NULL);
+  CHECK(fast_stoi_engine_score(engine, x, y, LEN, FS, false, &engine_score) ==
+        FAST_STOI_ERROR_OK);
+  fast_stoi_engine_destroy(engine);
+  fast_stoi_engine_destroy(NULL);
+
+  printf("ok\n");
+  return 0;
+}
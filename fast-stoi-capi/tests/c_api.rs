@@ -0,0 +1,45 @@
+//! Build and run the C test program against the static library
+
+use std::{env, fs, path::PathBuf, process::Command};
+
+#[test]
+fn header_is_up_to_date() {
+    let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let generated = PathBuf::from(env!("OUT_DIR")).join("fast_stoi.h");
+    let committed = crate_dir.join("include/fast_stoi.h");
+
+    assert!(
+        fs::read_to_string(&generated).unwrap() == fs::read_to_string(&committed).unwrap(),
+        "{} is out of date, copy {} over it",
+        committed.display(),
+        generated.display()
+    );
+}
+
+#[test]
+fn c_program() {
+    let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // The test executable lives in `target/<profile>/deps`, next to the library
+    let exe = env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap().parent().unwrap();
+    let program = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("fast_stoi_test");
+
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(compiler)
+        .arg(crate_dir.join("tests/c/test.c"))
+        .arg("-I")
+        .arg(crate_dir.join("include"))
+        .arg(lib_dir.join("libfast_stoi_capi.a"))
+        .args(["-lm", "-lpthread", "-ldl", "-o"])
+        .arg(&program)
+        .status()
+        .expect("Could not run the C compiler");
+    assert!(status.success(), "The C test program failed to compile");
+
+    let output = Command::new(&program).output().unwrap();
+    assert!(
+        output.status.success(),
+        "The C test program failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
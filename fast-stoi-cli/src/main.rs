@@ -0,0 +1,47 @@
+//! Command line interface to the fast-stoi library
+
+mod score;
+mod serve;
+
+use std::{error::Error, process::ExitCode};
+
+use clap::{Parser, Subcommand};
+
+/// Fast STOI measure between audio files
+#[derive(Parser)]
+#[command(name = "fast-stoi", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Score a processed audio file against its clean reference
+    Score(score::ScoreArgs),
+    /// Score JSON-lines requests read from stdin or a Unix socket
+    Serve(serve::ServeArgs),
+}
+
+fn main() -> ExitCode {
+    let result: Result<(), Box<dyn Error>> = match Cli::parse().command {
+        Command::Score(args) => score::run(args),
+        Command::Serve(args) => serve::run(args),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Decode an audio file and downmix it to mono. Returns the samples and the sampling frequency.
+fn read_mono(path: &std::path::Path) -> Result<(Vec<f32>, usize), lib_fast_stoi::DecodeError> {
+    let audio = lib_fast_stoi::Audio::read(path)?;
+    let mono = audio.multichannel().mean().into_owned();
+
+    Ok((mono, audio.fs))
+}
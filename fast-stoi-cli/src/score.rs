@@ -0,0 +1,116 @@
+//! `score` subcommand
+
+use std::{error::Error, fs, path::PathBuf};
+
+use clap::{Args, ValueEnum};
+use lib_fast_stoi::{FileOptions, Interval, IntervalError};
+
+use crate::read_mono;
+
+#[derive(Args)]
+pub struct ScoreArgs {
+    /// Clean speech file (WAV, FLAC or Ogg Vorbis)
+    clean: PathBuf,
+    /// Processed speech file (WAV, FLAC or Ogg Vorbis)
+    processed: PathBuf,
+    /// Use the extended STOI measure
+    #[arg(long)]
+    extended: bool,
+    /// Score the labelled intervals of a segmentation file instead of the whole files,
+    /// printing per-interval STOI and ESTOI as CSV
+    #[arg(long)]
+    intervals: Option<PathBuf>,
+    /// Segmentation file format (guessed from the extension by default)
+    #[arg(long, value_enum, requires = "intervals")]
+    format: Option<SegmentationFormat>,
+}
+
+/// Maximum length difference in seconds between the clean and processed files
+const MAX_MISMATCH_SECONDS: f64 = 0.02;
+
+/// Segmentation file formats
+#[derive(Clone, Copy, ValueEnum)]
+enum SegmentationFormat {
+    Csv,
+    Rttm,
+    Textgrid,
+}
+
+pub fn run(args: ScoreArgs) -> Result<(), Box<dyn Error>> {
+    let Some(intervals) = &args.intervals else {
+        let options = FileOptions {
+            extended: args.extended,
+            ..Default::default()
+        };
+        let result = lib_fast_stoi::stoi_files(&args.clean, &args.processed, &options)?;
+        println!("{}", result.score?);
+        return Ok(());
+    };
+
+    let format = match args.format {
+        Some(format) => format,
+        None => match intervals.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("rttm") => SegmentationFormat::Rttm,
+            Some(e) if e.eq_ignore_ascii_case("textgrid") => SegmentationFormat::Textgrid,
+            _ => SegmentationFormat::Csv,
+        },
+    };
+    let text = fs::read_to_string(intervals)?;
+    let intervals = match format {
+        SegmentationFormat::Csv => Interval::from_csv(&text),
+        SegmentationFormat::Rttm => Interval::from_rttm(&text),
+        SegmentationFormat::Textgrid => Interval::from_textgrid(&text),
+    }?;
+
+    let (mut x, fs) = read_mono(&args.clean)?;
+    let (mut y, fs_y) = read_mono(&args.processed)?;
+    if fs != fs_y {
+        return Err("clean and processed files must have the same sampling frequency".into());
+    }
+    // Aligned recordings may differ by a few trailing samples, but not by more than 20ms
+    let mismatch = x.len().abs_diff(y.len()) as f64 / fs as f64;
+    if mismatch > MAX_MISMATCH_SECONDS {
+        return Err(format!(
+            "clean and processed files must have the same duration (up to {}ms): \
+             they differ by {mismatch:.3}s",
+            MAX_MISMATCH_SECONDS * 1000.0
+        )
+        .into());
+    }
+    let len = x.len().min(y.len());
+    x.truncate(len);
+    y.truncate(len);
+
+    let scores = lib_fast_stoi::stoi_intervals(&x, &y, fs, &intervals);
+    let format_score = |score: &Result<f32, IntervalError>| match score {
+        Ok(score) => score.to_string(),
+        Err(_) => String::new(),
+    };
+
+    println!("label,start,end,stoi,estoi");
+    for score in &scores.intervals {
+        let interval = &score.interval;
+        println!(
+            "\"{}\",{},{},{},{}",
+            interval.label.replace('"', "\"\""),
+            interval.start,
+            interval.end,
+            format_score(&score.stoi),
+            format_score(&score.estoi),
+        );
+    }
+
+    let skipped = scores.intervals.iter().filter(|s| s.stoi.is_err()).count();
+    if skipped > 0 {
+        eprintln!("Skipped {skipped} too short or silent intervals");
+    }
+    match (scores.stoi, scores.estoi) {
+        (Some(stoi), Some(estoi)) => eprintln!(
+            "Duration-weighted STOI: {stoi}, ESTOI: {estoi} over {:.2}s",
+            scores.scored_duration
+        ),
+        _ => return Err("no interval could be scored".into()),
+    }
+
+    Ok(())
+}
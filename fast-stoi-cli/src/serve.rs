@@ -0,0 +1,552 @@
+//! `serve` subcommand
+//!
+//! Requests are JSON objects, one per line:
+//! `{"id": 1, "clean": "<base64>", "processed": "<base64>", "fs": 16000, "metrics": ["stoi"]}`
+//! with signals as base64 little-endian `f32` samples, or
+//! `{"id": 2, "clean_path": "a.wav", "processed_path": "b.wav"}` with audio files.
+//!
+//! Files may have different sampling frequencies, in which case only `stoi` and `estoi` can be
+//! computed.
+//!
+//! Requests are scored concurrently, so responses are written in completion order:
+//! `{"id": 1, "scores": {"stoi": 0.93}}` or `{"id": 2, "error": "..."}`.
+//! Malformed lines, including invalid UTF-8, get an error response and the server keeps reading.
+//! At most one request per scoring thread is in flight: the next lines are read once a thread
+//! is available.
+//! The engine and the resampling filters are cached for the lifetime of the server.
+
+use std::{
+    error::Error,
+    io::{self, BufRead, BufReader, Write},
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{Arc, Mutex, mpsc},
+    thread,
+};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use clap::Args;
+use lib_fast_stoi::{CropPolicy, Metric, Scores};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
+use crate::read_mono;
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Listen on a Unix socket instead of reading stdin and writing stdout
+    #[arg(long)]
+    socket: Option<PathBuf>,
+    /// Amount of scoring threads (all cores by default)
+    #[arg(long)]
+    threads: Option<usize>,
+}
+
+/// Scoring request
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Request {
+    /// Identifier echoed in the response
+    #[serde(default)]
+    id: Value,
+    /// Clean signal as base64 little-endian `f32` samples
+    clean: Option<String>,
+    /// Processed signal as base64 little-endian `f32` samples
+    processed: Option<String>,
+    /// Sampling frequency of inline signals
+    fs: Option<usize>,
+    /// Clean audio file
+    clean_path: Option<PathBuf>,
+    /// Processed audio file
+    processed_path: Option<PathBuf>,
+    /// Metrics to compute: `stoi`, `estoi`, `si_sdr` and `fw_seg_snr`
+    #[serde(default = "default_metrics")]
+    metrics: Vec<String>,
+}
+
+fn default_metrics() -> Vec<String> {
+    vec!["stoi".to_string()]
+}
+
+pub fn run(args: ServeArgs) -> Result<(), Box<dyn Error>> {
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+
+    match args.socket {
+        None => serve(io::stdin().lock(), io::stdout()),
+        Some(path) => listen(path),
+    }
+}
+
+/// Accept connections on a Unix socket, serving each one on its own thread
+#[cfg(unix)]
+fn listen(path: PathBuf) -> Result<(), Box<dyn Error>> {
+    use std::os::unix::{fs::FileTypeExt, net::UnixListener};
+
+    // Remove the socket left by a previous server
+    if std::fs::metadata(&path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        std::fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let reader = BufReader::new(stream.try_clone()?);
+        thread::spawn(move || {
+            if let Err(err) = serve(reader, stream) {
+                eprintln!("error: {err}");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn listen(_path: PathBuf) -> Result<(), Box<dyn Error>> {
+    Err("Unix sockets are not supported on this platform".into())
+}
+
+/// Score the requests of a stream, writing each response as soon as it is ready
+fn serve(
+    reader: impl BufRead,
+    mut writer: impl Write + Send + 'static,
+) -> Result<(), Box<dyn Error>> {
+    let (sender, receiver) = mpsc::channel::<String>();
+    // One permit per scoring thread, returned once the response has been sent
+    let (acquire, release) = mpsc::sync_channel::<()>(rayon::current_num_threads());
+    let release = Arc::new(Mutex::new(release));
+
+    let writer = thread::spawn(move || -> io::Result<()> {
+        for response in receiver {
+            writeln!(writer, "{response}")?;
+            writer.flush()?;
+        }
+        Ok(())
+    });
+
+    // Lines are read as bytes so that invalid UTF-8 is answered as a malformed request
+    let mut result = Ok(());
+    for line in reader.split(b'\n') {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                result = Err(err.into());
+                break;
+            }
+        };
+        if line.trim_ascii().is_empty() {
+            continue;
+        }
+
+        // Wait for a scoring thread to be available
+        acquire
+            .send(())
+            .expect("Permits are released by the scoring threads");
+
+        let sender = sender.clone();
+        let release = release.clone();
+        rayon::spawn(move || {
+            // The writer only stops early if the client is gone
+            let _ = sender.send(respond(&line));
+            let _ = release.lock().unwrap().recv();
+        });
+    }
+
+    // The writer stops once every pending request has been answered, even if reading failed
+    drop(sender);
+    writer.join().expect("The writer thread panicked")?;
+
+    result
+}
+
+/// Answer a request line
+fn respond(line: &[u8]) -> String {
+    let (id, result) = match serde_json::from_slice::<Request>(line) {
+        Ok(request) => {
+            let id = request.id.clone();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| score(request)))
+                .unwrap_or_else(|_| Err("internal error while scoring".to_string()));
+            (id, result)
+        }
+        Err(err) => {
+            // Echo the identifier of malformed requests when possible
+            let id = serde_json::from_str::<Value>(&String::from_utf8_lossy(line))
+                .ok()
+                .and_then(|value| value.get("id").cloned())
+                .unwrap_or(Value::Null);
+            (id, Err(format!("invalid request: {err}")))
+        }
+    };
+
+    match result {
+        Ok(scores) => json!({ "id": id, "scores": scores }),
+        Err(error) => json!({ "id": id, "error": error }),
+    }
+    .to_string()
+}
+
+/// Compute the requested metrics
+fn score(request: Request) -> Result<Map<String, Value>, String> {
+    let metrics = request
+        .metrics
+        .iter()
+        .map(|metric| match metric.as_str() {
+            "stoi" => Ok(Metric::Stoi),
+            "estoi" => Ok(Metric::Estoi),
+            "si_sdr" => Ok(Metric::SiSdr),
+            "fw_seg_snr" => Ok(Metric::FwSegSnr),
+            _ => Err(format!("unknown metric \"{metric}\"")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (x, fs, y, fs_y) = match request {
+        Request {
+            clean: Some(clean),
+            processed: Some(processed),
+            fs: Some(fs),
+            clean_path: None,
+            processed_path: None,
+            ..
+        } => (decode(&clean)?, fs, decode(&processed)?, fs),
+        Request {
+            clean: None,
+            processed: None,
+            clean_path: Some(clean_path),
+            processed_path: Some(processed_path),
+            ..
+        } => {
+            let (x, y) = rayon::join(|| read_mono(&clean_path), || read_mono(&processed_path));
+            let ((x, fs), (y, fs_y)) = (
+                x.map_err(|err| err.to_string())?,
+                y.map_err(|err| err.to_string())?,
+            );
+            (x, fs, y, fs_y)
+        }
+        _ => {
+            return Err(
+                "expected \"clean\", \"processed\" and \"fs\", or \"clean_path\" and \"processed_path\""
+                    .to_string(),
+            );
+        }
+    };
+
+    if fs == 0 || fs_y == 0 {
+        return Err("the sampling frequency must be positive".to_string());
+    }
+    if fs != fs_y {
+        return score_mixed_rate(&x, fs, &y, fs_y, &metrics);
+    }
+    if x.len() != y.len() {
+        return Err("clean and processed signals must have the same length".to_string());
+    }
+
+    let scores = lib_fast_stoi::evaluate(&x, &y, fs, &metrics).map_err(|err| err.to_string())?;
+    Ok(to_json(&scores))
+}
+
+/// Compute the requested metrics between signals at different sampling frequencies
+fn score_mixed_rate(
+    x: &[f32],
+    fs_x: usize,
+    y: &[f32],
+    fs_y: usize,
+    metrics: &[Metric],
+) -> Result<Map<String, Value>, String> {
+    if metrics
+        .iter()
+        .any(|metric| !matches!(metric, Metric::Stoi | Metric::Estoi))
+    {
+        return Err(
+            "si_sdr and fw_seg_snr need signals at the same sampling frequency".to_string(),
+        );
+    }
+
+    let stoi = |extended: bool| {
+        lib_fast_stoi::stoi_mixed_rate(x, fs_x, y, fs_y, extended, CropPolicy::Shortest)
+            .map_err(|err| err.to_string())
+    };
+    let scores = Scores {
+        stoi: metrics
+            .contains(&Metric::Stoi)
+            .then(|| stoi(false))
+            .transpose()?,
+        estoi: metrics
+            .contains(&Metric::Estoi)
+            .then(|| stoi(true))
+            .transpose()?,
+        ..Default::default()
+    };
+
+    Ok(to_json(&scores))
+}
+
+/// Decode base64 little-endian `f32` samples
+fn decode(signal: &str) -> Result<Vec<f32>, String> {
+    let bytes = STANDARD
+        .decode(signal)
+        .map_err(|err| format!("invalid base64 signal: {err}"))?;
+    if !bytes.len().is_multiple_of(4) {
+        return Err("signals must be made of 4-byte f32 samples".to_string());
+    }
+
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|sample| f32::from_le_bytes(sample.try_into().unwrap()))
+        .collect())
+}
+
+/// Requested scores as a JSON object
+fn to_json(scores: &Scores) -> Map<String, Value> {
+    [
+        ("stoi", scores.stoi),
+        ("estoi", scores.estoi),
+        ("si_sdr", scores.si_sdr),
+        ("fw_seg_snr", scores.fw_seg_snr),
+    ]
+    .into_iter()
+    .filter_map(|(name, score)| score.map(|score| (name.to_string(), json!(score))))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// Writer whose output can be read once the server is done
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Serve the request lines, returning the responses by id
+    fn responses(input: &[u8]) -> Vec<Value> {
+        let output = Output::default();
+        serve(input, output.clone()).unwrap();
+
+        let output = output.0.lock().unwrap();
+        let mut responses = std::str::from_utf8(&output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        responses.sort_by_key(|response| response["id"].as_i64().unwrap_or(-1));
+        responses
+    }
+
+    /// Speech-like deterministic signal: noise modulated at a syllabic rate
+    fn signal(len: usize, fs: usize, seed: u64) -> Vec<f32> {
+        let mut state = seed;
+        (0..len)
+            .map(|i| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                let noise = (state >> 40) as f32 / (1u64 << 23) as f32 - 1.0;
+                let t = i as f32 / fs as f32;
+                noise * (2.0 * std::f32::consts::PI * 4.0 * t).sin().max(0.0)
+            })
+            .collect()
+    }
+
+    fn encode(signal: &[f32]) -> String {
+        STANDARD.encode(
+            signal
+                .iter()
+                .flat_map(|s| s.to_le_bytes())
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn write_wav(path: &Path, samples: &[f32], fs: usize) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: fs as u32,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        samples
+            .iter()
+            .for_each(|&s| writer.write_sample(s).unwrap());
+        writer.finalize().unwrap();
+    }
+
+    fn score_of(response: &Value, metric: &str) -> f32 {
+        response["scores"][metric].as_f64().unwrap() as f32
+    }
+
+    #[test]
+    fn base64_requests() {
+        const FS: usize = 16_000;
+        let x = signal(2 * FS, FS, 1);
+        let y = x
+            .iter()
+            .zip(signal(2 * FS, FS, 2))
+            .map(|(x, n)| x + 0.3 * n)
+            .collect::<Vec<_>>();
+
+        let request = json!({
+            "id": 1,
+            "clean": encode(&x),
+            "processed": encode(&y),
+            "fs": FS,
+            "metrics": ["stoi", "estoi", "si_sdr"],
+        });
+        let default_metrics =
+            json!({ "id": 2, "clean": encode(&x), "processed": encode(&y), "fs": FS });
+        let input = format!("{request}\n\n{default_metrics}\n");
+
+        let [scored, default] = &responses(input.as_bytes())[..] else {
+            panic!("expected two responses");
+        };
+        let expected =
+            lib_fast_stoi::evaluate(&x, &y, FS, &[Metric::Stoi, Metric::Estoi, Metric::SiSdr])
+                .unwrap();
+        assert_eq!(score_of(scored, "stoi"), expected.stoi.unwrap());
+        assert_eq!(score_of(scored, "estoi"), expected.estoi.unwrap());
+        assert_eq!(score_of(scored, "si_sdr"), expected.si_sdr.unwrap());
+        assert!(scored["scores"].get("fw_seg_snr").is_none());
+
+        assert_eq!(default["scores"].as_object().unwrap().len(), 1);
+        assert_eq!(
+            score_of(default, "stoi"),
+            lib_fast_stoi::stoi(&x, &y, FS, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_requests_are_answered() {
+        let x = encode(&signal(16_000, 16_000, 1));
+        let input = [
+            json!({ "id": 1, "clean": 3 }).to_string().into_bytes(),
+            json!({ "id": 2, "clean": x, "processed": x, "fs": 16_000, "metrics": ["pesq"] })
+                .to_string()
+                .into_bytes(),
+            json!({ "id": 3, "clean": x, "processed": x })
+                .to_string()
+                .into_bytes(),
+            b"{\"id\": 4, \"clean\": \"\xff\xfe\"}".to_vec(),
+            b"{not json".to_vec(),
+            json!({ "id": 5, "clean": x, "processed": x, "fs": 16_000 })
+                .to_string()
+                .into_bytes(),
+        ]
+        .join(&b'\n');
+
+        let responses = responses(&input);
+        let errors = responses
+            .iter()
+            .map(|response| {
+                (
+                    response["id"].clone(),
+                    response.get("error").and_then(Value::as_str),
+                )
+            })
+            .collect::<Vec<_>>();
+        let [
+            (Value::Null, Some(not_json)),
+            (_, Some(wrong_type)),
+            (_, Some(unknown_metric)),
+            (_, Some(missing_fs)),
+            (_, Some(not_utf8)),
+            (_, None),
+        ] = &errors[..]
+        else {
+            panic!("unexpected responses {responses:?}");
+        };
+
+        assert_eq!(
+            responses
+                .iter()
+                .map(|r| r["id"].as_i64())
+                .collect::<Vec<_>>(),
+            [None, Some(1), Some(2), Some(3), Some(4), Some(5)]
+        );
+        assert!(not_json.starts_with("invalid request"), "{not_json}");
+        assert!(wrong_type.starts_with("invalid request"), "{wrong_type}");
+        assert_eq!(*unknown_metric, "unknown metric \"pesq\"");
+        assert!(missing_fs.starts_with("expected"), "{missing_fs}");
+        assert!(not_utf8.starts_with("invalid request"), "{not_utf8}");
+        assert_eq!(score_of(&responses[5], "stoi"), 1.0);
+    }
+
+    #[test]
+    fn read_errors_keep_pending_responses() {
+        /// Connection dropped after the first request
+        struct Reset;
+
+        impl io::Read for Reset {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::ConnectionReset.into())
+            }
+        }
+
+        let x = encode(&signal(16_000, 16_000, 1));
+        let request = json!({ "id": 1, "clean": x, "processed": x, "fs": 16_000 });
+        let input = format!("{request}\n");
+        let reader = BufReader::new(io::Read::chain(input.as_bytes(), Reset));
+
+        let output = Output::default();
+        assert!(serve(reader, output.clone()).is_err());
+
+        let output = output.0.lock().unwrap();
+        let response = serde_json::from_slice::<Value>(&output).unwrap();
+        assert_eq!(response["id"], 1);
+        assert_eq!(score_of(&response, "stoi"), 1.0);
+    }
+
+    #[test]
+    fn mixed_rate_files() {
+        let dir = std::env::temp_dir().join(format!("fast-stoi-serve-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (clean, processed) = (dir.join("clean.wav"), dir.join("processed.wav"));
+
+        let x = signal(2 * 48_000, 48_000, 1);
+        let y = lib_fast_stoi::Resampler::new(48_000, 16_000).process(&x);
+        write_wav(&clean, &x, 48_000);
+        write_wav(&processed, &y, 16_000);
+
+        let request = |id: usize, metrics: &[&str]| json!({ "id": id, "clean_path": clean, "processed_path": processed, "metrics": metrics });
+        let input = format!(
+            "{}\n{}\n",
+            request(1, &["stoi", "estoi"]),
+            request(2, &["stoi", "si_sdr"])
+        );
+
+        let [scored, rejected] = &responses(input.as_bytes())[..] else {
+            panic!("expected two responses");
+        };
+        for (metric, extended) in [("stoi", false), ("estoi", true)] {
+            let expected = lib_fast_stoi::stoi_mixed_rate(
+                &x,
+                48_000,
+                &y,
+                16_000,
+                extended,
+                CropPolicy::Shortest,
+            )
+            .unwrap();
+            assert_eq!(score_of(scored, metric), expected);
+        }
+        assert!(
+            rejected["error"]
+                .as_str()
+                .unwrap()
+                .contains("same sampling frequency")
+        );
+    }
+}
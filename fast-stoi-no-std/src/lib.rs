@@ -0,0 +1,46 @@
+//! `no_std` test harness for the fast-stoi library
+//!
+//! This crate builds fast-stoi without its `std` feature, the way firmware would:
+//! the engine and the resampler are constructed explicitly once, then reused for every score.
+//! The integration tests run it on the host. Run them alone so that the other workspace
+//! members do not enable the `std` feature of fast-stoi:
+//!
+//! ```bash
+//! cargo test -p fast-stoi-no-std
+//! ```
+
+#![no_std]
+
+extern crate alloc;
+
+use lib_fast_stoi::{Config, Engine, Resampler, Result};
+
+/// Classic STOI scorer for signals at a fixed sampling frequency
+pub struct Scorer {
+    engine: Engine,
+    /// Resampler to the internal rate, if the signals are not already at that rate
+    resampler: Option<Resampler>,
+}
+
+impl Scorer {
+    /// Create a scorer for signals at `fs_sig` Hz
+    pub fn new(fs_sig: usize) -> Self {
+        let engine = Engine::new(Config::classic());
+        let resampler = (fs_sig != engine.config().fs()).then(|| engine.resampler(fs_sig));
+
+        Self { engine, resampler }
+    }
+
+    /// Compute the STOI measure between a clean signal `x` and a processed signal `y`
+    pub fn score(&self, x: &[f32], y: &[f32], extended: bool) -> Result<f32> {
+        let fs = self.engine.config().fs();
+
+        match &self.resampler {
+            Some(resampler) => {
+                let (x, y) = (resampler.process(x), resampler.process(y));
+                self.engine.stoi(&x, &y, fs, extended)
+            }
+            None => self.engine.stoi(x, y, fs, extended),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Score synthetic signals with the `no_std` build on the host
+
+use fast_stoi_no_std::Scorer;
+
+const FS: usize = 16_000;
+
+/// Deterministic white noise in [-1, 1]
+fn noise(len: usize, seed: u64) -> Vec<f32> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (state >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+        })
+        .collect()
+}
+
+/// Speech-like signal: noise modulated at a syllabic rate, with pauses
+fn speech(seconds: usize, fs: usize) -> Vec<f32> {
+    noise(seconds * fs, 1)
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let t = i as f32 / fs as f32;
+            let syllables = (2.0 * std::f32::consts::PI * 4.0 * t).sin().max(0.0);
+            let pause = if (t % 1.5) < 1.2 { 1.0 } else { 0.01 };
+            x * syllables * pause
+        })
+        .collect()
+}
+
+/// Mix a signal with noise scaled by `gain`
+fn degrade(x: &[f32], gain: f32) -> Vec<f32> {
+    x.iter()
+        .zip(noise(x.len(), 2))
+        .map(|(x, n)| x + gain * n)
+        .collect()
+}
+
+#[test]
+fn identical_signals() {
+    let x = speech(3, FS);
+    let scorer = Scorer::new(FS);
+
+    assert!(scorer.score(&x, &x, false).unwrap() > 0.99);
+    assert!(scorer.score(&x, &x, true).unwrap() > 0.99);
+}
+
+/// Scores of the `std` build (`realfft` and `windowfunctions`) with light and heavy noise
+const STD_SCORES: [(bool, f32, f32); 2] =
+    [(false, 0.9983679, 0.8608164), (true, 0.9807714, 0.5426491)];
+
+#[test]
+fn noise_lowers_the_score() {
+    let x = speech(3, FS);
+    let scorer = Scorer::new(FS);
+
+    for (extended, std_light, std_heavy) in STD_SCORES {
+        let light = scorer.score(&x, &degrade(&x, 0.05), extended).unwrap();
+        let heavy = scorer.score(&x, &degrade(&x, 0.5), extended).unwrap();
+        assert!(heavy < light && light < 1.0, "{heavy} < {light} < 1");
+
+        // The libm math, FFT and windows of the no_std build match the std build
+        assert!((light - std_light).abs() < 1e-4, "{light} != {std_light}");
+        assert!((heavy - std_heavy).abs() < 1e-4, "{heavy} != {std_heavy}");
+    }
+}
+
+#[test]
+fn internal_rate_signals() {
+    // Signals at the internal rate are not resampled
+    let x = speech(3, 10_000);
+    let y = degrade(&x, 0.1);
+
+    let score = Scorer::new(10_000).score(&x, &y, false).unwrap();
+    assert!(score > 0.0 && score < 1.0);
+}
+
+#[test]
+fn short_signals() {
+    let x = speech(3, FS);
+    let x = &x[..FS / 10];
+
+    assert!(Scorer::new(FS).score(x, x, false).is_err());
+}
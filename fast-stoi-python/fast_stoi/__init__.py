@@ -1,13 +1,37 @@
 """Fast STOI implementation."""
 
+import os
 import warnings
+from typing import Optional, Sequence, Tuple, Union
 
 import numpy as np
 
+from .fast_stoi import fit_mapping as fit_mapping_internal  # type: ignore
+from .fast_stoi import multichannel_stoi as multichannel_stoi_internal  # type: ignore
+from .fast_stoi import ncm as ncm_internal  # type: ignore
+from .fast_stoi import par_pit_stoi as par_pit_stoi_internal  # type: ignore
+from .fast_stoi import par_siib as par_siib_internal  # type: ignore
 from .fast_stoi import par_stoi as par_stoi_internal  # type: ignore
+from .fast_stoi import par_vocoded_stoi as par_vocoded_stoi_internal  # type: ignore
+from .fast_stoi import pit_stoi as pit_stoi_internal  # type: ignore
+from .fast_stoi import predict_intelligibility as predict_intelligibility_internal  # type: ignore
+from .fast_stoi import siib as siib_internal  # type: ignore
+from .fast_stoi import Vocoder  # type: ignore
 from .fast_stoi import stoi as stoi_internal  # type: ignore
+from .fast_stoi import stoi_files as stoi_files_internal  # type: ignore
+from .fast_stoi import vocoded_stoi as vocoded_stoi_internal  # type: ignore
 
-__all__ = ["stoi", "STOI"]
+__all__ = [
+    "stoi",
+    "stoi_multichannel",
+    "stoi_files",
+    "pit_stoi",
+    "siib",
+    "ncm",
+    "Mapping",
+    "Vocoder",
+    "STOI",
+]
 
 ERROR_MESSAGE = (
     "Not enough STFT frames to compute intermediate "
@@ -16,7 +40,13 @@ ERROR_MESSAGE = (
 )
 
 
-def stoi(x: np.ndarray, y: np.ndarray, fs_sig: int, extended=False) -> np.ndarray:
+def stoi(
+    x: np.ndarray,
+    y: np.ndarray,
+    fs_sig: int,
+    extended=False,
+    vocoder: Optional[Vocoder] = None,
+) -> np.ndarray:
     """
     Compute the Short-Time Objective Intelligibility (STOI) measure between two signals.
     Args:
@@ -24,6 +54,8 @@ def stoi(x: np.ndarray, y: np.ndarray, fs_sig: int, extended=False) -> np.ndarra
         y: Processed speech signal (1D array).
         fs_sig: Sampling frequency of the signals (must be positive).
         extended: Whether to use the extended STOI measure (default: False).
+        vocoder: Cochlear implant vocoder applied to the processed signal before scoring
+            (non-standard, default: None).
     """
 
     assert fs_sig > 0, "fs_sig must be positive"
@@ -36,13 +68,19 @@ def stoi(x: np.ndarray, y: np.ndarray, fs_sig: int, extended=False) -> np.ndarra
         y = y.astype(np.float32)
 
     if len(x.shape) == 2:
-        out = par_stoi_internal(x, y, fs_sig, extended)
+        if vocoder is None:
+            out = par_stoi_internal(x, y, fs_sig, extended)
+        else:
+            out = par_vocoded_stoi_internal(x, y, fs_sig, extended, vocoder)
         if np.any(out == 1e-5):
             warnings.warn(ERROR_MESSAGE)
         return out
 
     try:
-        out = stoi_internal(x, y, fs_sig, extended)
+        if vocoder is None:
+            out = stoi_internal(x, y, fs_sig, extended)
+        else:
+            out = vocoded_stoi_internal(x, y, fs_sig, extended, vocoder)
     except Warning:
         warnings.warn(ERROR_MESSAGE)
         out = 1e-5
@@ -50,6 +88,248 @@ def stoi(x: np.ndarray, y: np.ndarray, fs_sig: int, extended=False) -> np.ndarra
     return np.array(out)
 
 
+def stoi_multichannel(
+    x: np.ndarray,
+    y: np.ndarray,
+    fs_sig: int,
+    extended=False,
+    policy: str = "per_channel",
+    channel: Optional[int] = None,
+) -> Tuple[np.ndarray, np.ndarray, Optional[int]]:
+    """
+    Compute the STOI measure between multichannel signals with an explicit channel policy.
+    Unlike `stoi`, rows of 2D arrays are channels of the same recording, not batch items.
+    Returns the channel scores, the score selected by the policy, and the selected channel
+    for the "channel" and "best" policies.
+    Args:
+        x: Clean speech signal (2D array of shape (channels, samples)).
+            A single clean channel is the reference of every processed channel,
+            as is the chosen clean channel of the "reference" policy.
+        y: Processed speech signal (2D array of shape (channels, samples)).
+        fs_sig: Sampling frequency of the signals (must be positive).
+        extended: Whether to use the extended STOI measure (default: False).
+        policy: "per_channel" (mean of the channel scores), "channel" (a single channel),
+            "mean" (downmix), "best" (best channel) or "reference" (mean of the scores of
+            every channel against one clean channel) (default: "per_channel").
+        channel: Channel scored with the "channel" policy, or clean reference channel
+            of the "reference" policy.
+    """
+
+    assert fs_sig > 0, "fs_sig must be positive"
+    assert x.ndim == 2 and y.ndim == 2, "Arrays must be 2D"
+    assert x.shape[1] == y.shape[1], "x and y must have the same amount of samples"
+    if policy != "reference":
+        assert x.shape[0] in (1, y.shape[0]), "x must have one channel or as many as y"
+
+    x = np.ascontiguousarray(x, dtype=np.float32)
+    y = np.ascontiguousarray(y, dtype=np.float32)
+
+    try:
+        scores, out, selected = multichannel_stoi_internal(
+            x, y, fs_sig, extended, policy, channel
+        )
+    except Warning:
+        warnings.warn(ERROR_MESSAGE)
+        scores, out, selected = np.full(y.shape[0], 1e-5, dtype=np.float32), 1e-5, None
+
+    return scores, np.array(out), selected
+
+
+def stoi_files(
+    ref_paths: Union[str, Sequence[str]],
+    deg_paths: Union[str, Sequence[str]],
+    extended=False,
+    policy: str = "mean",
+    channel: Optional[int] = None,
+) -> np.ndarray:
+    """
+    Compute the STOI measure between clean and processed audio files (WAV, FLAC, Ogg Vorbis).
+    Files are decoded in Rust without holding the GIL, and pairs are scored in parallel.
+    Sampling frequencies are read from the files and may differ between clean and processed files.
+    Args:
+        ref_paths: Clean speech file, or list of files.
+        deg_paths: Processed speech file, or list of files (same length as ref_paths).
+        extended: Whether to use the extended STOI measure (default: False).
+        policy: Channel policy of multichannel files, see `stoi_multichannel` (default: "mean").
+        channel: Channel scored with the "channel" policy, or clean reference channel
+            of the "reference" policy.
+    Raises an OSError if a file cannot be decoded, and a ValueError if the channels of a pair
+    of files do not fit the policy or their durations differ by more than 20ms.
+    """
+
+    single = isinstance(ref_paths, (str, os.PathLike))
+    if single:
+        ref_paths, deg_paths = [ref_paths], [deg_paths]
+
+    assert len(ref_paths) == len(deg_paths), "There must be as many clean as processed files"
+
+    out = stoi_files_internal(
+        [os.fspath(p) for p in ref_paths],
+        [os.fspath(p) for p in deg_paths],
+        extended,
+        policy,
+        channel,
+    )
+    if np.any(out == 1e-5):
+        warnings.warn(ERROR_MESSAGE)
+
+    return np.array(out[0]) if single else out
+
+
+SIIB_ERROR_MESSAGE = (
+    "Not enough STFT frames to compute SIIB after removing silent "
+    "frames. Returning NaN. Please check you wav files"
+)
+
+
+def pit_stoi(
+    refs: np.ndarray, ests: np.ndarray, fs_sig: int, extended=False
+) -> Tuple[np.ndarray, np.ndarray]:
+    """
+    Compute the permutation-invariant STOI between reference and estimated sources.
+    Returns the index of the estimate assigned to each reference, and the mean STOI
+    of the assigned pairs.
+    Args:
+        refs: Reference sources (2D array of shape (sources, samples)).
+        ests: Estimated sources in any order (same shape as refs).
+        fs_sig: Sampling frequency of the signals (must be positive).
+        extended: Whether to use the extended STOI measure (default: False).
+    """
+
+    assert fs_sig > 0, "fs_sig must be positive"
+    assert refs.shape == ests.shape, "refs and ests must be of the same shapes"
+    assert len(refs.shape) in (2, 3), "Arrays must be 2D or 3D"
+
+    if refs.dtype != np.float32:
+        refs = refs.astype(np.float32)
+    if ests.dtype != np.float32:
+        ests = ests.astype(np.float32)
+
+    if len(refs.shape) == 3:
+        permutations, means = par_pit_stoi_internal(refs, ests, fs_sig, extended)
+        if np.any(means == 1e-5):
+            warnings.warn(ERROR_MESSAGE)
+        return permutations, means
+
+    try:
+        permutation, mean = pit_stoi_internal(refs, ests, fs_sig, extended)
+    except Warning:
+        warnings.warn(ERROR_MESSAGE)
+        permutation, mean = np.arange(refs.shape[0]), 1e-5
+
+    return permutation, np.array(mean)
+
+
+def siib(x: np.ndarray, y: np.ndarray, fs_sig: int) -> np.ndarray:
+    """
+    Compute the Speech Intelligibility In Bits (SIIB) measure between two signals, in bits/s.
+    Signals should last at least 20 seconds for a reliable estimate.
+    Args:
+        x: Clean speech signal (1D array).
+        y: Processed speech signal (1D array).
+        fs_sig: Sampling frequency of the signals (must be positive).
+    """
+
+    assert fs_sig > 0, "fs_sig must be positive"
+    assert x.shape == y.shape, "x and y must be of the same shapes"
+    assert len(x.shape) <= 2, "Arrays must be 1D or 2D"
+
+    if x.dtype != np.float32:
+        x = x.astype(np.float32)
+    if y.dtype != np.float32:
+        y = y.astype(np.float32)
+
+    if len(x.shape) == 2:
+        out = par_siib_internal(x, y, fs_sig)
+        if np.any(np.isnan(out)):
+            warnings.warn(SIIB_ERROR_MESSAGE)
+        return out
+
+    try:
+        out = siib_internal(x, y, fs_sig)
+    except Warning:
+        warnings.warn(SIIB_ERROR_MESSAGE)
+        out = np.nan
+
+    return np.array(out)
+
+
+def ncm(x: np.ndarray, y: np.ndarray, fs_sig: int) -> np.ndarray:
+    """
+    Compute the Normalized Covariance Measure (NCM) between two signals.
+    Args:
+        x: Clean speech signal (1D array).
+        y: Processed speech signal (1D array).
+        fs_sig: Sampling frequency of the signals (must be positive).
+    """
+
+    assert fs_sig > 0, "fs_sig must be positive"
+    assert x.shape == y.shape, "x and y must be of the same shapes"
+    assert len(x.shape) == 1, "Arrays must be 1D"
+
+    if x.dtype != np.float32:
+        x = x.astype(np.float32)
+    if y.dtype != np.float32:
+        y = y.astype(np.float32)
+
+    try:
+        out = ncm_internal(x, y, fs_sig)
+    except Warning:
+        warnings.warn(ERROR_MESSAGE)
+        out = 1e-5
+
+    return np.array(out)
+
+
+class Mapping:
+    """
+    Logistic mapping 100 / (1 + exp(a * d + b)) from a STOI score d
+    to a predicted word-recognition percentage.
+    Args:
+        a: Slope parameter.
+        b: Offset parameter.
+    """
+
+    def __init__(self, a: float, b: float):
+        self.a = a
+        self.b = b
+
+    @staticmethod
+    def dantale_ii() -> "Mapping":
+        """Danish Dantale II sentences (Kjems et al., 2009), fitted by Taal et al. (2011)."""
+        return Mapping(-17.4906, 9.6921)
+
+    @staticmethod
+    def ieee() -> "Mapping":
+        """English IEEE sentences (Hu & Loizou, 2007), fitted by Taal et al. (2011)."""
+        return Mapping(-13.1903, 6.5293)
+
+    @staticmethod
+    def fit(scores: np.ndarray, intelligibility: np.ndarray) -> "Mapping":
+        """
+        Fit the mapping parameters by least squares.
+        Args:
+            scores: STOI scores (1D array).
+            intelligibility: Measured word-recognition percentages (1D array).
+        """
+        a, b = fit_mapping_internal(
+            np.ascontiguousarray(scores, dtype=np.float32),
+            np.ascontiguousarray(intelligibility, dtype=np.float32),
+        )
+        return Mapping(a, b)
+
+    def predict_intelligibility(self, scores) -> np.ndarray:
+        """
+        Predict word-recognition percentages from STOI scores (scalar or 1D array).
+        """
+        scores = np.ascontiguousarray(scores, dtype=np.float32)
+        out = predict_intelligibility_internal(scores.reshape(-1), self.a, self.b)
+        return out.reshape(scores.shape)
+
+    def __repr__(self) -> str:
+        return f"Mapping(a={self.a}, b={self.b})"
+
+
 try:
     from torch import Tensor, nn, tensor
 
@@ -3,9 +3,14 @@ use pyo3::prelude::*;
 /// Python bindings for fast-stoi
 #[pymodule]
 mod fast_stoi {
-    use numpy::ndarray::parallel::prelude::*;
-    use numpy::{IntoPyArray, PyArray1, PyReadonlyArray1, PyReadonlyArray2};
-    use pyo3::{exceptions::PyWarning, prelude::*};
+    use numpy::{
+        IntoPyArray, PyArray1, PyArray2, PyReadonlyArray1, PyReadonlyArray2, PyReadonlyArray3,
+        ndarray::{Array2, Axis},
+    };
+    use pyo3::{
+        exceptions::{PyOSError, PyValueError, PyWarning},
+        prelude::*,
+    };
 
     #[pyfunction]
     fn stoi(
@@ -36,20 +41,369 @@ mod fast_stoi {
         let x = x.as_array();
         let y = y.as_array();
 
-        x.outer_iter()
-            .into_par_iter()
-            .zip(y.outer_iter().into_par_iter())
-            .map(|(x, y)| {
-                match lib_fast_stoi::stoi(
-                    x.as_slice().expect("x is not contiguous"),
-                    y.as_slice().expect("y is not contiguous"),
-                    fs_sig,
-                    extended,
-                ) {
-                    Ok(value) => value,
-                    Err(_) => 1e-5,
+        let x = x
+            .outer_iter()
+            .map(|x| x.to_slice().expect("x is not contiguous"))
+            .collect::<Vec<_>>();
+        let y = y
+            .outer_iter()
+            .map(|y| y.to_slice().expect("y is not contiguous"))
+            .collect::<Vec<_>>();
+
+        lib_fast_stoi::batch_stoi(&x, &y, fs_sig, extended)
+            .into_iter()
+            .map(|value| value.unwrap_or(1e-5))
+            .collect::<Vec<_>>()
+            .into_pyarray(py)
+    }
+
+    #[pyfunction]
+    #[pyo3(signature = (x, y, fs_sig, extended, policy, channel=None))]
+    fn multichannel_stoi<'py>(
+        py: Python<'py>,
+        x: PyReadonlyArray2<'_, f32>,
+        y: PyReadonlyArray2<'_, f32>,
+        fs_sig: usize,
+        extended: bool,
+        policy: &str,
+        channel: Option<usize>,
+    ) -> PyResult<(Bound<'py, PyArray1<f32>>, f32, Option<usize>)> {
+        let x = x.as_array();
+        let y = y.as_array();
+
+        let policy = match (policy, channel) {
+            ("per_channel", _) => lib_fast_stoi::ChannelPolicy::PerChannel,
+            ("channel", Some(channel)) if channel < y.nrows() => {
+                lib_fast_stoi::ChannelPolicy::Channel(channel)
+            }
+            ("mean", _) => lib_fast_stoi::ChannelPolicy::Mean,
+            ("reference", Some(channel)) if channel < x.nrows() => {
+                lib_fast_stoi::ChannelPolicy::Reference(channel)
+            }
+            ("best", _) => lib_fast_stoi::ChannelPolicy::Best,
+            _ => return Err(PyValueError::new_err("invalid channel policy")),
+        };
+
+        let x = lib_fast_stoi::Multichannel::planar(
+            x.as_slice().expect("x is not contiguous"),
+            x.nrows(),
+        );
+        let y = lib_fast_stoi::Multichannel::planar(
+            y.as_slice().expect("y is not contiguous"),
+            y.nrows(),
+        );
+
+        let result = lib_fast_stoi::stoi_multichannel(&x, &y, fs_sig, extended, policy);
+        let channel_scores = result
+            .channel_scores
+            .into_iter()
+            .map(|value| value.unwrap_or(1e-5))
+            .collect::<Vec<_>>()
+            .into_pyarray(py);
+
+        match result.score {
+            Ok(score) => Ok((channel_scores, score, result.channel)),
+            Err(err) => Err(PyWarning::new_err(err.to_string())),
+        }
+    }
+
+    #[pyfunction]
+    #[pyo3(signature = (ref_paths, deg_paths, extended, policy, channel=None))]
+    fn stoi_files<'py>(
+        py: Python<'py>,
+        ref_paths: Vec<String>,
+        deg_paths: Vec<String>,
+        extended: bool,
+        policy: &str,
+        channel: Option<usize>,
+    ) -> PyResult<Bound<'py, PyArray1<f32>>> {
+        let policy = match (policy, channel) {
+            ("per_channel", _) => lib_fast_stoi::ChannelPolicy::PerChannel,
+            ("channel", Some(channel)) => lib_fast_stoi::ChannelPolicy::Channel(channel),
+            ("mean", _) => lib_fast_stoi::ChannelPolicy::Mean,
+            ("reference", Some(channel)) => lib_fast_stoi::ChannelPolicy::Reference(channel),
+            ("best", _) => lib_fast_stoi::ChannelPolicy::Best,
+            _ => return Err(PyValueError::new_err("invalid channel policy")),
+        };
+        let options = lib_fast_stoi::FileOptions {
+            extended,
+            policy,
+            ..Default::default()
+        };
+
+        let results =
+            py.detach(|| lib_fast_stoi::batch_stoi_files(&ref_paths, &deg_paths, &options));
+
+        results
+            .into_iter()
+            .map(|result| match result {
+                Ok(result) => Ok(result.score.unwrap_or(1e-5)),
+                Err(err @ lib_fast_stoi::FileError::Decode(_)) => {
+                    Err(PyOSError::new_err(err.to_string()))
+                }
+                Err(err @ lib_fast_stoi::FileError::Mismatch { .. }) => {
+                    Err(PyValueError::new_err(err.to_string()))
                 }
             })
+            .collect::<PyResult<Vec<_>>>()
+            .map(|scores| scores.into_pyarray(py))
+    }
+
+    #[pyfunction]
+    fn ncm(
+        x: PyReadonlyArray1<'_, f32>,
+        y: PyReadonlyArray1<'_, f32>,
+        fs_sig: usize,
+    ) -> PyResult<f32> {
+        match lib_fast_stoi::ncm(
+            x.as_slice().expect("x is not contiguous"),
+            y.as_slice().expect("y is not contiguous"),
+            fs_sig,
+        ) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(PyWarning::new_err(err.to_string())),
+        }
+    }
+
+    #[pyfunction]
+    fn siib(
+        x: PyReadonlyArray1<'_, f32>,
+        y: PyReadonlyArray1<'_, f32>,
+        fs_sig: usize,
+    ) -> PyResult<f32> {
+        match lib_fast_stoi::siib(
+            x.as_slice().expect("x is not contiguous"),
+            y.as_slice().expect("y is not contiguous"),
+            fs_sig,
+        ) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(PyWarning::new_err(err.to_string())),
+        }
+    }
+
+    #[pyfunction]
+    fn par_siib<'py>(
+        py: Python<'py>,
+        x: PyReadonlyArray2<'_, f32>,
+        y: PyReadonlyArray2<'_, f32>,
+        fs_sig: usize,
+    ) -> Bound<'py, PyArray1<f32>> {
+        let x = x.as_array();
+        let y = y.as_array();
+
+        let x = x
+            .outer_iter()
+            .map(|x| x.to_slice().expect("x is not contiguous"))
+            .collect::<Vec<_>>();
+        let y = y
+            .outer_iter()
+            .map(|y| y.to_slice().expect("y is not contiguous"))
+            .collect::<Vec<_>>();
+
+        lib_fast_stoi::batch_siib(&x, &y, fs_sig)
+            .into_iter()
+            .map(|value| value.unwrap_or(f32::NAN))
+            .collect::<Vec<_>>()
+            .into_pyarray(py)
+    }
+
+    #[pyfunction]
+    fn pit_stoi<'py>(
+        py: Python<'py>,
+        refs: PyReadonlyArray2<'_, f32>,
+        ests: PyReadonlyArray2<'_, f32>,
+        fs_sig: usize,
+        extended: bool,
+    ) -> PyResult<(Bound<'py, PyArray1<usize>>, f32)> {
+        let refs = refs.as_array();
+        let ests = ests.as_array();
+
+        let refs = refs
+            .outer_iter()
+            .map(|x| x.to_slice().expect("refs is not contiguous"))
+            .collect::<Vec<_>>();
+        let ests = ests
+            .outer_iter()
+            .map(|y| y.to_slice().expect("ests is not contiguous"))
+            .collect::<Vec<_>>();
+
+        match lib_fast_stoi::pit_stoi(&refs, &ests, fs_sig, extended) {
+            Ok(pit) => Ok((pit.permutation.into_pyarray(py), pit.mean)),
+            Err(err) => Err(PyWarning::new_err(err.to_string())),
+        }
+    }
+
+    #[pyfunction]
+    fn par_pit_stoi<'py>(
+        py: Python<'py>,
+        refs: PyReadonlyArray3<'_, f32>,
+        ests: PyReadonlyArray3<'_, f32>,
+        fs_sig: usize,
+        extended: bool,
+    ) -> (Bound<'py, PyArray2<usize>>, Bound<'py, PyArray1<f32>>) {
+        let refs = refs.as_array();
+        let ests = ests.as_array();
+        let (batch, sources) = (refs.shape()[0], refs.shape()[1]);
+
+        let refs = refs
+            .outer_iter()
+            .map(|refs| {
+                (0..sources)
+                    .map(|i| refs.index_axis_move(Axis(0), i))
+                    .map(|x| x.to_slice().expect("refs is not contiguous"))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let ests = ests
+            .outer_iter()
+            .map(|ests| {
+                (0..sources)
+                    .map(|i| ests.index_axis_move(Axis(0), i))
+                    .map(|y| y.to_slice().expect("ests is not contiguous"))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let refs = refs.iter().map(|x| x.as_slice()).collect::<Vec<_>>();
+        let ests = ests.iter().map(|y| y.as_slice()).collect::<Vec<_>>();
+
+        let (permutations, means): (Vec<_>, Vec<_>) =
+            lib_fast_stoi::batch_pit_stoi(&refs, &ests, fs_sig, extended)
+                .into_iter()
+                .map(|pit| match pit {
+                    Ok(pit) => (pit.permutation, pit.mean),
+                    Err(_) => ((0..sources).collect(), 1e-5),
+                })
+                .unzip();
+
+        let permutations = Array2::from_shape_vec((batch, sources), permutations.concat()).unwrap();
+
+        (permutations.into_pyarray(py), means.into_pyarray(py))
+    }
+
+    #[pyfunction]
+    fn fit_mapping(
+        scores: PyReadonlyArray1<'_, f32>,
+        intelligibility: PyReadonlyArray1<'_, f32>,
+    ) -> (f32, f32) {
+        let mapping = lib_fast_stoi::Mapping::fit(
+            scores.as_slice().expect("scores is not contiguous"),
+            intelligibility
+                .as_slice()
+                .expect("intelligibility is not contiguous"),
+        );
+
+        (mapping.a, mapping.b)
+    }
+
+    #[pyfunction]
+    fn predict_intelligibility<'py>(
+        py: Python<'py>,
+        scores: PyReadonlyArray1<'_, f32>,
+        a: f32,
+        b: f32,
+    ) -> Bound<'py, PyArray1<f32>> {
+        lib_fast_stoi::Mapping::new(a, b)
+            .predict_batch(scores.as_slice().expect("scores is not contiguous"))
+            .into_pyarray(py)
+    }
+
+    /// Cochlear implant vocoder simulation
+    #[pyclass(frozen)]
+    struct Vocoder(lib_fast_stoi::Vocoder);
+
+    #[pymethods]
+    impl Vocoder {
+        #[new]
+        #[pyo3(signature = (channels=8, envelope_cutoff=160.0, carrier="noise", min_freq=100.0, max_freq=8000.0, seed=0))]
+        fn new(
+            channels: usize,
+            envelope_cutoff: f32,
+            carrier: &str,
+            min_freq: f32,
+            max_freq: f32,
+            seed: u64,
+        ) -> PyResult<Self> {
+            let carrier = match carrier {
+                "noise" => lib_fast_stoi::Carrier::Noise,
+                "sine" => lib_fast_stoi::Carrier::Sine,
+                _ => return Err(PyValueError::new_err("carrier must be 'noise' or 'sine'")),
+            };
+            if channels == 0 || envelope_cutoff <= 0.0 || !(0.0 < min_freq && min_freq < max_freq) {
+                return Err(PyValueError::new_err("invalid vocoder parameters"));
+            }
+
+            Ok(Self(
+                lib_fast_stoi::Vocoder::new(channels, envelope_cutoff, carrier)
+                    .with_frequency_range(min_freq, max_freq)
+                    .with_seed(seed),
+            ))
+        }
+
+        /// Vocode a signal at sampling frequency fs_sig
+        fn process<'py>(
+            &self,
+            py: Python<'py>,
+            signal: PyReadonlyArray1<'_, f32>,
+            fs_sig: usize,
+        ) -> Bound<'py, PyArray1<f32>> {
+            self.0
+                .process(signal.as_slice().expect("signal is not contiguous"), fs_sig)
+                .into_pyarray(py)
+        }
+    }
+
+    #[pyfunction]
+    fn vocoded_stoi(
+        x: PyReadonlyArray1<'_, f32>,
+        y: PyReadonlyArray1<'_, f32>,
+        fs_sig: usize,
+        extended: bool,
+        vocoder: &Vocoder,
+    ) -> PyResult<f32> {
+        let engine = lib_fast_stoi::Engine::new(
+            lib_fast_stoi::Config::classic().with_vocoder(vocoder.0.clone()),
+        );
+
+        match engine.stoi(
+            x.as_slice().expect("x is not contiguous"),
+            y.as_slice().expect("y is not contiguous"),
+            fs_sig,
+            extended,
+        ) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(PyWarning::new_err(err.to_string())),
+        }
+    }
+
+    #[pyfunction]
+    fn par_vocoded_stoi<'py>(
+        py: Python<'py>,
+        x: PyReadonlyArray2<'_, f32>,
+        y: PyReadonlyArray2<'_, f32>,
+        fs_sig: usize,
+        extended: bool,
+        vocoder: &Vocoder,
+    ) -> Bound<'py, PyArray1<f32>> {
+        let x = x.as_array();
+        let y = y.as_array();
+
+        let x = x
+            .outer_iter()
+            .map(|x| x.to_slice().expect("x is not contiguous"))
+            .collect::<Vec<_>>();
+        let y = y
+            .outer_iter()
+            .map(|y| y.to_slice().expect("y is not contiguous"))
+            .collect::<Vec<_>>();
+
+        let engine = lib_fast_stoi::Engine::new(
+            lib_fast_stoi::Config::classic().with_vocoder(vocoder.0.clone()),
+        );
+
+        engine
+            .batch_stoi(&x, &y, fs_sig, extended)
+            .into_iter()
+            .map(|value| value.unwrap_or(1e-5))
             .collect::<Vec<_>>()
             .into_pyarray(py)
     }
@@ -0,0 +1,36 @@
+import numpy as np
+
+from fast_stoi import Vocoder, stoi
+
+
+def speech(seconds: int, sr: int) -> np.ndarray:
+    """Noise modulated at a syllabic rate, with frequency regions modulated differently"""
+    rng = np.random.default_rng(42)
+    t = np.arange(seconds * sr) / sr
+    x = rng.standard_normal(t.size) * np.maximum(np.sin(2 * np.pi * 4 * t), 0)
+    for k in range(24):
+        freq = 200 * 2 ** (k / 6)
+        rate = 2 + (k * 7 % 11) / 2
+        x += 0.1 * np.sin(2 * np.pi * freq * t) * np.maximum(np.sin(2 * np.pi * rate * t + k), 0)
+    return x.astype(np.float32)
+
+
+def test_output_length():
+    x = speech(1, 16_000)
+    for carrier in ["noise", "sine"]:
+        assert Vocoder(channels=4, carrier=carrier).process(x, 16_000).shape == x.shape
+
+
+def test_seed():
+    x = speech(1, 16_000)
+    a = Vocoder(channels=4, seed=1).process(x, 16_000)
+    b = Vocoder(channels=4, seed=1).process(x, 16_000)
+    c = Vocoder(channels=4, seed=2).process(x, 16_000)
+    assert np.array_equal(a, b)
+    assert not np.array_equal(a, c)
+
+
+def test_stoi_rises_with_channels():
+    x = speech(2, 16_000)
+    scores = [stoi(x, x, 16_000, vocoder=Vocoder(channels=n, seed=42)) for n in [1, 4, 16]]
+    assert scores[0] < scores[1] < scores[2]
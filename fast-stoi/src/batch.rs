@@ -0,0 +1,228 @@
+//! Batch-lane standard STOI kernels scoring several clips side by side
+//!
+//! Per-clip segments only have `SEGMENT_LENGTH` elements, which is too short
+//! to fill SIMD registers. Instead, we lay out one clip per SIMD lane and
+//! process the same segment index of `LANES` clips at once.
+//! Each lane performs exactly the same operations in the same order as
+//! `standard::from_segments`, so that per-clip results stay identical.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use faer::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+use crate::constants::{BETA, SEGMENT_LENGTH};
+
+/// Amount of clips processed side by side (8 f32 lanes fill an AVX2 register)
+pub const LANES: usize = 8;
+
+type Lanes = [f32; LANES];
+
+/// Compute the standard STOI of up to `LANES` clips from their octave segment spectrograms.
+/// The segments have shapes (segment_length, num_segments * num_bands),
+/// and the amount of segments may differ between clips.
+pub fn from_segments(x_segments: &[MatRef<f32>], y_segments: &[MatRef<f32>]) -> Vec<f32> {
+    assert!(
+        x_segments.len() <= LANES,
+        "Too many clips for the batch lanes"
+    );
+
+    #[cfg(all(target_arch = "x86_64", feature = "std"))]
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: the avx2 target feature is available on this cpu
+        return unsafe { from_segments_avx2(x_segments, y_segments) };
+    }
+
+    from_segments_lanes(x_segments, y_segments)
+}
+
+/// AVX2 version of `from_segments_lanes`.
+#[cfg(all(target_arch = "x86_64", feature = "std"))]
+#[target_feature(enable = "avx2")]
+fn from_segments_avx2(x_segments: &[MatRef<f32>], y_segments: &[MatRef<f32>]) -> Vec<f32> {
+    from_segments_lanes(x_segments, y_segments)
+}
+
+#[inline(always)]
+fn from_segments_lanes(x_segments: &[MatRef<f32>], y_segments: &[MatRef<f32>]) -> Vec<f32> {
+    let clip_value = 10.0_f32.powf(-BETA / 20.0);
+    let n_max = x_segments.iter().map(|x| x.ncols()).max().unwrap_or(0);
+
+    let mut similarity = [-0.0; LANES];
+    let mut x_lanes = [[0.0; LANES]; SEGMENT_LENGTH];
+    let mut y_lanes = [[0.0; LANES]; SEGMENT_LENGTH];
+
+    for j in 0..n_max {
+        // Gather the j-th segment of each clip into the lanes
+        let mut active = [false; LANES];
+        for (lane, (x, y)) in x_segments.iter().zip(y_segments).enumerate() {
+            if j >= x.ncols() {
+                continue;
+            }
+            active[lane] = true;
+
+            x.col(j)
+                .iter()
+                .zip(y.col(j).iter())
+                .enumerate()
+                .for_each(|(t, (&x, &y))| {
+                    x_lanes[t][lane] = x;
+                    y_lanes[t][lane] = y;
+                });
+        }
+
+        let s = segment_similarities(&mut x_lanes, &mut y_lanes, clip_value);
+
+        // Aggregate similarities of the clips that still have segments
+        for lane in 0..LANES {
+            if active[lane] {
+                similarity[lane] += s[lane];
+            }
+        }
+    }
+
+    x_segments
+        .iter()
+        .zip(similarity)
+        .map(|(x, similarity)| similarity / x.ncols() as f32)
+        .collect()
+}
+
+/// Compute the similarities between clean and processed segments, one per lane.
+/// Mirrors `standard::segment_similarity`.
+#[inline(always)]
+fn segment_similarities(
+    x: &mut [Lanes; SEGMENT_LENGTH],
+    y: &mut [Lanes; SEGMENT_LENGTH],
+    clip_value: f32,
+) -> Lanes {
+    // Normalize y so that it has the same norm as x
+    // and then clip y
+    let mut x_sq_sum = [0.0; LANES];
+    let mut y_sq_sum = [0.0; LANES];
+    for t in 0..SEGMENT_LENGTH {
+        for l in 0..LANES {
+            x_sq_sum[l] += x[t][l] * x[t][l];
+            y_sq_sum[l] += y[t][l] * y[t][l];
+        }
+    }
+
+    let mut ratio = [0.0; LANES];
+    for l in 0..LANES {
+        let xnorm2 = x_sq_sum[l].sqrt() + f32::EPSILON;
+        let ynorm2 = y_sq_sum[l].sqrt() + f32::EPSILON;
+        ratio[l] = xnorm2 / (ynorm2 + f32::EPSILON);
+    }
+
+    let mut x_sum = [0.0; LANES];
+    let mut y_sum = [0.0; LANES];
+    for t in 0..SEGMENT_LENGTH {
+        for l in 0..LANES {
+            y[t][l] = (y[t][l] * ratio[l]).min(x[t][l] * (1.0 + clip_value));
+            x_sum[l] += x[t][l];
+            y_sum[l] += y[t][l];
+        }
+    }
+
+    // Compute means
+    let mut x_mean = [0.0; LANES];
+    let mut y_mean = [0.0; LANES];
+    for l in 0..LANES {
+        x_mean[l] = x_sum[l] / SEGMENT_LENGTH as f32;
+        y_mean[l] = y_sum[l] / SEGMENT_LENGTH as f32;
+    }
+
+    // Subtract mean and start computing resulting norm
+    // at the same time
+    let mut x_sq_sum = [0.0; LANES];
+    let mut y_sq_sum = [0.0; LANES];
+    for t in 0..SEGMENT_LENGTH {
+        for l in 0..LANES {
+            x[t][l] -= x_mean[l];
+            y[t][l] -= y_mean[l];
+            x_sq_sum[l] += x[t][l].powi(2);
+            y_sq_sum[l] += y[t][l].powi(2);
+        }
+    }
+
+    // Compute pre-normalization similarity
+    let mut s = [0.0; LANES];
+    for t in 0..SEGMENT_LENGTH {
+        for l in 0..LANES {
+            s[l] += x[t][l] * y[t][l];
+        }
+    }
+
+    // Apply normalization
+    for l in 0..LANES {
+        let x_norm = x_sq_sum[l].sqrt() + f32::EPSILON;
+        let y_norm = y_sq_sum[l].sqrt() + f32::EPSILON;
+        s[l] /= x_norm * y_norm;
+    }
+
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constants::NUM_BANDS, standard};
+
+    /// Positive envelope segments with `n` columns, as produced by the octave stage
+    fn segments(n: usize, seed: u64) -> Mat<f32> {
+        let mut state = seed;
+        Mat::from_fn(SEGMENT_LENGTH, n, |_, _| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (state >> 40) as f32 / (1u64 << 24) as f32
+        })
+    }
+
+    type Kernel = fn(&[MatRef<f32>], &[MatRef<f32>]) -> Vec<f32>;
+
+    /// Check a lane kernel against `standard::from_segments` for clips of ragged lengths
+    fn assert_kernel_matches(kernel: Kernel) {
+        let lengths = [3, 1, 7, 2, 5, 4, 6, 2].map(|n| n * NUM_BANDS);
+        let x = lengths
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| segments(n, i as u64))
+            .collect::<Vec<_>>();
+        let y = lengths
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| segments(n, i as u64 + 100))
+            .collect::<Vec<_>>();
+
+        for count in [LANES, 3] {
+            let x_refs = x[..count].iter().map(Mat::as_ref).collect::<Vec<_>>();
+            let y_refs = y[..count].iter().map(Mat::as_ref).collect::<Vec<_>>();
+            let scores = kernel(&x_refs, &y_refs);
+
+            for (i, score) in scores.into_iter().enumerate() {
+                let expected =
+                    standard::from_segments(x[i].clone().as_mut(), y[i].clone().as_mut());
+                assert_eq!(score.to_bits(), expected.to_bits(), "clip {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn fallback_matches_standard() {
+        assert_kernel_matches(from_segments_lanes);
+    }
+
+    #[cfg(all(target_arch = "x86_64", feature = "std"))]
+    #[test]
+    fn avx2_matches_standard() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+        // SAFETY: the avx2 target feature is available on this cpu
+        assert_kernel_matches(|x, y| unsafe { from_segments_avx2(x, y) });
+    }
+}
@@ -0,0 +1,345 @@
+//! Deterministic Binaural STOI (DBSTOI)
+//!
+//! Implementation of the binaural STOI measure from Andersen et al.,
+//! "Refinement and validation of the binaural short time objective intelligibility
+//! measure for spatially diverse conditions" (Speech Communication, 2018).
+//!
+//! Both ears go through the engine resampling, framing, STFT and third octave
+//! band stages. Frames are kept when they are not silent in either clean ear.
+//! An equalization-cancellation (EC) stage then combines the ears
+//! with an interaural time and level compensation, subject to internal jitter.
+//! Instead of sampling the jitter, the expected EC output envelope correlations
+//! are computed in closed form, and the best time and level compensation is
+//! searched on a grid for each band and segment.
+//! A better-ear stage uses the STOI correlation of the ear whose clean envelope varies
+//! the most instead, when it varies more than the clean EC output.
+
+use faer::prelude::*;
+
+use crate::{
+    constants::{PARALLEL_MIN_FRAMES, SEGMENT_LENGTH},
+    engine::Engine,
+    errors::{NotEnoughFramesError, Result},
+    octave, parallel,
+    pipeline::{Segments, Spectrogram},
+    standard, stft,
+};
+
+/// Maximum interaural time compensation in seconds
+const TAU_MAX: f64 = 0.001;
+/// Amount of tested interaural time compensations in [-TAU_MAX, TAU_MAX]
+const NUM_TAUS: usize = 100;
+/// Maximum interaural level compensation in dB
+const GAMMA_MAX: f64 = 20.0;
+/// Amount of tested interaural level compensations in [-GAMMA_MAX, GAMMA_MAX]
+const NUM_GAMMAS: usize = 40;
+
+/// Time jitter standard deviation constant in seconds
+const SIGMA_DELTA_0: f64 = 65e-6;
+/// Level jitter standard deviation constant in dB
+const SIGMA_EPSILON_0: f64 = 1.5;
+/// Level jitter constants for large level compensations (dB and exponent)
+const ALPHA_0: f64 = 13.0;
+const P: f64 = 1.6;
+/// Time jitter constant for large time compensations in seconds
+const TAU_0: f64 = 1.6e-3;
+
+/// Third octave band representation of a binaural signal over the selected frames.
+struct BinauralBands {
+    /// Band powers of the left ear, of shape (num_bands, frames)
+    left: Mat<f64>,
+    /// Band powers of the right ear
+    right: Mat<f64>,
+    /// Band cross powers between the left and right ears
+    cross: Mat<c64>,
+    /// Segments of the left ear band envelopes, as scored by STOI
+    left_segments: Segments,
+    /// Segments of the right ear band envelopes
+    right_segments: Segments,
+}
+
+/// Sufficient statistics of the expected EC output envelope correlation
+/// between two binaural segments `a` and `b` (mean-removed over the segment).
+struct SegmentStats {
+    /// Σ La Lb
+    ll: f64,
+    /// Σ Ra Rb
+    rr: f64,
+    /// Σ La Rb + Ra Lb
+    lr: f64,
+    /// Σ La Cb + Lb Ca
+    lc: c64,
+    /// Σ Ra Cb + Rb Ca
+    rc: c64,
+    /// Re Σ Ca conj(Cb)
+    cc_conj: f64,
+    /// Σ Ca Cb
+    cc: c64,
+}
+
+/// Best EC output correlation of a segment in a band
+struct EcCorrelation {
+    /// Expected correlation between the clean and processed EC outputs
+    correlation: f64,
+    /// Expected variance of the clean EC output envelope
+    power: f64,
+}
+
+/// Compute DBSTOI between binaural clean (`xl`, `xr`) and processed (`yl`, `yr`) signals.
+pub fn dbstoi(
+    engine: &Engine,
+    xl: &[f32],
+    xr: &[f32],
+    yl: &[f32],
+    yr: &[f32],
+    fs_sig: usize,
+) -> Result<f32> {
+    assert!(
+        [xr.len(), yl.len(), yr.len()]
+            .iter()
+            .all(|&l| l == xl.len()),
+        "Input signals must have the same length"
+    );
+
+    let resampled = [
+        engine.resample(xl, fs_sig),
+        engine.resample(xr, fs_sig),
+        engine.resample_processed(yl, fs_sig),
+        engine.resample_processed(yr, fs_sig),
+    ];
+    let [xl, xr, yl, yr] = &resampled;
+
+    // Keep the frames that are non-silent in either clean ear
+    let frames = engine.windows().valid_multichannel_frames(&[xl, xr]);
+
+    if frames.len() < SEGMENT_LENGTH {
+        return Err(NotEnoughFramesError);
+    }
+
+    let (mut x, mut y) = parallel::join(
+        frames.len() >= PARALLEL_MIN_FRAMES,
+        || BinauralBands::new(engine, xl, xr, &frames),
+        || BinauralBands::new(engine, yl, yr, &frames),
+    );
+
+    // Better-ear correlations, with the segment correlation of the standard STOI
+    let better_ear = |x: &mut Segments, y: &mut Segments| {
+        x.segments
+            .col_iter_mut()
+            .zip(y.segments.col_iter_mut())
+            .map(|(x, y)| standard::correlation(x, y) as f64)
+            .collect::<Vec<_>>()
+    };
+    let left = better_ear(&mut x.left_segments, &mut y.left_segments);
+    let right = better_ear(&mut x.right_segments, &mut y.right_segments);
+
+    // Angular center frequencies of the bands
+    let min_freq = engine.config().min_freq() as f64;
+    let num_bands = engine.config().num_bands();
+    let omegas = (0..num_bands)
+        .map(|k| 2.0 * std::f64::consts::PI * min_freq * 2.0_f64.powf(k as f64 / 3.0))
+        .collect::<Vec<_>>();
+
+    let n_segments = frames.len() - SEGMENT_LENGTH + 1;
+    let band_sum = |band: usize| {
+        (0..n_segments)
+            .map(|m| {
+                let xx = SegmentStats::new(&x, &x, band, m);
+                let ec = ec_correlation(&x, &y, &xx, band, m, omegas[band]);
+
+                // Use the better ear when its clean envelope varies more than the EC output
+                let (power, correlation) = if xx.ll > xx.rr {
+                    (xx.ll, left[m * num_bands + band])
+                } else {
+                    (xx.rr, right[m * num_bands + band])
+                };
+                if power > ec.power {
+                    correlation
+                } else {
+                    ec.correlation
+                }
+            })
+            .sum::<f64>()
+    };
+
+    // The grid search is expensive, so bands are always searched in parallel.
+    // Sum the bands in order so that the result stays deterministic
+    #[cfg(feature = "rayon")]
+    let band_sums = {
+        use rayon::prelude::*;
+        (0..num_bands)
+            .into_par_iter()
+            .map(band_sum)
+            .collect::<Vec<_>>()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let band_sums = (0..num_bands).map(band_sum).collect::<Vec<_>>();
+
+    Ok((band_sums.iter().sum::<f64>() / (n_segments * num_bands) as f64) as f32)
+}
+
+impl BinauralBands {
+    /// Run both ears through the STFT and third octave band stages of the engine.
+    fn new(engine: &Engine, left: &[f32], right: &[f32], frames: &[usize]) -> Self {
+        let spectra =
+            |signal| stft::compute_frame_spectra(signal, frames, engine.windows(), engine.r2c());
+        let (left, right) = (spectra(left), spectra(right));
+        let cross = |a: &Mat<c32>, b: &Mat<c32>| {
+            octave::compute_octave_cross_bands(a.as_ref(), b.as_ref(), engine.bands())
+        };
+
+        // Envelopes of each ear, as computed by the STOI stages
+        let segments = |spectra: &Mat<c32>| {
+            let power = Mat::from_fn(spectra.nrows(), spectra.ncols(), |i, j| {
+                let bin = spectra[(i, j)];
+                bin.re.powi(2) + bin.im.powi(2)
+            });
+            let envelopes = engine.octave_envelopes(&Spectrogram::from_power(power));
+            engine
+                .segments(&envelopes)
+                .expect("There are enough frames for a segment")
+        };
+
+        Self {
+            left_segments: segments(&left),
+            right_segments: segments(&right),
+            cross: cross(&left, &right),
+            left: real(cross(&left, &left)),
+            right: real(cross(&right, &right)),
+        }
+    }
+}
+
+/// Real part of band powers
+fn real(power: Mat<c64>) -> Mat<f64> {
+    Mat::from_fn(power.nrows(), power.ncols(), |i, j| power[(i, j)].re)
+}
+
+/// Copy the segment starting at frame `m` of a band and remove its mean.
+fn centered<T>(values: MatRef<T>, band: usize, m: usize) -> Vec<T>
+where
+    T: Copy + std::iter::Sum + std::ops::Sub<Output = T> + std::ops::Div<f64, Output = T>,
+{
+    let segment = values.row(band).subcols(m, SEGMENT_LENGTH);
+    let mean = segment.iter().copied().sum::<T>() / SEGMENT_LENGTH as f64;
+    segment.iter().map(|&v| v - mean).collect()
+}
+
+impl SegmentStats {
+    /// Compute the statistics between the segments starting at frame `m` of `a` and `b` in a band.
+    fn new(a: &BinauralBands, b: &BinauralBands, band: usize, m: usize) -> Self {
+        let la = centered(a.left.as_ref(), band, m);
+        let ra = centered(a.right.as_ref(), band, m);
+        let ca = centered(a.cross.as_ref(), band, m);
+        let lb = centered(b.left.as_ref(), band, m);
+        let rb = centered(b.right.as_ref(), band, m);
+        let cb = centered(b.cross.as_ref(), band, m);
+
+        let mut stats = Self {
+            ll: 0.0,
+            rr: 0.0,
+            lr: 0.0,
+            lc: c64::new(0.0, 0.0),
+            rc: c64::new(0.0, 0.0),
+            cc_conj: 0.0,
+            cc: c64::new(0.0, 0.0),
+        };
+
+        for n in 0..SEGMENT_LENGTH {
+            stats.ll += la[n] * lb[n];
+            stats.rr += ra[n] * rb[n];
+            stats.lr += la[n] * rb[n] + ra[n] * lb[n];
+            stats.lc += cb[n] * la[n] + ca[n] * lb[n];
+            stats.rc += cb[n] * ra[n] + ca[n] * rb[n];
+            stats.cc_conj += (ca[n] * cb[n].conj()).re;
+            stats.cc += ca[n] * cb[n];
+        }
+
+        stats
+    }
+
+    /// Parts of the expected EC output envelope correlation that only depend on
+    /// the time compensation `tau`, for a band with angular center frequency `omega`.
+    /// Returns the terms to weight with E[α] and E[1/α], and the constant term.
+    fn time_terms(&self, omega: f64, tau: f64) -> (f64, f64, f64) {
+        let sigma_delta = std::f64::consts::SQRT_2 * SIGMA_DELTA_0 * (1.0 + tau.abs() / TAU_0);
+        let phase = c64::from_polar(1.0, omega * tau);
+
+        // E[exp(j omega delta)] and E[exp(2j omega delta)] for a gaussian time jitter delta
+        let jitter = (-(omega * sigma_delta).powi(2) / 2.0).exp();
+        let jitter_2 = (-2.0 * (omega * sigma_delta).powi(2)).exp();
+
+        (
+            -2.0 * jitter * (self.lc * phase).re,
+            -2.0 * jitter * (self.rc * phase).re,
+            self.lr + 2.0 * self.cc_conj + 2.0 * jitter_2 * (self.cc * phase * phase).re,
+        )
+    }
+}
+
+/// Moments E[α], E[1/α], E[α²] and E[1/α²] of the EC level factor α = 10^((γ + ε) / 20)
+/// for a gaussian level jitter ε.
+fn level_moments(gamma: f64) -> [f64; 4] {
+    let kappa = std::f64::consts::LN_10 / 20.0;
+    let sigma_epsilon =
+        std::f64::consts::SQRT_2 * SIGMA_EPSILON_0 * (1.0 + (gamma.abs() / ALPHA_0).powf(P));
+    let variance = (kappa * sigma_epsilon).powi(2);
+
+    [
+        (kappa * gamma + variance / 2.0).exp(),
+        (-kappa * gamma + variance / 2.0).exp(),
+        (2.0 * kappa * gamma + 2.0 * variance).exp(),
+        (-2.0 * kappa * gamma + 2.0 * variance).exp(),
+    ]
+}
+
+/// Compute the maximum expected EC output envelope correlation over the grid of
+/// time and level compensations for the segment starting at frame `m` in a band.
+/// `xx` are the statistics of the clean segment with itself.
+fn ec_correlation(
+    x: &BinauralBands,
+    y: &BinauralBands,
+    xx: &SegmentStats,
+    band: usize,
+    m: usize,
+    omega: f64,
+) -> EcCorrelation {
+    let xy = SegmentStats::new(x, y, band, m);
+    let yy = SegmentStats::new(y, y, band, m);
+
+    let grid = |i: usize, n: usize, max: f64| -max + 2.0 * max * i as f64 / (n - 1) as f64;
+    let moments = (0..NUM_GAMMAS)
+        .map(|i| level_moments(grid(i, NUM_GAMMAS, GAMMA_MAX)))
+        .collect::<Vec<_>>();
+
+    let mut best = EcCorrelation {
+        correlation: f64::NEG_INFINITY,
+        power: 0.0,
+    };
+
+    for i in 0..NUM_TAUS {
+        let tau = grid(i, NUM_TAUS, TAU_MAX);
+        let terms = [&xy, xx, &yy].map(|stats| stats.time_terms(omega, tau));
+
+        for &[alpha, alpha_inv, alpha_2, alpha_inv_2] in &moments {
+            let [xy_value, xx_value, yy_value] = [(&xy, terms[0]), (xx, terms[1]), (&yy, terms[2])]
+                .map(|(stats, (l, r, constant))| {
+                    alpha_2 * stats.ll
+                        + alpha_inv_2 * stats.rr
+                        + alpha * l
+                        + alpha_inv * r
+                        + constant
+                });
+
+            let correlation = xy_value / ((xx_value * yy_value).max(0.0).sqrt() + f64::EPSILON);
+            if correlation > best.correlation {
+                best = EcCorrelation {
+                    correlation,
+                    power: xx_value,
+                };
+            }
+        }
+    }
+
+    best
+}
@@ -0,0 +1,345 @@
+//! Binary cache of precomputed clean references (`cache` feature)
+//!
+//! A cache file stores the references of a clean corpus, to score new processed signals
+//! against it without resampling and framing the clean signals again. Layout:
+//! - `FSTOIREF` magic bytes
+//! - format version (`u32`, little-endian)
+//! - header length (`u64`, little-endian)
+//! - header: compatibility mode, configuration, and the key, offset and length of each entry
+//! - entries: the references, each encoded separately
+//!
+//! The header and the entries are encoded with `bincode` (variable-length integers).
+//! Caches are memory-mapped when opened, and entries are only decoded when requested.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use bincode::Options;
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+
+use crate::{constants::SEGMENT_LENGTH, engine::Config, pipeline::Reference};
+
+const MAGIC: &[u8; 8] = b"FSTOIREF";
+
+/// Version of the cache format, increased whenever the layout or the encoded types change
+pub const CACHE_VERSION: u32 = 1;
+
+/// Size of the magic bytes, format version and header length
+const PREAMBLE_LENGTH: usize = MAGIC.len() + 4 + 8;
+
+/// Whether scores computed with a configuration are comparable with the classic STOI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compatibility {
+    /// Classic STOI configuration
+    Classic,
+    /// Non-standard configuration (wideband, custom bands, hearing loss or vocoder)
+    NonStandard,
+}
+
+impl Compatibility {
+    /// Compatibility mode of a configuration
+    pub fn of(config: &Config) -> Self {
+        if config.is_classic() {
+            Self::Classic
+        } else {
+            Self::NonStandard
+        }
+    }
+}
+
+/// Error while writing or opening a reference cache
+#[derive(Debug)]
+pub enum CacheError {
+    /// The cache file could not be read or written
+    Io(io::Error),
+    /// The file is not a reference cache, or it is corrupted
+    Format(String),
+    /// The cache was written with another version of the format
+    Version { found: u32, expected: u32 },
+    /// The cache was computed in another compatibility mode than the engine
+    Compatibility {
+        cache: Compatibility,
+        engine: Compatibility,
+    },
+    /// The cache was computed with another configuration than the engine
+    Config {
+        cache: Box<Config>,
+        engine: Box<Config>,
+    },
+}
+
+impl Display for CacheError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Could not access the reference cache: {err}"),
+            Self::Format(message) => write!(f, "Invalid reference cache: {message}"),
+            Self::Version { found, expected } => write!(
+                f,
+                "Reference cache format version {found} is not supported (expected {expected})"
+            ),
+            Self::Compatibility { cache, engine } => write!(
+                f,
+                "Reference cache computed in {cache:?} mode cannot be used in {engine:?} mode"
+            ),
+            Self::Config { cache, engine } => write!(
+                f,
+                "Reference cache computed with {cache:?} cannot be used with {engine:?}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CacheError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<bincode::Error> for CacheError {
+    fn from(err: bincode::Error) -> Self {
+        match *err {
+            bincode::ErrorKind::Io(err) => Self::Io(err),
+            err => Self::Format(err.to_string()),
+        }
+    }
+}
+
+/// Cache header, recording what the references were computed with
+#[derive(Serialize, Deserialize)]
+struct Header {
+    compatibility: Compatibility,
+    config: Config,
+    /// Entries sorted by key
+    entries: Vec<Entry>,
+}
+
+/// Location of an encoded reference, relative to the end of the header
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    key: String,
+    offset: u64,
+    length: u64,
+}
+
+/// Precomputed clean references read from a memory-mapped cache file.
+pub struct ReferenceCache {
+    mmap: Mmap,
+    header: Header,
+    /// Start of the entries in the file
+    data_start: usize,
+}
+
+impl ReferenceCache {
+    /// Configuration the references were computed with
+    pub fn config(&self) -> &Config {
+        &self.header.config
+    }
+
+    /// Compatibility mode the references were computed in
+    pub fn compatibility(&self) -> Compatibility {
+        self.header.compatibility
+    }
+
+    /// Amount of references
+    pub fn len(&self) -> usize {
+        self.header.entries.len()
+    }
+
+    /// Whether the cache has no reference
+    pub fn is_empty(&self) -> bool {
+        self.header.entries.is_empty()
+    }
+
+    /// Keys of the references, in sorted order
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.header.entries.iter().map(|entry| entry.key.as_str())
+    }
+
+    /// Decode the reference stored under `key`, or return `None` if there is none.
+    pub fn get(&self, key: &str) -> Option<Result<Reference, CacheError>> {
+        let index = self
+            .header
+            .entries
+            .binary_search_by(|entry| entry.key.as_str().cmp(key))
+            .ok()?;
+
+        Some(self.decode(&self.header.entries[index]))
+    }
+
+    /// Decode an entry, checking that it matches the configuration
+    fn decode(&self, entry: &Entry) -> Result<Reference, CacheError> {
+        let corrupted = || CacheError::Format(format!("entry \"{}\" is corrupted", entry.key));
+
+        let bytes = usize::try_from(entry.offset)
+            .ok()
+            .zip(usize::try_from(entry.length).ok())
+            .and_then(|(offset, length)| {
+                let start = self.data_start.checked_add(offset)?;
+                self.mmap.get(start..start.checked_add(length)?)
+            })
+            .ok_or_else(corrupted)?;
+
+        let reference: Reference = options(bytes.len()).deserialize(bytes)?;
+        if !is_consistent(&reference, &self.header.config) {
+            return Err(corrupted());
+        }
+
+        Ok(reference)
+    }
+}
+
+/// Write the references of a clean corpus to a cache file, with their keys.
+pub(crate) fn write<K: AsRef<str>>(
+    config: &Config,
+    path: &Path,
+    references: &[(K, Reference)],
+) -> Result<(), CacheError> {
+    let mut references = references
+        .iter()
+        .map(|(key, reference)| (key.as_ref(), reference))
+        .collect::<Vec<_>>();
+    references.sort_by_key(|&(key, _)| key);
+    assert!(
+        references.windows(2).all(|w| w[0].0 != w[1].0),
+        "Reference keys must be unique"
+    );
+    assert!(
+        references
+            .iter()
+            .all(|(_, reference)| is_consistent(reference, config)),
+        "References must be computed with the engine configuration"
+    );
+
+    // Locate the entries before writing them, so that they are encoded straight to the file
+    let mut offset = 0;
+    let entries = references
+        .iter()
+        .map(|&(key, reference)| {
+            let length = options(usize::MAX).serialized_size(reference)?;
+            let entry = Entry {
+                key: key.to_string(),
+                offset,
+                length,
+            };
+            offset += length;
+            Ok(entry)
+        })
+        .collect::<Result<Vec<_>, CacheError>>()?;
+
+    let header = options(usize::MAX).serialize(&Header {
+        compatibility: Compatibility::of(config),
+        config: config.clone(),
+        entries,
+    })?;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&CACHE_VERSION.to_le_bytes())?;
+    writer.write_all(&(header.len() as u64).to_le_bytes())?;
+    writer.write_all(&header)?;
+    for (_, reference) in references {
+        options(usize::MAX).serialize_into(&mut writer, reference)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Open a cache file, rejecting it if it was not computed with the same configuration.
+pub(crate) fn open(config: &Config, path: &Path) -> Result<ReferenceCache, CacheError> {
+    let file = File::open(path)?;
+    // SAFETY: the file is only read, and must not be modified while the cache is open
+    let mmap = unsafe { Mmap::map(&file)? };
+
+    let Some(preamble) = mmap.get(..PREAMBLE_LENGTH) else {
+        return Err(CacheError::Format("file too short".to_string()));
+    };
+    let (magic, preamble) = preamble.split_at(MAGIC.len());
+    let (version, header_length) = preamble.split_at(4);
+
+    if magic != MAGIC {
+        return Err(CacheError::Format(
+            "missing reference cache magic bytes".to_string(),
+        ));
+    }
+    let version = u32::from_le_bytes(version.try_into().unwrap());
+    if version != CACHE_VERSION {
+        return Err(CacheError::Version {
+            found: version,
+            expected: CACHE_VERSION,
+        });
+    }
+
+    let data_start = usize::try_from(u64::from_le_bytes(header_length.try_into().unwrap()))
+        .ok()
+        .and_then(|length| PREAMBLE_LENGTH.checked_add(length))
+        .filter(|&end| end <= mmap.len())
+        .ok_or_else(|| CacheError::Format("truncated header".to_string()))?;
+    let bytes = &mmap[PREAMBLE_LENGTH..data_start];
+    let header: Header = options(bytes.len()).deserialize(bytes)?;
+
+    let compatibility = Compatibility::of(config);
+    if header.compatibility != compatibility {
+        return Err(CacheError::Compatibility {
+            cache: header.compatibility,
+            engine: compatibility,
+        });
+    }
+    if header.config != *config {
+        return Err(CacheError::Config {
+            cache: Box::new(header.config),
+            engine: Box::new(config.clone()),
+        });
+    }
+
+    Ok(ReferenceCache {
+        mmap,
+        header,
+        data_start,
+    })
+}
+
+/// Compact `bincode` encoding, decoding at most `limit` bytes
+fn options(limit: usize) -> impl Options {
+    bincode::DefaultOptions::new().with_limit(limit as u64)
+}
+
+/// Whether the shapes of a reference match a configuration,
+/// so that scoring processed signals against it cannot fail.
+fn is_consistent(reference: &Reference, config: &Config) -> bool {
+    let segments = &reference.segments;
+    let n_frames = reference.frames.len();
+    let resampled_len = if reference.fs_sig == config.fs() {
+        Some(reference.len)
+    } else {
+        (reference.len as u128 * config.fs() as u128)
+            .checked_div(reference.fs_sig as u128)
+            .map(|len| len as usize)
+    };
+
+    n_frames >= SEGMENT_LENGTH
+        && segments.num_bands == config.num_bands()
+        && segments.segments.nrows() == SEGMENT_LENGTH
+        && segments.segments.ncols() == (n_frames - SEGMENT_LENGTH + 1) * config.num_bands()
+        && resampled_len.is_some_and(|len| {
+            reference.frames.starts.iter().all(|&start| {
+                start
+                    .checked_add(config.frame_length())
+                    .is_some_and(|end| end <= len)
+            })
+        })
+}
@@ -16,7 +16,13 @@ pub const FFT_LENGTH: usize = 512;
 pub const FFT_BINS: usize = FFT_LENGTH / 2 + 1;
 
 pub const NUM_BANDS: usize = 15; // Amount of 13 octave band
+pub const MIN_FREQ: f32 = 150.0; // Center frequency of the first 13 octave band
 
 pub const SEGMENT_LENGTH: usize = 30;
 
 pub const BETA: f32 = -15.0; // Lower SDR bound
+
+// Minimum signal length (in samples at FS) above which a single signal
+// is split across threads (20s), so that short clips stay on one thread
+pub const PARALLEL_MIN_LENGTH: usize = 20 * FS;
+pub const PARALLEL_MIN_FRAMES: usize = PARALLEL_MIN_LENGTH / HOP_LENGTH;
@@ -0,0 +1,255 @@
+//! Clock drift estimation and compensation between recordings
+//!
+//! When the processed signal is re-recorded by a device with its own clock, its lag
+//! relative to the clean signal grows linearly over time. The lag is tracked with
+//! normalized cross-correlations of successive blocks, a line is fitted through the
+//! reliable block lags, and the processed signal is resampled along this line.
+
+use std::f32::consts::PI;
+
+use num::complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+
+/// Block duration in seconds
+const BLOCK_SECONDS: f32 = 1.0;
+/// Maximum lag searched in each block, in seconds
+const MAX_LAG_SECONDS: f32 = 0.25;
+/// Minimum normalized cross-correlation peak for a block lag to be used
+const MIN_CORRELATION: f32 = 0.4;
+/// Half-length of the Lanczos interpolation kernel
+const LANCZOS: isize = 16;
+
+/// Clock drift of a processed signal relative to its clean reference.
+///
+/// The lag of the processed signal grows as `offset + ppm * 1e-6 * t` seconds at time `t`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClockDrift {
+    /// Clock skew in parts per million
+    pub ppm: f32,
+    /// Lag at the start of the signals, in seconds
+    pub offset: f32,
+    /// Amount of blocks used for the fit (0 when the drift could not be estimated)
+    pub blocks: usize,
+}
+
+/// STOI after clock drift compensation
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DriftCompensatedStoi {
+    /// STOI of the compensated processed signal
+    pub stoi: f32,
+    /// Estimated clock drift
+    pub drift: ClockDrift,
+}
+
+/// Estimate the clock drift of `y` relative to `x`, both at sampling frequency `fs`.
+pub(crate) fn estimate(x: &[f32], y: &[f32], fs: usize) -> ClockDrift {
+    let block = (BLOCK_SECONDS * fs as f32) as usize;
+    let max_lag = (MAX_LAG_SECONDS * fs as f32) as usize;
+    let fft_length = (block + 2 * max_lag).next_power_of_two();
+    let mut planner = RealFftPlanner::<f32>::new();
+    let r2c = planner.plan_fft_forward(fft_length);
+    let c2r = planner.plan_fft_inverse(fft_length);
+
+    let starts = (0..x.len().saturating_sub(block - 1))
+        .step_by(block)
+        .collect::<Vec<_>>();
+
+    let block_lag = |start: usize| {
+        block_lag(x, y, start, block, max_lag, r2c.as_ref(), c2r.as_ref())
+            .map(|lag| ((start + block / 2) as f64, lag as f64))
+    };
+
+    #[cfg(feature = "rayon")]
+    let lags = {
+        use rayon::prelude::*;
+
+        starts
+            .par_iter()
+            .filter_map(|&start| block_lag(start))
+            .collect::<Vec<_>>()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let lags = starts
+        .iter()
+        .filter_map(|&start| block_lag(start))
+        .collect::<Vec<_>>();
+
+    let Some((offset, skew)) = fit_line(&lags) else {
+        return ClockDrift::default();
+    };
+
+    // Refit without outliers, such as blocks locked on a pitch period
+    let residuals = lags
+        .iter()
+        .map(|&(t, lag)| (lag - offset - skew * t).abs())
+        .collect::<Vec<_>>();
+    let threshold = {
+        let mut sorted = residuals.clone();
+        sorted.sort_by(f64::total_cmp);
+        (3.0 * sorted[sorted.len() / 2]).max(1.0)
+    };
+    let inliers = lags
+        .iter()
+        .zip(&residuals)
+        .filter(|(_, residual)| **residual <= threshold)
+        .map(|(&point, _)| point)
+        .collect::<Vec<_>>();
+    let (offset, skew) = fit_line(&inliers).unwrap_or((offset, skew));
+
+    ClockDrift {
+        ppm: (skew * 1e6) as f32,
+        offset: (offset / fs as f64) as f32,
+        blocks: inliers.len(),
+    }
+}
+
+/// Resample `y` at sampling frequency `fs` along the estimated lag, so that it lines up with
+/// the clean signal over its whole duration (constant offset included).
+pub(crate) fn compensate(y: &[f32], drift: &ClockDrift, fs: usize) -> Vec<f32> {
+    if drift.blocks == 0 {
+        return y.to_vec();
+    }
+
+    let offset = drift.offset as f64 * fs as f64;
+    let skew = drift.ppm as f64 * 1e-6;
+
+    (0..y.len())
+        .map(|n| {
+            let position = n as f64 + offset + skew * n as f64;
+            let center = position.floor() as isize;
+            let fraction = (position - center as f64) as f32;
+
+            (center - LANCZOS + 1..=center + LANCZOS)
+                .filter(|&i| i >= 0 && (i as usize) < y.len())
+                .map(|i| y[i as usize] * lanczos(i as f32 - center as f32 - fraction))
+                .sum()
+        })
+        .collect()
+}
+
+/// Lag of `y` relative to the block of `x` starting at `start`, in fractional samples,
+/// or `None` if the block is silent or its correlation peak is not reliable.
+fn block_lag(
+    x: &[f32],
+    y: &[f32],
+    start: usize,
+    block: usize,
+    max_lag: usize,
+    r2c: &dyn RealToComplex<f32>,
+    c2r: &dyn ComplexToReal<f32>,
+) -> Option<f32> {
+    let x_block = &x[start..start + block];
+    let x_energy = x_block.iter().map(|v| v * v).sum::<f32>();
+    if x_energy <= f32::EPSILON {
+        return None;
+    }
+
+    // Search window of y, zero-padded beyond the signal
+    let y_window = (0..block + 2 * max_lag)
+        .map(|i| {
+            (start + i)
+                .checked_sub(max_lag)
+                .and_then(|j| y.get(j))
+                .copied()
+                .unwrap_or(0.0)
+        })
+        .collect::<Vec<_>>();
+
+    // Circular cross-correlation without wrap-around for lags in [-max_lag, max_lag]
+    let mut x_input = r2c.make_input_vec();
+    let mut y_input = r2c.make_input_vec();
+    x_input[..block].copy_from_slice(x_block);
+    y_input[..y_window.len()].copy_from_slice(&y_window);
+    let mut x_spectrum = r2c.make_output_vec();
+    let mut y_spectrum = r2c.make_output_vec();
+    r2c.process(&mut x_input, &mut x_spectrum).unwrap();
+    r2c.process(&mut y_input, &mut y_spectrum).unwrap();
+
+    let mut product = x_spectrum
+        .iter()
+        .zip(&y_spectrum)
+        .map(|(x, y)| x.conj() * y)
+        .collect::<Vec<Complex<f32>>>();
+    product[0].im = 0.0;
+    let last = product.len() - 1;
+    product[last].im = 0.0;
+    let mut correlation = c2r.make_output_vec();
+    c2r.process(&mut product, &mut correlation).unwrap();
+
+    // Normalize by the energies of x and of each y segment
+    let mut cumulative = vec![0.0_f64; y_window.len() + 1];
+    for (i, v) in y_window.iter().enumerate() {
+        cumulative[i + 1] = cumulative[i] + (v * v) as f64;
+    }
+    let normalized = (0..=2 * max_lag)
+        .map(|k| {
+            let y_energy = (cumulative[k + block] - cumulative[k]) as f32;
+            let norm = (x_energy * y_energy.max(0.0)).sqrt() * r2c.len() as f32;
+            if norm > f32::EPSILON {
+                correlation[k] / norm
+            } else {
+                0.0
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let (peak, &value) = normalized
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))?;
+    if value < MIN_CORRELATION {
+        return None;
+    }
+
+    // Parabolic interpolation around the peak
+    let fraction = if peak > 0 && peak < 2 * max_lag {
+        let (left, right) = (normalized[peak - 1], normalized[peak + 1]);
+        let curvature = left - 2.0 * value + right;
+        if curvature < 0.0 {
+            0.5 * (left - right) / curvature
+        } else {
+            0.0
+        }
+    } else {
+        0.0
+    };
+
+    Some(peak as f32 + fraction - max_lag as f32)
+}
+
+/// Least squares fit of `lag = offset + skew * t`, if there are at least two points
+fn fit_line(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let t_mean = points.iter().map(|&(t, _)| t).sum::<f64>() / n;
+    let lag_mean = points.iter().map(|&(_, lag)| lag).sum::<f64>() / n;
+    let covariance = points
+        .iter()
+        .map(|&(t, lag)| (t - t_mean) * (lag - lag_mean))
+        .sum::<f64>();
+    let variance = points
+        .iter()
+        .map(|&(t, _)| (t - t_mean).powi(2))
+        .sum::<f64>();
+
+    let skew = covariance / variance;
+    Some((lag_mean - skew * t_mean, skew))
+}
+
+/// Lanczos interpolation kernel
+fn lanczos(x: f32) -> f32 {
+    if x == 0.0 {
+        return 1.0;
+    }
+    if x.abs() >= LANCZOS as f32 {
+        return 0.0;
+    }
+
+    let a = LANCZOS as f32;
+    a * (PI * x).sin() * (PI * x / a).sin() / (PI * PI * x * x)
+}
@@ -0,0 +1,248 @@
+//! Dynamic time warping of octave band envelopes
+//!
+//! Time-warped outputs (text-to-speech, voice conversion) do not line up with their reference
+//! frame by frame. Their envelope frames are matched to the reference frames with dynamic
+//! time warping, and each reference frame receives the mean of its matched processed frames.
+//! Frames are compared with the euclidean distance between their log band envelopes,
+//! after removing the mean of each band over the signal to ignore fixed channel gains.
+
+use faer::prelude::*;
+
+/// Envelope floor before log compression
+const LOG_FLOOR: f32 = 1e-8;
+
+/// Steps of a warping path
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Diagonal,
+    Reference,
+    Processed,
+}
+
+/// STOI after dynamic time warping of the processed envelopes
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DtwStoi {
+    /// STOI of the warped processed signal
+    pub stoi: f32,
+    /// Mean frame distance along the warping path (0 for identical envelopes)
+    pub path_cost: f32,
+}
+
+/// Warp processed envelopes `y` of shape (num_bands, y_frames) onto reference envelopes `x`
+/// of shape (num_bands, x_frames). Returns the warped envelopes of shape (num_bands, x_frames)
+/// and the mean frame distance along the warping path.
+///
+/// With a Sakoe-Chiba window of `radius` frames, the path stays within `radius` frames of the
+/// diagonal (scaled to the lengths of both signals), and the memory use is O(x_frames * radius)
+/// instead of O(x_frames * y_frames).
+pub(crate) fn align(x: MatRef<f32>, y: MatRef<f32>, radius: Option<usize>) -> (Mat<f32>, f32) {
+    let (n, m) = (x.ncols(), y.ncols());
+    let bands = x.nrows();
+    let (x_log, y_log) = (normalized_log(x), normalized_log(y));
+
+    let distance = |i: usize, j: usize| {
+        x_log[i * bands..(i + 1) * bands]
+            .iter()
+            .zip(&y_log[j * bands..(j + 1) * bands])
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    };
+
+    // Range of processed frames allowed for each reference frame
+    let window = Window::new(n, m, radius);
+
+    // Accumulated costs are kept for two rows, and steps for the whole window
+    let mut steps = vec![Step::Diagonal; window.size()];
+    let mut previous = vec![f32::INFINITY; m];
+    let mut current = vec![f32::INFINITY; m];
+
+    for i in 0..n {
+        for j in window.columns(i) {
+            let d = distance(i, j);
+            let (cost, step) = if i == 0 && j == 0 {
+                (0.0, Step::Diagonal)
+            } else {
+                let mut best = (f32::INFINITY, Step::Diagonal);
+                if i > 0 && j > 0 && previous[j - 1] < best.0 {
+                    best = (previous[j - 1], Step::Diagonal);
+                }
+                if i > 0 && previous[j] < best.0 {
+                    best = (previous[j], Step::Reference);
+                }
+                if j > 0 && current[j - 1] < best.0 {
+                    best = (current[j - 1], Step::Processed);
+                }
+                best
+            };
+            current[j] = cost + d;
+            steps[window.index(i, j)] = step;
+        }
+        std::mem::swap(&mut previous, &mut current);
+        // Reset the costs of the row before, outside of the next row's window
+        if i > 0 {
+            window
+                .columns(i - 1)
+                .for_each(|j| current[j] = f32::INFINITY);
+        }
+    }
+    let total = previous[m - 1];
+
+    // Backtrack, accumulating the processed frames matched to each reference frame
+    let mut warped = Mat::<f32>::zeros(bands, n);
+    let mut matches = vec![0usize; n];
+    let (mut i, mut j) = (n - 1, m - 1);
+    let mut path_length = 0;
+    loop {
+        let mut column = warped.col_mut(i);
+        column += y.col(j);
+        matches[i] += 1;
+        path_length += 1;
+
+        if i == 0 && j == 0 {
+            break;
+        }
+        match steps[window.index(i, j)] {
+            Step::Diagonal => (i, j) = (i - 1, j - 1),
+            Step::Reference => i -= 1,
+            Step::Processed => j -= 1,
+        }
+    }
+
+    for (i, &count) in matches.iter().enumerate() {
+        warped
+            .col_mut(i)
+            .iter_mut()
+            .for_each(|v| *v /= count as f32);
+    }
+
+    (warped, total / path_length as f32)
+}
+
+/// Processed frames allowed for each reference frame by a Sakoe-Chiba window
+struct Window {
+    /// First allowed processed frame of each reference frame
+    starts: Vec<usize>,
+    /// Offset of each reference frame in the steps, followed by the total size
+    offsets: Vec<usize>,
+}
+
+impl Window {
+    /// Window of `radius` frames around the diagonal between `n` reference frames
+    /// and `m` processed frames, or the whole matrix without radius
+    fn new(n: usize, m: usize, radius: Option<usize>) -> Self {
+        // A single reference frame is matched to all the processed frames
+        let (starts, ends): (Vec<_>, Vec<_>) = match radius.filter(|_| n > 1) {
+            Some(radius) => {
+                // The window must be at least as wide as the diagonal slope to stay connected
+                let slope = (m - 1).div_ceil(n - 1);
+                let radius = radius.max(slope.div_ceil(2)).max(1);
+                (0..n)
+                    .map(|i| {
+                        let center = (i * (m - 1) + (n - 1) / 2) / (n - 1);
+                        (center.saturating_sub(radius), (center + radius + 1).min(m))
+                    })
+                    .unzip()
+            }
+            None => (0..n).map(|_| (0, m)).unzip(),
+        };
+
+        let mut offsets = Vec::with_capacity(n + 1);
+        offsets.push(0);
+        for (start, end) in starts.iter().zip(&ends) {
+            offsets.push(offsets[offsets.len() - 1] + end - start);
+        }
+
+        Self { starts, offsets }
+    }
+
+    /// Amount of (reference, processed) frame pairs in the window
+    fn size(&self) -> usize {
+        self.offsets[self.offsets.len() - 1]
+    }
+
+    /// Processed frames allowed for the reference frame `i`
+    fn columns(&self, i: usize) -> std::ops::Range<usize> {
+        self.starts[i]..self.starts[i] + self.offsets[i + 1] - self.offsets[i]
+    }
+
+    /// Index of a pair of frames in the steps
+    fn index(&self, i: usize, j: usize) -> usize {
+        self.offsets[i] + j - self.starts[i]
+    }
+}
+
+/// Log envelopes with the mean of each band removed, grouped by frame
+fn normalized_log(envelopes: MatRef<f32>) -> Vec<f32> {
+    let (bands, frames) = (envelopes.nrows(), envelopes.ncols());
+    let mut log = vec![0.0; bands * frames];
+
+    for band in 0..bands {
+        let row = envelopes.row(band);
+        let mean = row.iter().map(|v| v.max(LOG_FLOOR).log10()).sum::<f32>() / frames as f32;
+        for (frame, v) in row.iter().enumerate() {
+            log[frame * bands + band] = v.max(LOG_FLOOR).log10() - mean;
+        }
+    }
+
+    log
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Positive envelopes of shape (bands, frames) with a distinct pattern in each frame
+    fn envelopes(frames: usize, seed: u64) -> Mat<f32> {
+        let mut state = seed;
+        Mat::from_fn(4, frames, |_, _| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (state >> 40) as f32 / (1u64 << 24) as f32 + 0.1
+        })
+    }
+
+    /// Repeat every `k`-th frame to stretch the envelopes
+    fn stretched(x: MatRef<f32>, k: usize) -> Mat<f32> {
+        let frames = (0..x.ncols())
+            .flat_map(|j| if j % k == 0 { vec![j, j] } else { vec![j] })
+            .collect::<Vec<_>>();
+        Mat::from_fn(x.nrows(), frames.len(), |i, j| x[(i, frames[j])])
+    }
+
+    #[test]
+    fn window_keeps_the_optimal_path_near_the_diagonal() {
+        let x = envelopes(200, 1);
+        let y = stretched(x.as_ref(), 5);
+
+        let (full, full_cost) = align(x.as_ref(), y.as_ref(), None);
+        let (windowed, windowed_cost) = align(x.as_ref(), y.as_ref(), Some(10));
+
+        assert_eq!(full_cost, windowed_cost);
+        assert_eq!(full, windowed);
+        assert_eq!(full, x);
+    }
+
+    #[test]
+    fn window_bounds_the_memory() {
+        let window = Window::new(10_000, 12_000, Some(20));
+        assert!(window.size() <= 10_000 * 41, "{}", window.size());
+        assert_eq!(Window::new(300, 200, None).size(), 300 * 200);
+    }
+
+    #[test]
+    fn narrow_windows_stay_connected() {
+        for (n, m) in [(10, 37), (37, 10), (1, 5), (5, 1), (2, 2)] {
+            let (x, y) = (envelopes(n, 2), envelopes(m, 3));
+            let window = Window::new(n, m, Some(0));
+            assert!(window.columns(0).contains(&0));
+            assert!(window.columns(n - 1).contains(&(m - 1)));
+
+            let (warped, cost) = align(x.as_ref(), y.as_ref(), Some(0));
+            assert_eq!(warped.ncols(), n);
+            assert!(cost.is_finite(), "{n}x{m}: {cost}");
+        }
+    }
+}
@@ -0,0 +1,1118 @@
+//! STOI configuration and reusable computation engine
+
+#[cfg(not(feature = "std"))]
+use alloc::{borrow::Cow, vec::Vec};
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+#[cfg(any(feature = "io", feature = "cache"))]
+use std::path::Path;
+
+#[cfg(feature = "std")]
+use faer::Mat;
+#[cfg(feature = "std")]
+use lazy_static::lazy_static;
+
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+use crate::{
+    batch,
+    constants::{
+        FRAME_LENGTH, FS, HALF_FRAME, MIN_FREQ, NUM_BANDS, PARALLEL_MIN_FRAMES,
+        PARALLEL_MIN_LENGTH, SEGMENT_LENGTH,
+    },
+    errors::{NotEnoughFramesError, Result},
+    extended,
+    frames::{self, FrameWindows},
+    octave, parallel,
+    pipeline::{Envelopes, Frames, Reference, Segments, Spectrogram},
+    resample::{self, Resampler},
+    standard,
+    stft::{self, FftPlan},
+};
+#[cfg(feature = "std")]
+use crate::{
+    binaural,
+    drift::{self, ClockDrift, DriftCompensatedStoi},
+    dtw::{self, DtwStoi},
+    evaluate::{Metric, Scores},
+    hearing::HearingLoss,
+    improvement::{self, StoiImprovement},
+    intervals::{self, Interval, IntervalError, IntervalScore, IntervalScores},
+    mixed_rate::{self, CropPolicy, MixedRateError},
+    multichannel::{self, ChannelPolicy, Multichannel, MultichannelStoi},
+    ncm,
+    pit::PitStoi,
+    snr,
+    sweep::{Noise, SnrSweep, SweepError},
+    vocoder::Vocoder,
+};
+
+#[cfg(feature = "cache")]
+use crate::cache::{self, CacheError, ReferenceCache};
+#[cfg(feature = "io")]
+use crate::io::{self, FileError, FileOptions};
+
+#[cfg(feature = "std")]
+lazy_static! {
+    /// Engine for the classic STOI configuration
+    pub static ref CLASSIC: Engine = Engine::new(Config::classic());
+}
+
+/// STOI configuration.
+///
+/// The default configuration is the classic STOI: 10kHz internal rate,
+/// 256-sample frames, 512-point FFT and 15 third octave bands from 150Hz.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    fs: usize,
+    frame_length: usize,
+    num_bands: usize,
+    min_freq: f32,
+    #[cfg(feature = "std")]
+    hearing_loss: Option<HearingLoss>,
+    #[cfg(feature = "std")]
+    vocoder: Option<Vocoder>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+impl Config {
+    /// Classic STOI configuration
+    pub fn classic() -> Self {
+        Self {
+            fs: FS,
+            frame_length: FRAME_LENGTH,
+            num_bands: NUM_BANDS,
+            min_freq: MIN_FREQ,
+            #[cfg(feature = "std")]
+            hearing_loss: None,
+            #[cfg(feature = "std")]
+            vocoder: None,
+        }
+    }
+
+    /// Wideband STOI configuration with an internal rate `fs` of at least 16kHz.
+    ///
+    /// **This mode is non-standard**: its scores are not comparable with the classic STOI.
+    ///
+    /// Frame and FFT lengths are scaled with the internal rate to keep the classic time and
+    /// frequency resolutions, and third octave bands from 150Hz are added up to the Nyquist frequency
+    /// so that high frequency content is taken into account.
+    pub fn wideband(fs: usize) -> Self {
+        assert!(
+            fs >= 16_000,
+            "Wideband STOI requires an internal rate of at least 16kHz"
+        );
+
+        Self {
+            fs,
+            frame_length: 2 * ((HALF_FRAME * fs + FS / 2) / FS),
+            num_bands: max_bands(fs, MIN_FREQ),
+            min_freq: MIN_FREQ,
+            #[cfg(feature = "std")]
+            hearing_loss: None,
+            #[cfg(feature = "std")]
+            vocoder: None,
+        }
+    }
+
+    /// Use `num_bands` third octave bands with center frequencies starting from `min_freq`.
+    ///
+    /// **This is non-standard** unless used with the classic values.
+    pub fn with_bands(mut self, num_bands: usize, min_freq: f32) -> Self {
+        assert!(num_bands > 0, "At least one band is required");
+        assert!(
+            num_bands <= max_bands(self.fs, min_freq),
+            "Third octave bands must stay below the Nyquist frequency"
+        );
+
+        self.num_bands = num_bands;
+        self.min_freq = min_freq;
+        self
+    }
+
+    /// Simulate a hearing loss on the processed signals before framing,
+    /// to evaluate them as heard by an impaired ear.
+    ///
+    /// **This is non-standard**: scores are not comparable with the classic STOI.
+    #[cfg(feature = "std")]
+    pub fn with_hearing_loss(mut self, hearing_loss: HearingLoss) -> Self {
+        self.hearing_loss = Some(hearing_loss);
+        self
+    }
+
+    /// Vocode the processed signals before framing, to evaluate them
+    /// as heard through a cochlear implant. The vocoder runs before the hearing loss simulation.
+    ///
+    /// **This is non-standard**: scores are not comparable with the classic STOI.
+    #[cfg(feature = "std")]
+    pub fn with_vocoder(mut self, vocoder: Vocoder) -> Self {
+        self.vocoder = Some(vocoder);
+        self
+    }
+
+    /// Internal sampling frequency
+    pub fn fs(&self) -> usize {
+        self.fs
+    }
+
+    /// Frame length in samples at the internal rate
+    pub fn frame_length(&self) -> usize {
+        self.frame_length
+    }
+
+    /// Hop length between frames (half overlap)
+    pub fn hop_length(&self) -> usize {
+        self.frame_length / 2
+    }
+
+    /// FFT length (frames are zero-padded to twice their length)
+    pub fn fft_length(&self) -> usize {
+        2 * self.frame_length
+    }
+
+    /// Amount of frequency bins in spectrograms
+    pub fn fft_bins(&self) -> usize {
+        self.fft_length() / 2 + 1
+    }
+
+    /// Amount of third octave bands
+    pub fn num_bands(&self) -> usize {
+        self.num_bands
+    }
+
+    /// Center frequency of the first third octave band
+    pub fn min_freq(&self) -> f32 {
+        self.min_freq
+    }
+
+    /// Hearing loss simulated on the processed signals
+    #[cfg(feature = "std")]
+    pub fn hearing_loss(&self) -> Option<&HearingLoss> {
+        self.hearing_loss.as_ref()
+    }
+
+    /// Vocoder applied to the processed signals
+    #[cfg(feature = "std")]
+    pub fn vocoder(&self) -> Option<&Vocoder> {
+        self.vocoder.as_ref()
+    }
+
+    /// Whether this is the classic STOI configuration
+    pub fn is_classic(&self) -> bool {
+        *self == Self::classic()
+    }
+}
+
+/// Maximum amount of third octave bands starting from `min_freq`
+/// whose upper edge stays below the Nyquist frequency.
+fn max_bands(fs: usize, min_freq: f32) -> usize {
+    let nyquist = fs as f64 / 2.0;
+    (0..)
+        .take_while(|&k| min_freq as f64 * 2.0_f64.powf((2 * k + 1) as f64 / 6.0) <= nyquist)
+        .count()
+}
+
+/// Reusable STOI engine holding the precomputed windows, FFT plan and
+/// octave band table for a given configuration.
+pub struct Engine {
+    config: Config,
+    windows: FrameWindows,
+    r2c: FftPlan,
+    bands: Vec<(usize, usize)>,
+}
+
+impl Engine {
+    /// Create an engine for the given configuration
+    pub fn new(config: Config) -> Self {
+        let bands = octave::band_table(
+            config.fs,
+            config.fft_length(),
+            config.num_bands,
+            config.min_freq,
+        );
+
+        Self {
+            windows: FrameWindows::new(config.frame_length),
+            r2c: stft::plan(config.fft_length()),
+            bands,
+            config,
+        }
+    }
+
+    /// Engine configuration
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Frame windows for the configured frame length
+    #[cfg(feature = "std")]
+    pub(crate) fn windows(&self) -> &FrameWindows {
+        &self.windows
+    }
+
+    /// RFFT plan for the configured FFT length
+    #[cfg(feature = "std")]
+    pub(crate) fn r2c(&self) -> &stft::Fft {
+        self.r2c.as_ref()
+    }
+
+    /// Third octave band indices in FFT spectrums
+    #[cfg(feature = "std")]
+    pub(crate) fn bands(&self) -> &[(usize, usize)] {
+        &self.bands
+    }
+
+    /// Resample a signal to the internal rate with the polyphase resampler used by `stoi`.
+    pub fn resample(&self, signal: &[f32], fs_sig: usize) -> Vec<f32> {
+        if fs_sig == self.config.fs {
+            signal.to_vec()
+        } else {
+            resample::resample(signal, fs_sig, self.config.fs)
+        }
+    }
+
+    /// Design a resampler from `fs_sig` to the internal rate, to resample signals
+    /// without designing its filter again.
+    pub fn resampler(&self, fs_sig: usize) -> Resampler {
+        Resampler::new(fs_sig, self.config.fs)
+    }
+
+    /// Resample a processed signal to the internal rate,
+    /// and simulate the configured vocoder and hearing loss.
+    pub(crate) fn resample_processed(&self, signal: &[f32], fs_sig: usize) -> Vec<f32> {
+        self.process(self.resample(signal, fs_sig))
+    }
+
+    /// Simulate the configured vocoder and hearing loss on a processed signal at the internal rate.
+    #[cfg(feature = "std")]
+    fn process(&self, mut processed: Vec<f32>) -> Vec<f32> {
+        if let Some(vocoder) = &self.config.vocoder {
+            processed = vocoder.process(&processed, self.config.fs);
+        }
+        if let Some(hearing_loss) = &self.config.hearing_loss {
+            processed = hearing_loss.apply(&processed, self.config.fs, self.config.frame_length);
+        }
+
+        processed
+    }
+
+    /// Processed signals are scored as is without the `std` feature.
+    #[cfg(not(feature = "std"))]
+    fn process(&self, processed: Vec<f32>) -> Vec<f32> {
+        processed
+    }
+
+    /// Select the non-silent frames of a clean signal at the internal rate.
+    pub fn frames(&self, x: &[f32]) -> Frames {
+        Frames {
+            starts: self.windows.valid_frames(x),
+        }
+    }
+
+    /// Compute the squared magnitude spectrogram of the selected frames of a signal
+    /// at the internal rate.
+    pub fn spectrogram(&self, signal: &[f32], frames: &Frames) -> Spectrogram {
+        Spectrogram {
+            power: stft::compute_frame_rffts(
+                signal,
+                &frames.starts,
+                &self.windows,
+                self.r2c.as_ref(),
+            ),
+        }
+    }
+
+    /// Pool a spectrogram into third octave band envelopes.
+    pub fn octave_envelopes(&self, spectrogram: &Spectrogram) -> Envelopes {
+        assert!(
+            spectrogram.power.nrows() == self.config.fft_bins(),
+            "Spectrograms must have {} frequency bins",
+            self.config.fft_bins()
+        );
+
+        Envelopes {
+            bands: octave::compute_octave_bands(spectrogram.power(), &self.bands),
+        }
+    }
+
+    /// Slice envelopes into overlapping segments.
+    /// Fails if there are not enough frames to form a single segment.
+    pub fn segments(&self, envelopes: &Envelopes) -> Result<Segments> {
+        assert!(
+            envelopes.bands.nrows() == self.config.num_bands,
+            "Envelopes must have {} bands",
+            self.config.num_bands
+        );
+
+        if envelopes.n_frames() < SEGMENT_LENGTH {
+            return Err(NotEnoughFramesError);
+        }
+
+        Ok(Segments {
+            segments: frames::segments(envelopes.bands().transpose()),
+            num_bands: self.config.num_bands,
+        })
+    }
+
+    /// Compute STOI from the segments of the clean and processed signals.
+    pub fn stoi_from_segments(&self, mut x: Segments, mut y: Segments, extended: bool) -> f32 {
+        assert!(
+            x.segments.ncols() == y.segments.ncols() && x.num_bands == self.config.num_bands,
+            "Segments must have the same shape"
+        );
+
+        if extended {
+            extended::from_segments(
+                x.segments.as_mut(),
+                y.segments.as_mut(),
+                self.config.num_bands,
+            )
+        } else {
+            standard::from_segments(x.segments.as_mut(), y.segments.as_mut())
+        }
+    }
+
+    /// Compute STOI from the third octave band envelopes of the clean and processed signals.
+    pub fn stoi_from_envelopes(&self, x: &Envelopes, y: &Envelopes, extended: bool) -> Result<f32> {
+        assert!(
+            x.n_frames() == y.n_frames(),
+            "Envelopes must have the same amount of frames"
+        );
+
+        Ok(self.stoi_from_segments(self.segments(x)?, self.segments(y)?, extended))
+    }
+
+    /// Compute STOI from the spectrograms of the clean and processed signals,
+    /// computed over the non-silent frames of the clean signal.
+    pub fn stoi_from_spectrogram(
+        &self,
+        x: &Spectrogram,
+        y: &Spectrogram,
+        extended: bool,
+    ) -> Result<f32> {
+        self.stoi_from_envelopes(
+            &self.octave_envelopes(x),
+            &self.octave_envelopes(y),
+            extended,
+        )
+    }
+
+    /// Compute the NCM from the third octave band envelopes of the clean and processed signals.
+    #[cfg(feature = "std")]
+    pub fn ncm_from_envelopes(&self, x: &Envelopes, y: &Envelopes) -> f32 {
+        assert!(
+            x.bands.shape() == y.bands.shape() && x.bands.nrows() == self.config.num_bands,
+            "Envelopes must have the same shape"
+        );
+
+        ncm::from_envelopes(
+            x.bands(),
+            y.bands(),
+            &ncm::band_importance(self.config.num_bands, self.config.min_freq),
+        )
+    }
+
+    /// Precompute the clean reference of a signal.
+    /// Fails if there are not enough non-silent frames.
+    pub fn reference(&self, x: &[f32], fs_sig: usize) -> Result<Reference> {
+        let resampled = self.resample(x, fs_sig);
+        let frames = self.frames(&resampled);
+        let envelopes = self.octave_envelopes(&self.spectrogram(&resampled, &frames));
+
+        Ok(Reference {
+            fs_sig,
+            len: x.len(),
+            segments: self.segments(&envelopes)?,
+            frames,
+        })
+    }
+
+    /// Compute STOI between a precomputed clean reference and a processed signal.
+    pub fn stoi_from_reference(&self, reference: &Reference, y: &[f32], extended: bool) -> f32 {
+        assert!(
+            y.len() == reference.len,
+            "Input signals must have the same length"
+        );
+
+        let y = self.resample_processed(y, reference.fs_sig);
+        let envelopes = self.octave_envelopes(&self.spectrogram(&y, &reference.frames));
+        let segments = self
+            .segments(&envelopes)
+            .expect("Processed signals have as many frames as their reference");
+
+        self.stoi_from_segments(reference.segments.clone(), segments, extended)
+    }
+
+    /// Write the precomputed references of a clean corpus to a cache file, with their keys.
+    /// See [`crate::write_reference_cache`].
+    #[cfg(feature = "cache")]
+    pub fn write_reference_cache<K: AsRef<str>>(
+        &self,
+        path: impl AsRef<Path>,
+        references: &[(K, Reference)],
+    ) -> std::result::Result<(), CacheError> {
+        cache::write(&self.config, path.as_ref(), references)
+    }
+
+    /// Open a reference cache written with the same configuration.
+    /// See [`crate::open_reference_cache`].
+    #[cfg(feature = "cache")]
+    pub fn open_reference_cache(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> std::result::Result<ReferenceCache, CacheError> {
+        cache::open(&self.config, path.as_ref())
+    }
+
+    /// Prepare a sweep of STOI as a function of the SNR.
+    /// See [`crate::snr_sweep`].
+    #[cfg(feature = "std")]
+    pub fn snr_sweep<'a>(
+        &'a self,
+        clean: &'a [f32],
+        noise: Noise,
+        fs_sig: usize,
+        extended: bool,
+        seed: u64,
+    ) -> std::result::Result<SnrSweep<'a>, SweepError> {
+        SnrSweep::new(self, clean, noise, fs_sig, extended, seed)
+    }
+
+    /// Compute the Short-Time Objective Intelligibility (STOI) measure between two signals.
+    /// See [`crate::stoi`].
+    pub fn stoi(&self, x: &[f32], y: &[f32], fs_sig: usize, extended: bool) -> Result<f32> {
+        let (x_segments, y_segments) = self.compute_segments(x, y, fs_sig)?;
+
+        Ok(self.stoi_from_segments(x_segments, y_segments, extended))
+    }
+
+    /// Estimate the clock drift of the processed signal `y` relative to the clean signal `x`,
+    /// with block-wise cross-correlations at the internal rate. See [`crate::drift`].
+    #[cfg(feature = "std")]
+    pub fn estimate_drift(&self, x: &[f32], y: &[f32], fs_sig: usize) -> ClockDrift {
+        let (x, y) = parallel::join(
+            x.len() * FS / fs_sig >= PARALLEL_MIN_LENGTH,
+            || self.resample(x, fs_sig),
+            || self.resample(y, fs_sig),
+        );
+
+        drift::estimate(&x, &y, self.config.fs)
+    }
+
+    /// Compute STOI after estimating the clock drift of `y` relative to `x`
+    /// and resampling `y` to cancel it.
+    ///
+    /// The estimated drift is reported with the score. When it cannot be estimated
+    /// (no reliable blocks), `y` is scored as is. The signals may have different lengths:
+    /// the compensated `y` is brought to the length of `x` with the `crop` policy.
+    #[cfg(feature = "std")]
+    pub fn stoi_with_drift_compensation(
+        &self,
+        x: &[f32],
+        y: &[f32],
+        fs_sig: usize,
+        extended: bool,
+        crop: CropPolicy,
+    ) -> Result<DriftCompensatedStoi> {
+        let (mut x, y) = parallel::join(
+            x.len() * FS / fs_sig >= PARALLEL_MIN_LENGTH,
+            || self.resample(x, fs_sig),
+            || self.resample(y, fs_sig),
+        );
+
+        let drift = drift::estimate(&x, &y, self.config.fs);
+        let mut y = drift::compensate(&y, &drift, self.config.fs);
+        mixed_rate::crop(&mut x, &mut y, crop);
+        let y = self.process(y);
+
+        let (x_envelopes, y_envelopes) = self.compute_envelopes(&x, &y)?;
+        let stoi = self.stoi_from_segments(
+            self.segments(&x_envelopes)?,
+            self.segments(&y_envelopes)?,
+            extended,
+        );
+
+        Ok(DriftCompensatedStoi { stoi, drift })
+    }
+
+    /// Warp processed envelopes onto clean envelopes with dynamic time warping.
+    /// Returns the warped envelopes, with as many frames as the clean ones,
+    /// and the mean frame distance along the warping path. See [`crate::stoi_dtw`]
+    /// for the Sakoe-Chiba `window`, in seconds.
+    #[cfg(feature = "std")]
+    pub fn align_envelopes(
+        &self,
+        x: &Envelopes,
+        y: &Envelopes,
+        window: Option<f32>,
+    ) -> (Envelopes, f32) {
+        assert!(
+            x.bands.nrows() == self.config.num_bands && y.bands.nrows() == self.config.num_bands,
+            "Envelopes must have {} bands",
+            self.config.num_bands
+        );
+        assert!(
+            y.bands.ncols() > 0 && x.bands.ncols() > 0,
+            "Envelopes must not be empty"
+        );
+
+        let radius = window.map(|window| {
+            assert!(window >= 0.0, "The DTW window must not be negative");
+            let hop = (self.config.frame_length / 2) as f32;
+            (window * self.config.fs as f32 / hop).ceil() as usize
+        });
+        let (bands, path_cost) = dtw::align(x.bands(), y.bands(), radius);
+
+        (Envelopes { bands }, path_cost)
+    }
+
+    /// Compute STOI between signals that are not aligned in time, such as text-to-speech
+    /// or voice conversion outputs and a reference recording. The signals may have different lengths.
+    ///
+    /// The non-silent frames of each signal are selected separately, and the processed
+    /// envelopes are warped onto the clean frames with dynamic time warping before scoring.
+    /// The warping path cost is reported with the score.
+    ///
+    /// **This is non-standard**: scores are not comparable with the classic STOI.
+    #[cfg(feature = "std")]
+    pub fn stoi_dtw(
+        &self,
+        x: &[f32],
+        y: &[f32],
+        fs_sig: usize,
+        extended: bool,
+        window: Option<f32>,
+    ) -> Result<DtwStoi> {
+        let (x, y) = parallel::join(
+            x.len().max(y.len()) * FS / fs_sig >= PARALLEL_MIN_LENGTH,
+            || self.resample(x, fs_sig),
+            || self.resample_processed(y, fs_sig),
+        );
+
+        let (x_frames, y_frames) = (self.frames(&x), self.frames(&y));
+        if x_frames.len() < SEGMENT_LENGTH || y_frames.is_empty() {
+            return Err(NotEnoughFramesError);
+        }
+
+        let (x_envelopes, y_envelopes) = parallel::join(
+            x_frames.len() >= PARALLEL_MIN_FRAMES,
+            || self.octave_envelopes(&self.spectrogram(&x, &x_frames)),
+            || self.octave_envelopes(&self.spectrogram(&y, &y_frames)),
+        );
+        let (y_envelopes, path_cost) = self.align_envelopes(&x_envelopes, &y_envelopes, window);
+
+        let stoi = self.stoi_from_segments(
+            self.segments(&x_envelopes)?,
+            self.segments(&y_envelopes)?,
+            extended,
+        );
+
+        Ok(DtwStoi { stoi, path_cost })
+    }
+
+    /// Compute STOI and ESTOI of labelled intervals inside two long aligned recordings.
+    /// See [`crate::stoi_intervals`].
+    #[cfg(feature = "std")]
+    pub fn stoi_intervals(
+        &self,
+        x: &[f32],
+        y: &[f32],
+        fs_sig: usize,
+        intervals: &[Interval],
+    ) -> IntervalScores {
+        assert!(
+            x.len() == y.len(),
+            "Input signals must have the same length"
+        );
+
+        // Both recordings are resampled once for all the intervals
+        let (x, y) = self.resample_pair(x, y, fs_sig);
+
+        let score = |interval: &Interval| {
+            let (stoi, estoi) = match self.interval_segments(&x, &y, interval) {
+                Ok((x_segments, y_segments)) => (
+                    Ok(self.stoi_from_segments(x_segments.clone(), y_segments.clone(), false)),
+                    Ok(self.stoi_from_segments(x_segments, y_segments, true)),
+                ),
+                Err(err) => (Err(err.clone()), Err(err)),
+            };
+
+            IntervalScore {
+                interval: interval.clone(),
+                stoi,
+                estoi,
+            }
+        };
+
+        #[cfg(feature = "rayon")]
+        let scores = {
+            use rayon::prelude::*;
+
+            intervals.par_iter().map(score).collect::<Vec<_>>()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let scores = intervals.iter().map(score).collect::<Vec<_>>();
+
+        intervals::aggregate(scores)
+    }
+
+    /// Compute STOI between signals at different sampling frequencies.
+    /// See [`crate::stoi_mixed_rate`].
+    #[cfg(feature = "std")]
+    pub fn stoi_mixed_rate(
+        &self,
+        x: &[f32],
+        fs_x: usize,
+        y: &[f32],
+        fs_y: usize,
+        extended: bool,
+        crop: CropPolicy,
+    ) -> std::result::Result<f32, MixedRateError> {
+        mixed_rate::check_durations(x.len(), fs_x, y.len(), fs_y)?;
+
+        let (mut x, mut y) = parallel::join(
+            x.len() * FS / fs_x >= PARALLEL_MIN_LENGTH,
+            || self.resample(x, fs_x),
+            || self.resample_processed(y, fs_y),
+        );
+        mixed_rate::crop(&mut x, &mut y, crop);
+
+        let (x_envelopes, y_envelopes) = self.compute_envelopes(&x, &y)?;
+
+        Ok(self.stoi_from_segments(
+            self.segments(&x_envelopes)?,
+            self.segments(&y_envelopes)?,
+            extended,
+        ))
+    }
+
+    /// Compute several metrics between two signals, running each shared stage once.
+    /// See [`crate::evaluate`].
+    #[cfg(feature = "std")]
+    pub fn evaluate(
+        &self,
+        x: &[f32],
+        y: &[f32],
+        fs_sig: usize,
+        metrics: &[Metric],
+    ) -> Result<Scores> {
+        assert!(
+            x.len() == y.len(),
+            "Input signals must have the same length"
+        );
+
+        let stoi = metrics.contains(&Metric::Stoi);
+        let estoi = metrics.contains(&Metric::Estoi);
+        let fw_seg_snr = metrics.contains(&Metric::FwSegSnr);
+
+        let mut scores = Scores {
+            si_sdr: metrics.contains(&Metric::SiSdr).then(|| snr::si_sdr(x, y)),
+            fw_seg_snr: fw_seg_snr
+                .then(|| snr::fw_seg_snr(x, y, fs_sig))
+                .transpose()?,
+            ..Default::default()
+        };
+
+        if stoi || estoi {
+            // Shared stages: resampling, frame selection, spectrograms and octave bands
+            let (x, y) = self.resample_pair(x, y, fs_sig);
+            let (x_envelopes, y_envelopes) = self.compute_envelopes(&x, &y)?;
+            let (x_segments, y_segments) =
+                (self.segments(&x_envelopes)?, self.segments(&y_envelopes)?);
+
+            // Segments are normalized in place: only copy them when both measures are needed
+            if stoi && estoi {
+                scores.stoi =
+                    Some(self.stoi_from_segments(x_segments.clone(), y_segments.clone(), false));
+                scores.estoi = Some(self.stoi_from_segments(x_segments, y_segments, true));
+            } else if stoi {
+                scores.stoi = Some(self.stoi_from_segments(x_segments, y_segments, false));
+            } else {
+                scores.estoi = Some(self.stoi_from_segments(x_segments, y_segments, true));
+            }
+        }
+
+        Ok(scores)
+    }
+
+    /// Compute the STOI improvement from a noisy to an enhanced signal.
+    /// See [`crate::stoi_improvement`].
+    #[cfg(feature = "std")]
+    pub fn stoi_improvement(
+        &self,
+        clean: &[f32],
+        noisy: &[f32],
+        enhanced: &[f32],
+        fs_sig: usize,
+        extended: bool,
+        detailed: bool,
+    ) -> Result<StoiImprovement> {
+        improvement::stoi_improvement(self, clean, noisy, enhanced, fs_sig, extended, detailed)
+    }
+
+    /// Compute STOI between a clean and a processed audio file.
+    /// See [`crate::stoi_files`].
+    #[cfg(feature = "io")]
+    pub fn stoi_files(
+        &self,
+        ref_path: impl AsRef<Path>,
+        deg_path: impl AsRef<Path>,
+        options: &FileOptions,
+    ) -> std::result::Result<MultichannelStoi, FileError> {
+        io::stoi_files(self, ref_path.as_ref(), deg_path.as_ref(), options)
+    }
+
+    /// Compute STOI between pairs of clean and processed audio files.
+    /// See [`crate::batch_stoi_files`].
+    #[cfg(feature = "io")]
+    pub fn batch_stoi_files<P: AsRef<Path>>(
+        &self,
+        ref_paths: &[P],
+        deg_paths: &[P],
+        options: &FileOptions,
+    ) -> Vec<std::result::Result<MultichannelStoi, FileError>> {
+        let ref_paths = ref_paths.iter().map(|p| p.as_ref()).collect::<Vec<_>>();
+        let deg_paths = deg_paths.iter().map(|p| p.as_ref()).collect::<Vec<_>>();
+
+        io::batch_stoi_files(self, &ref_paths, &deg_paths, options)
+    }
+
+    /// Compute the permutation-invariant STOI between reference and estimated sources.
+    /// See [`crate::pit_stoi`].
+    #[cfg(feature = "std")]
+    pub fn pit_stoi(
+        &self,
+        refs: &[&[f32]],
+        ests: &[&[f32]],
+        fs_sig: usize,
+        extended: bool,
+    ) -> Result<PitStoi> {
+        assert!(!refs.is_empty(), "There must be at least one reference");
+        assert!(
+            refs.len() == ests.len(),
+            "There must be as many estimates as references"
+        );
+        assert!(
+            refs.iter().chain(ests).all(|s| s.len() == refs[0].len()),
+            "Input signals must have the same length"
+        );
+
+        let n = refs.len();
+        let refs = refs
+            .iter()
+            .map(|signal| self.resample(signal, fs_sig))
+            .collect::<Vec<_>>();
+        let ests = ests
+            .iter()
+            .map(|signal| self.resample_processed(signal, fs_sig))
+            .collect::<Vec<_>>();
+
+        // Reference frames and segments are shared by all the estimates
+        let references = refs
+            .iter()
+            .map(|x| {
+                let frames = self.frames(x);
+                let envelopes = self.octave_envelopes(&self.spectrogram(x, &frames));
+                Ok((frames, self.segments(&envelopes)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let score = |k: usize| {
+            let (frames, x_segments) = &references[k / n];
+            let y_envelopes = self.octave_envelopes(&self.spectrogram(&ests[k % n], frames));
+            let y_segments = self
+                .segments(&y_envelopes)
+                .expect("Estimates have as many frames as their reference");
+
+            self.stoi_from_segments(x_segments.clone(), y_segments, extended)
+        };
+
+        #[cfg(feature = "rayon")]
+        let scores = {
+            use rayon::prelude::*;
+            (0..n * n).into_par_iter().map(score).collect::<Vec<_>>()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let scores = (0..n * n).map(score).collect::<Vec<_>>();
+
+        Ok(PitStoi::from_scores(Mat::from_fn(n, n, |i, j| {
+            scores[i * n + j]
+        })))
+    }
+
+    /// Compute the permutation-invariant STOI for a batch of source separation examples.
+    /// See [`crate::batch_pit_stoi`].
+    #[cfg(feature = "std")]
+    pub fn batch_pit_stoi(
+        &self,
+        refs: &[&[&[f32]]],
+        ests: &[&[&[f32]]],
+        fs_sig: usize,
+        extended: bool,
+    ) -> Vec<Result<PitStoi>> {
+        assert!(
+            refs.len() == ests.len(),
+            "Input batches must have the same length"
+        );
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+
+            refs.par_iter()
+                .zip(ests)
+                .map(|(refs, ests)| self.pit_stoi(refs, ests, fs_sig, extended))
+                .collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            refs.iter()
+                .zip(ests)
+                .map(|(refs, ests)| self.pit_stoi(refs, ests, fs_sig, extended))
+                .collect()
+        }
+    }
+
+    /// Compute the Normalized Covariance Measure (NCM) between two signals.
+    /// See [`crate::ncm`].
+    #[cfg(feature = "std")]
+    pub fn ncm(&self, x: &[f32], y: &[f32], fs_sig: usize) -> Result<f32> {
+        let (x_envelopes, y_envelopes) = self.compute_resampled_envelopes(x, y, fs_sig)?;
+
+        Ok(self.ncm_from_envelopes(&x_envelopes, &y_envelopes))
+    }
+
+    /// Compute the Deterministic Binaural STOI (DBSTOI) measure between two binaural signals.
+    /// See [`crate::dbstoi`].
+    #[cfg(feature = "std")]
+    pub fn dbstoi(
+        &self,
+        xl: &[f32],
+        xr: &[f32],
+        yl: &[f32],
+        yr: &[f32],
+        fs_sig: usize,
+    ) -> Result<f32> {
+        binaural::dbstoi(self, xl, xr, yl, yr, fs_sig)
+    }
+
+    /// Compute STOI between multichannel signals with a channel policy.
+    /// See [`crate::stoi_multichannel`].
+    #[cfg(feature = "std")]
+    pub fn stoi_multichannel(
+        &self,
+        x: &Multichannel,
+        y: &Multichannel,
+        fs_sig: usize,
+        extended: bool,
+        policy: ChannelPolicy,
+    ) -> MultichannelStoi {
+        assert!(
+            x.len() == y.len(),
+            "Input signals must have the same length"
+        );
+
+        multichannel::apply_policy(x, y, policy, |x, y| self.batch_stoi(x, y, fs_sig, extended))
+    }
+
+    /// Compute the STOI measure for a batch of clean and processed signal pairs.
+    /// See [`crate::batch_stoi`].
+    pub fn batch_stoi(
+        &self,
+        x: &[&[f32]],
+        y: &[&[f32]],
+        fs_sig: usize,
+        extended: bool,
+    ) -> Vec<Result<f32>> {
+        assert!(
+            x.len() == y.len(),
+            "Input batches must have the same length"
+        );
+
+        let score_group = |(x, y): (&[&[f32]], &[&[f32]])| {
+            if extended {
+                return x
+                    .iter()
+                    .zip(y)
+                    .map(|(x, y)| self.stoi(x, y, fs_sig, true))
+                    .collect::<Vec<_>>();
+            }
+
+            let segments = x
+                .iter()
+                .zip(y)
+                .map(|(x, y)| self.compute_segments(x, y, fs_sig))
+                .collect::<Vec<_>>();
+
+            // Only score the valid clips in the lanes
+            let (x_segments, y_segments): (Vec<_>, Vec<_>) = segments
+                .iter()
+                .filter_map(|s| s.as_ref().ok())
+                .map(|(x, y)| (x.as_mat(), y.as_mat()))
+                .unzip();
+            let mut scores = batch::from_segments(&x_segments, &y_segments).into_iter();
+
+            segments
+                .iter()
+                .map(|s| match s {
+                    Ok(_) => Ok(scores.next().unwrap()),
+                    Err(err) => Err(err.clone()),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+
+            x.par_chunks(batch::LANES)
+                .zip(y.par_chunks(batch::LANES))
+                .flat_map_iter(score_group)
+                .collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            x.chunks(batch::LANES)
+                .zip(y.chunks(batch::LANES))
+                .flat_map(score_group)
+                .collect()
+        }
+    }
+
+    /// Compute the spectrograms of both signals at the internal rate
+    /// over the non-silent frames of the clean signal
+    fn compute_spectrograms(&self, x: &[f32], y: &[f32]) -> Result<(Spectrogram, Spectrogram)> {
+        // Select frames
+        let frames = self.frames(x);
+
+        if frames.len() < SEGMENT_LENGTH {
+            return Err(NotEnoughFramesError);
+        }
+
+        // Long signals are processed on multiple threads
+        Ok(parallel::join(
+            frames.len() >= PARALLEL_MIN_FRAMES,
+            || self.spectrogram(x, &frames),
+            || self.spectrogram(y, &frames),
+        ))
+    }
+
+    /// Pool the spectrograms of both signals into octave band envelopes
+    fn pool_spectrograms(
+        &self,
+        x_spec: &Spectrogram,
+        y_spec: &Spectrogram,
+    ) -> (Envelopes, Envelopes) {
+        parallel::join(
+            x_spec.n_frames() >= PARALLEL_MIN_FRAMES,
+            || self.octave_envelopes(x_spec),
+            || self.octave_envelopes(y_spec),
+        )
+    }
+
+    /// Compute the octave band envelopes of both signals at the internal rate
+    fn compute_envelopes(&self, x: &[f32], y: &[f32]) -> Result<(Envelopes, Envelopes)> {
+        let (x_spec, y_spec) = self.compute_spectrograms(x, y)?;
+
+        // Accumulate into octave bands
+        Ok(self.pool_spectrograms(&x_spec, &y_spec))
+    }
+
+    /// Compute the octave band segments of both signals, resampling them to the internal rate if needed
+    fn compute_segments(
+        &self,
+        x: &[f32],
+        y: &[f32],
+        fs_sig: usize,
+    ) -> Result<(Segments, Segments)> {
+        let (x_envelopes, y_envelopes) = self.compute_resampled_envelopes(x, y, fs_sig)?;
+
+        // Slice into segments
+        Ok((self.segments(&x_envelopes)?, self.segments(&y_envelopes)?))
+    }
+
+    /// Compute the octave band envelopes of both signals, resampling them to the internal rate if needed
+    fn compute_resampled_envelopes(
+        &self,
+        x: &[f32],
+        y: &[f32],
+        fs_sig: usize,
+    ) -> Result<(Envelopes, Envelopes)> {
+        assert!(
+            x.len() == y.len(),
+            "Input signals must have the same length"
+        );
+
+        let (x, y) = self.resample_pair(x, y, fs_sig);
+
+        self.compute_envelopes(&x, &y)
+    }
+
+    /// Compute the segments of an interval of both signals at the internal rate
+    #[cfg(feature = "std")]
+    fn interval_segments(
+        &self,
+        x: &[f32],
+        y: &[f32],
+        interval: &Interval,
+    ) -> std::result::Result<(Segments, Segments), IntervalError> {
+        let range =
+            intervals::sample_range(interval, self.config.fs, x.len(), self.config.frame_length)?;
+        let (x_envelopes, y_envelopes) = self.compute_envelopes(&x[range.clone()], &y[range])?;
+
+        Ok((self.segments(&x_envelopes)?, self.segments(&y_envelopes)?))
+    }
+
+    /// Resample both signals to the internal rate if needed
+    fn resample_pair<'a>(
+        &self,
+        x: &'a [f32],
+        y: &'a [f32],
+        fs_sig: usize,
+    ) -> (Cow<'a, [f32]>, Cow<'a, [f32]>) {
+        #[cfg(feature = "std")]
+        let unprocessed = self.config.hearing_loss.is_none() && self.config.vocoder.is_none();
+        #[cfg(not(feature = "std"))]
+        let unprocessed = true;
+
+        if fs_sig == self.config.fs && unprocessed {
+            return (Cow::Borrowed(x), Cow::Borrowed(y));
+        }
+
+        let (x, y) = parallel::join(
+            x.len() * FS / fs_sig >= PARALLEL_MIN_LENGTH,
+            || self.resample(x, fs_sig),
+            || self.resample_processed(y, fs_sig),
+        );
+
+        (Cow::Owned(x), Cow::Owned(y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn hearing_loss_keeps_the_classic_bands() {
+        let config =
+            Config::classic().with_hearing_loss(HearingLoss::new(&[(500.0, 20.0), (4000.0, 60.0)]));
+        assert!(!config.is_classic());
+
+        assert_eq!(Engine::new(config).bands(), octave::OCTAVE_BANDS);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[should_panic(expected = "at least one reference")]
+    fn pit_stoi_rejects_empty_sources() {
+        let _ = Engine::new(Config::classic()).pit_stoi(&[], &[], FS, false);
+    }
+}
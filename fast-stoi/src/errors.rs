@@ -1,11 +1,12 @@
 //! Crate error type
 
-use std::fmt::{self, Display, Formatter};
+use core::fmt::{self, Display, Formatter};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NotEnoughFramesError;
 
-pub type Result<T> = std::result::Result<T, NotEnoughFramesError>;
+pub type Result<T> = core::result::Result<T, NotEnoughFramesError>;
 
 const MESSAGE: &str = "Not enough STFT frames to compute intermediate \
 intelligibility measure after removing silent \
@@ -18,5 +19,5 @@ impl Display for NotEnoughFramesError {
     }
 }
 
-/// Implement std::error::Error so it can be used with `?`
-impl std::error::Error for NotEnoughFramesError {}
+/// Implement Error so it can be used with `?`
+impl core::error::Error for NotEnoughFramesError {}
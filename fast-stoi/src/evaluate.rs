@@ -0,0 +1,25 @@
+//! Multi-metric evaluation types
+
+/// Metric computed by `evaluate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Metric {
+    /// Standard STOI
+    Stoi,
+    /// Extended STOI
+    Estoi,
+    /// Scale-invariant signal-to-distortion ratio in dB
+    SiSdr,
+    /// Frequency-weighted segmental SNR in dB
+    FwSegSnr,
+}
+
+/// Scores computed by `evaluate`. Metrics that were not requested are `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scores {
+    pub stoi: Option<f32>,
+    pub estoi: Option<f32>,
+    pub si_sdr: Option<f32>,
+    pub fw_seg_snr: Option<f32>,
+}
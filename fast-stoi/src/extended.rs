@@ -1,40 +1,114 @@
 //! Extended STOI computation from octave segment spectrograms
 
-use std::f32::EPSILON;
-
 use faer::prelude::*;
 
-use crate::constants::{NUM_BANDS, SEGMENT_LENGTH};
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+#[cfg(feature = "rayon")]
+use crate::constants::PARALLEL_MIN_FRAMES;
+use crate::constants::SEGMENT_LENGTH;
 
 /// Compute the extended STOI from octave segment spectrograms of the clean and processed signals.
 /// The segments have shapes (segment_length, num_segments * num_bands).
-pub fn from_segments(mut x_segments: MatMut<f32>, mut y_segments: MatMut<f32>) -> f32 {
-    row_col_normalize(x_segments.as_mut());
-    row_col_normalize(y_segments.as_mut());
-
+pub fn from_segments(
+    mut x_segments: MatMut<f32>,
+    mut y_segments: MatMut<f32>,
+    num_bands: usize,
+) -> f32 {
     let n_segments = x_segments.ncols();
 
+    // Long signals: normalize and correlate segments in parallel,
+    // and sum the correlations in order so that the result stays deterministic
+    #[cfg(feature = "rayon")]
+    if n_segments / num_bands >= PARALLEL_MIN_FRAMES {
+        use rayon::prelude::*;
+
+        rayon::join(
+            || par_row_col_normalize(x_segments.as_mut(), num_bands),
+            || par_row_col_normalize(y_segments.as_mut(), num_bands),
+        );
+
+        let dotted = x_segments
+            .par_col_iter()
+            .zip(y_segments.par_col_iter())
+            .map(|(x_col, y_col)| x_col.transpose() * y_col)
+            .collect::<Vec<f32>>();
+
+        return dotted.iter().sum::<f32>() / (n_segments as f32)
+            * (num_bands as f32 / SEGMENT_LENGTH as f32);
+    }
+
+    row_col_normalize(x_segments.as_mut(), num_bands);
+    row_col_normalize(y_segments.as_mut(), num_bands);
+
     let dotted: f32 = x_segments
         .col_iter()
         .zip(y_segments.col_iter())
         .map(|(x_col, y_col)| x_col.transpose() * y_col)
         .sum();
 
-    dotted / (n_segments as f32) * (NUM_BANDS as f32 / SEGMENT_LENGTH as f32)
+    dotted / (n_segments as f32) * (num_bands as f32 / SEGMENT_LENGTH as f32)
+}
+
+/// Compute the correlation of each normalized clean and processed segment column.
+/// Summing them in order and scaling them like `from_segments` gives the same result.
+#[cfg(feature = "std")]
+pub fn correlations(
+    mut x_segments: MatMut<f32>,
+    mut y_segments: MatMut<f32>,
+    num_bands: usize,
+) -> Vec<f32> {
+    #[cfg(feature = "rayon")]
+    if x_segments.ncols() / num_bands >= PARALLEL_MIN_FRAMES {
+        use rayon::prelude::*;
+
+        rayon::join(
+            || par_row_col_normalize(x_segments.as_mut(), num_bands),
+            || par_row_col_normalize(y_segments.as_mut(), num_bands),
+        );
+
+        return x_segments
+            .par_col_iter()
+            .zip(y_segments.par_col_iter())
+            .map(|(x_col, y_col)| x_col.transpose() * y_col)
+            .collect();
+    }
+
+    row_col_normalize(x_segments.as_mut(), num_bands);
+    row_col_normalize(y_segments.as_mut(), num_bands);
+
+    x_segments
+        .col_iter()
+        .zip(y_segments.col_iter())
+        .map(|(x_col, y_col)| x_col.transpose() * y_col)
+        .collect()
 }
 
 /// Normalize segments both along columns, and along rows by band subgroups.
-fn row_col_normalize(mut mat: MatMut<f32>) {
+fn row_col_normalize(mut mat: MatMut<f32>, num_bands: usize) {
     normalize_cols(mat.as_mut());
 
     // Group segments by bands
-    let band_segments = mat.ncols() / NUM_BANDS;
+    let band_segments = mat.ncols() / num_bands;
     for i in 0..band_segments {
-        let mut submat = mat.as_mut().subcols_mut(i * NUM_BANDS, NUM_BANDS);
+        let mut submat = mat.as_mut().subcols_mut(i * num_bands, num_bands);
         normalize_rows(submat.as_mut());
     }
 }
 
+/// Parallel version of `row_col_normalize` for long signals.
+#[cfg(feature = "rayon")]
+fn par_row_col_normalize(mat: MatMut<f32>, num_bands: usize) {
+    use rayon::prelude::*;
+
+    // Each group of bands is independent from the others
+    mat.par_col_chunks_mut(num_bands).for_each(|mut submat| {
+        normalize_cols(submat.as_mut());
+        normalize_rows(submat);
+    });
+}
+
 /// Normalize a 2D matrix along columns.
 fn normalize_cols(mat: MatMut<f32>) {
     // Subtract mean and divide by norm l2
@@ -45,7 +119,7 @@ fn normalize_cols(mat: MatMut<f32>) {
         });
 
         // NOTE: faer's .norm_l2 is very slow for such small vectors
-        let norm2 = (col.as_ref().iter().map(|x| x * x).sum::<f32>()).sqrt() + EPSILON;
+        let norm2 = (col.as_ref().iter().map(|x| x * x).sum::<f32>()).sqrt() + f32::EPSILON;
         col.iter_mut().for_each(|x| {
             *x /= norm2;
         });
@@ -61,7 +135,7 @@ fn normalize_rows(mat: MatMut<f32>) {
             *x -= mean;
         });
         // NOTE: faer's .norm_l2 is very slow for such small vectors
-        let norm2 = (row.as_ref().iter().map(|x| x * x).sum::<f32>()).sqrt() + EPSILON;
+        let norm2 = (row.as_ref().iter().map(|x| x * x).sum::<f32>()).sqrt() + f32::EPSILON;
         row.iter_mut().for_each(|x| {
             *x /= norm2;
         });
@@ -0,0 +1,121 @@
+//! Real FFT for `no_std` builds
+//!
+//! Mirrors the subset of the `realfft` API used by the STFT.
+//! Power of two lengths use an iterative radix-2 FFT, other lengths a direct DFT.
+
+use alloc::{vec, vec::Vec};
+use core::{convert::Infallible, f64::consts::PI};
+
+use num::Complex;
+
+use crate::math::Float;
+
+/// Forward real FFT of a given length
+pub struct Rfft {
+    length: usize,
+    /// `exp(-2iπk / length)` for `k` in `0..length`
+    twiddles: Vec<Complex<f32>>,
+}
+
+impl Rfft {
+    /// Plan a forward real FFT of `length` points
+    pub fn new(length: usize) -> Self {
+        assert!(length > 0, "The FFT length must be positive");
+
+        let twiddles = (0..length)
+            .map(|k| {
+                let angle = -2.0 * PI * k as f64 / length as f64;
+                Complex::new(angle.cos() as f32, angle.sin() as f32)
+            })
+            .collect();
+
+        Self { length, twiddles }
+    }
+
+    /// Amount of complex output bins
+    pub fn complex_len(&self) -> usize {
+        self.length / 2 + 1
+    }
+
+    /// Real input buffer
+    pub fn make_input_vec(&self) -> Vec<f32> {
+        vec![0.0; self.length]
+    }
+
+    /// Complex output buffer
+    pub fn make_output_vec(&self) -> Vec<Complex<f32>> {
+        vec![Complex::new(0.0, 0.0); self.complex_len()]
+    }
+
+    /// Complex scratch buffer
+    pub fn make_scratch_vec(&self) -> Vec<Complex<f32>> {
+        vec![Complex::new(0.0, 0.0); self.length]
+    }
+
+    /// Compute the FFT of `input` into `output`. The input buffer is left untouched.
+    pub fn process_with_scratch(
+        &self,
+        input: &mut [f32],
+        output: &mut [Complex<f32>],
+        scratch: &mut [Complex<f32>],
+    ) -> Result<(), Infallible> {
+        assert!(
+            input.len() == self.length
+                && output.len() == self.complex_len()
+                && scratch.len() == self.length,
+            "Invalid FFT buffer lengths"
+        );
+
+        if self.length.is_power_of_two() {
+            self.radix2(input, scratch);
+            output.copy_from_slice(&scratch[..self.complex_len()]);
+        } else {
+            self.dft(input, output);
+        }
+
+        Ok(())
+    }
+
+    /// Iterative radix-2 FFT of a real input into `buffer`
+    fn radix2(&self, input: &[f32], buffer: &mut [Complex<f32>]) {
+        let n = self.length;
+        let bits = n.trailing_zeros();
+
+        // Bit-reversed copy
+        for (i, &x) in input.iter().enumerate() {
+            let j = if bits == 0 {
+                0
+            } else {
+                i.reverse_bits() >> (usize::BITS - bits)
+            };
+            buffer[j] = Complex::new(x, 0.0);
+        }
+
+        // Butterflies
+        let mut size = 2;
+        while size <= n {
+            let half = size / 2;
+            let stride = n / size;
+            for block in buffer.chunks_exact_mut(size) {
+                let (low, high) = block.split_at_mut(half);
+                for (k, (a, b)) in low.iter_mut().zip(high.iter_mut()).enumerate() {
+                    let t = *b * self.twiddles[k * stride];
+                    *b = *a - t;
+                    *a += t;
+                }
+            }
+            size *= 2;
+        }
+    }
+
+    /// Direct DFT of the first `complex_len` bins
+    fn dft(&self, input: &[f32], output: &mut [Complex<f32>]) {
+        for (k, bin) in output.iter_mut().enumerate() {
+            *bin = input
+                .iter()
+                .enumerate()
+                .map(|(n, &x)| self.twiddles[k * n % self.length] * x)
+                .sum();
+        }
+    }
+}
@@ -1,12 +1,21 @@
 //! Slice, filter and preprocess audio frames.
 
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
 use faer::prelude::*;
-use lazy_static::lazy_static;
+#[cfg(feature = "std")]
 use windowfunctions::{Symmetry, WindowFunction, window};
 
-use crate::constants::{DYNAMIC_RANGE, FRAME_LENGTH, HALF_FRAME, HOP_LENGTH, SEGMENT_LENGTH};
+#[cfg(feature = "rayon")]
+use crate::constants::PARALLEL_MIN_FRAMES;
+use crate::constants::{DYNAMIC_RANGE, SEGMENT_LENGTH};
+#[cfg(not(feature = "std"))]
+use crate::math::{Float, Symmetry, WindowFunction, window};
 
-struct FrameWindows {
+/// Hann windows for a given frame length
+/// (the hop length is half the frame length).
+pub struct FrameWindows {
     /// Trimmed hann window
     pub hann: Col<f32>,
     /// Hann window with half overlap with another hann window at the end
@@ -17,25 +26,23 @@ struct FrameWindows {
 }
 
 impl FrameWindows {
-    fn new() -> Self {
-        let hann = window(FRAME_LENGTH + 2, WindowFunction::Hann, Symmetry::Symmetric)
-            .skip(1)
-            .take(FRAME_LENGTH)
-            .collect::<Col<f32>>();
+    pub fn new(frame_length: usize) -> Self {
+        let half_frame = frame_length / 2;
+        let hann = trimmed_hann(frame_length);
 
         // 1. Combine hann windows to mimic slicing + overlap-adding
         let mut hann_start = hann.clone();
-        let mut slice = hann_start.subrows_mut(HALF_FRAME, HALF_FRAME);
-        slice += &hann.subrows(0, HALF_FRAME);
+        let mut slice = hann_start.subrows_mut(half_frame, half_frame);
+        slice += &hann.subrows(0, half_frame);
         // 2. Apply hann again to account for the reslicing just before rfft
         zip!(&mut hann_start, &hann).for_each(|unzip!(w1, &w2)| *w1 *= w2);
 
         // 1. Combine hann windows to mimic slicing + overlap-adding
         let mut hann_center = hann.clone();
-        let mut slice = hann_center.subrows_mut(0, HALF_FRAME);
-        slice += &hann.subrows(HALF_FRAME, HALF_FRAME);
-        let mut slice = hann_center.subrows_mut(HALF_FRAME, HALF_FRAME);
-        slice += &hann.subrows(0, HALF_FRAME);
+        let mut slice = hann_center.subrows_mut(0, half_frame);
+        slice += &hann.subrows(half_frame, half_frame);
+        let mut slice = hann_center.subrows_mut(half_frame, half_frame);
+        slice += &hann.subrows(0, half_frame);
         // 2. Apply hann again to account for the reslicing just before rfft
         zip!(&mut hann_center, &hann).for_each(|unzip!(w1, &w2)| *w1 *= w2);
 
@@ -45,108 +52,151 @@ impl FrameWindows {
             hann_center,
         }
     }
-}
 
-lazy_static! {
-    static ref FRAME_WINDOWS: FrameWindows = FrameWindows::new();
-}
+    /// Plain hann windows, applied to each frame as is,
+    /// without mimicking the overlap-add of the original stoi implementation.
+    #[cfg(feature = "std")]
+    pub fn plain(frame_length: usize) -> Self {
+        let hann = trimmed_hann(frame_length);
 
-/// Slice 2 input signals into overlapping frames and
-/// applies a hann window to each frame.
-/// The frames are then filtered based on their energy.
-///
-/// Returns 2D arrays containing the frames with shape
-/// (frame_length, n_frames) along with a boolean mask
-/// and the total amount of valid frames.
-///
-/// Performance notes:
-/// Energy-based filtering is performed once all energies have been computed.
-/// For this reason, we cannot know beforehand which frames are to be discarded,
-/// hence why we store all frames in an intermediate 2D array.
-/// In order to avoid reallocations, we return the unfiltered 2D array along
-/// with a boolean mask indicating which frames to keep.
-pub fn process_frames(x: &[f32], y: &[f32]) -> (Mat<f32>, Mat<f32>, Col<bool>, usize) {
-    // 1. Compute frames and energies
-    let n = 1 + (x.len() - FRAME_LENGTH - 1) / HOP_LENGTH;
-    let mut x_frames = Mat::<f32>::zeros(FRAME_LENGTH, n);
-    let mut y_frames = Mat::<f32>::zeros(FRAME_LENGTH, n);
-    let mut energies = Col::<f32>::zeros(n);
-
-    for (i, start) in (0..x.len() - FRAME_LENGTH).step_by(HOP_LENGTH).enumerate() {
-        // Compute the energy for the current x frame
-        let end = start + FRAME_LENGTH;
-
-        let mut x_frame = x_frames.col_mut(i);
-        let mut y_frame = y_frames.col_mut(i);
-
-        // Copy frames
-        x_frame.copy_from(ColRef::from_slice(&x[start..end]));
-        y_frame.copy_from(ColRef::from_slice(&y[start..end]));
+        Self {
+            hann_start: hann.clone(),
+            hann_center: hann.clone(),
+            hann,
+        }
+    }
+
+    /// Frame length
+    pub fn frame_length(&self) -> usize {
+        self.hann.nrows()
+    }
+
+    /// Hop length between consecutive frames (half overlap)
+    pub fn hop_length(&self) -> usize {
+        self.hann.nrows() / 2
+    }
+
+    /// Select the frames of the clean signal to keep based on their energy.
+    ///
+    /// Returns the start indices of the valid frames in the signal.
+    ///
+    /// Performance notes:
+    /// Energy-based filtering needs the maximum energy over the whole signal,
+    /// so we perform a first streaming pass that only computes frame energies
+    /// without storing the frames themselves.
+    /// The surviving frames are then windowed on the fly with `window_frame`
+    /// right before their RFFT, which avoids storing every raw frame in an
+    /// intermediate 2D array.
+    pub fn valid_frames(&self, x: &[f32]) -> Vec<usize> {
+        self.valid_multichannel_frames(&[x])
+    }
+
+    /// Select the frames of a clean multichannel signal to keep: a frame is kept
+    /// if it is non-silent in any channel, compared to the loudest frame of that channel.
+    /// See `valid_frames`.
+    pub fn valid_multichannel_frames(&self, channels: &[&[f32]]) -> Vec<usize> {
+        let hop_length = self.hop_length();
+
+        // 1. Compute frame energies
+        let energies = channels
+            .iter()
+            .map(|x| self.frame_energies(x))
+            .collect::<Vec<_>>();
+
+        // 2. Keep frames based on energies
+        let Some(thresholds) = energies
+            .iter()
+            .map(|energies| energies.iter().copied().reduce(f32::max))
+            .map(|max| max.map(|max| max - DYNAMIC_RANGE))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Vec::new();
+        };
+
+        let mut frames = (0..energies[0].len())
+            .filter(|&i| {
+                energies
+                    .iter()
+                    .zip(&thresholds)
+                    .any(|(energies, &threshold)| energies[i] >= threshold)
+            })
+            .map(|i| i * hop_length)
+            .collect::<Vec<_>>();
+
+        // 3. Discard the last valid frame as the original implementation does (bad slicing)
+        frames.pop();
+
+        frames
+    }
+
+    /// Compute the energies of the frames of a signal.
+    fn frame_energies(&self, x: &[f32]) -> Vec<f32> {
+        let hop_length = self.hop_length();
+        let n = x
+            .len()
+            .saturating_sub(self.frame_length())
+            .div_ceil(hop_length);
+
+        #[cfg(feature = "rayon")]
+        if n >= PARALLEL_MIN_FRAMES {
+            use rayon::prelude::*;
+
+            return (0..n)
+                .into_par_iter()
+                .map(|i| self.frame_energy(x, i * hop_length))
+                .collect();
+        }
+
+        (0..n)
+            .map(|i| self.frame_energy(x, i * hop_length))
+            .collect()
+    }
 
+    /// Compute the energy of the frame starting at `start`.
+    ///
+    /// Note that we do not window the frames here,
+    /// because due to the original stoi implementation
+    /// 1. applying hann
+    /// 2. rebuilding the signal by overlap-adding the frames
+    /// 3. slicing and applying hann again
+    ///
+    /// the resulting window that is effectively applied to each frame
+    /// is a little different.
+    fn frame_energy(&self, x: &[f32], start: usize) -> f32 {
         // Compute the frame norm after applying hann window
-        // Note that we do not apply hann window to the frame in place,
-        // because due to the original stoi implementation
-        // 1. applying hann
-        // 2. rebuilding the signal by overlap-adding the frames
-        // 3. slicing and applying hann again
-        // the resulting window that is effectively applied to each frame
-        // is a little different.
-        let frame_norm = zip!(&x_frame, &FRAME_WINDOWS.hann)
+        let frame = ColRef::from_slice(&x[start..start + self.frame_length()]);
+        let frame_norm = zip!(frame, &self.hann)
             .map(|unzip!(x, w)| (x * w).powi(2))
             .sum()
             .sqrt();
 
-        // Compute frame energy
-        energies[i] = 20.0 * (frame_norm + f32::EPSILON).log10();
+        20.0 * (frame_norm + f32::EPSILON).log10()
     }
 
-    // 2. Compute frame mask based on energies
-    let threshold = energies.max().unwrap() - DYNAMIC_RANGE;
-    let mut count = 0;
-    let mut mask = energies
-        .iter()
-        .map(|&e| {
-            let valid = e >= threshold;
-            count += valid as usize;
-            valid
-        })
-        .collect::<Col<_>>();
-
-    // 3. Discard the last valid frame as the original implementation does (bad slicing)
-    // and then apply the combined hann window to each valid frame to mimic the result
-    // from slicing, overlap-adding and slicing again.
-    let mut index = 0;
-    x_frames
-        .col_iter_mut()
-        .zip(y_frames.col_iter_mut())
-        .zip(mask.iter_mut())
-        .for_each(|((mut x_frame, mut y_frame), valid)| {
-            if !*valid {
-                return;
-            }
-
-            // First valid frame: apply hann_start
-            if index == 0 {
-                zip!(&mut x_frame, &FRAME_WINDOWS.hann_start).for_each(|unzip!(w1, &w2)| *w1 *= w2);
-                zip!(&mut y_frame, &FRAME_WINDOWS.hann_start).for_each(|unzip!(w1, &w2)| *w1 *= w2);
-            }
-            // Last valid frame: discard it
-            else if index == count - 1 {
-                *valid = false;
-            } else {
-                // Center frames: apply hann center
-                zip!(&mut x_frame, &FRAME_WINDOWS.hann_center)
-                    .for_each(|unzip!(w1, &w2)| *w1 *= w2);
-                zip!(&mut y_frame, &FRAME_WINDOWS.hann_center)
-                    .for_each(|unzip!(w1, &w2)| *w1 *= w2);
-            }
-
-            index += 1;
-        });
-
-    count -= 1; // account for the discarded last frame
-
-    (x_frames, y_frames, mask, count)
+    /// Copy the frame starting at `start` into `output` and apply the combined
+    /// hann window that mimics the result from slicing, overlap-adding and slicing again.
+    /// The first valid frame only overlaps with the following frame.
+    pub fn window_frame(&self, signal: &[f32], start: usize, first: bool, output: &mut [f32]) {
+        let window = if first {
+            &self.hann_start
+        } else {
+            &self.hann_center
+        };
+
+        output
+            .iter_mut()
+            .zip(&signal[start..start + self.frame_length()])
+            .zip(window.iter())
+            .for_each(|((out, x), w)| *out = x * w);
+    }
+}
+
+/// Hann window of `frame_length + 2` samples without its zero end points
+fn trimmed_hann(frame_length: usize) -> Col<f32> {
+    window(frame_length + 2, WindowFunction::Hann, Symmetry::Symmetric)
+        .skip(1)
+        .take(frame_length)
+        .collect::<Col<f32>>()
 }
 
 /// Slice octave band spectrogram into overlapping segments
@@ -0,0 +1,323 @@
+//! Hearing loss simulation from an audiogram
+//!
+//! The processed signal is analysed with a short-time Fourier transform, and each
+//! frequency bin is transformed as heard by an impaired ear:
+//! 1. Spectral smearing: the power spectrum is smoothed with auditory filters
+//!    broadened by a given factor (Baer & Moore, 1993), simulating reduced frequency selectivity.
+//! 2. Threshold elevation: levels are reduced by the hearing loss interpolated from the audiogram,
+//!    and components below the elevated threshold become inaudible.
+//!    Hearing losses in dB HL are relative to the normal hearing threshold: the threshold of the
+//!    impaired ear in dB SPL is the reference threshold (RETSPL) plus the hearing loss.
+//! 3. Loudness recruitment (optional): instead of a plain attenuation, levels are expanded so that
+//!    they rise from the normal threshold at the elevated threshold to the uncomfortable level
+//!    (Moore & Glasberg, 1993).
+//!
+//! The signal is then resynthesized by overlap-adding the frames.
+
+use std::f32::consts::PI;
+
+use realfft::RealFftPlanner;
+
+/// Level in dB SPL at which loudness is the same for normal and impaired ears with recruitment
+const UNCOMFORTABLE_LEVEL: f32 = 100.0;
+
+/// Reference hearing thresholds in dB SPL of otologically normal listeners, in free field
+/// (ISO 389-7:2005), as (frequency in Hz, threshold in dB SPL) pairs.
+const RETSPL: [(f32, f32); 38] = [
+    (20.0, 78.1),
+    (25.0, 68.7),
+    (31.5, 59.5),
+    (40.0, 51.1),
+    (50.0, 44.0),
+    (63.0, 37.5),
+    (80.0, 31.5),
+    (100.0, 26.5),
+    (125.0, 22.1),
+    (160.0, 17.9),
+    (200.0, 14.4),
+    (250.0, 11.4),
+    (315.0, 8.6),
+    (400.0, 6.2),
+    (500.0, 4.4),
+    (630.0, 3.0),
+    (750.0, 2.4),
+    (800.0, 2.2),
+    (1000.0, 2.4),
+    (1250.0, 3.5),
+    (1500.0, 2.4),
+    (1600.0, 1.7),
+    (2000.0, -1.3),
+    (2500.0, -4.2),
+    (3000.0, -5.8),
+    (3150.0, -6.0),
+    (4000.0, -5.4),
+    (5000.0, -1.5),
+    (6000.0, 4.3),
+    (6300.0, 6.0),
+    (8000.0, 12.6),
+    (9000.0, 13.9),
+    (10000.0, 13.9),
+    (11200.0, 13.0),
+    (12500.0, 12.3),
+    (14000.0, 18.4),
+    (16000.0, 40.2),
+    (18000.0, 73.2),
+];
+
+/// Hearing loss described by an audiogram, applied to processed signals before framing.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HearingLoss {
+    audiogram: Vec<(f32, f32)>,
+    recruitment: bool,
+    smearing: f32,
+    full_scale_level: f32,
+}
+
+impl HearingLoss {
+    /// Create a hearing loss from an audiogram given as (frequency in Hz, hearing loss in dB HL)
+    /// pairs at the audiometric frequencies, sorted by frequency.
+    ///
+    /// The hearing loss is interpolated linearly on a logarithmic frequency axis,
+    /// and kept constant below the first and above the last audiometric frequencies.
+    pub fn new(audiogram: &[(f32, f32)]) -> Self {
+        assert!(!audiogram.is_empty(), "The audiogram must not be empty");
+        assert!(
+            audiogram.windows(2).all(|w| w[0].0 < w[1].0),
+            "Audiogram frequencies must be sorted"
+        );
+
+        Self {
+            audiogram: audiogram.to_vec(),
+            recruitment: false,
+            smearing: 1.0,
+            full_scale_level: 100.0,
+        }
+    }
+
+    /// Simulate loudness recruitment instead of a plain attenuation
+    pub fn with_recruitment(mut self, recruitment: bool) -> Self {
+        self.recruitment = recruitment;
+        self
+    }
+
+    /// Smear spectra with auditory filters broadened by `factor` (1 disables smearing)
+    pub fn with_smearing(mut self, factor: f32) -> Self {
+        assert!(factor >= 1.0, "The broadening factor must be at least 1");
+        self.smearing = factor;
+        self
+    }
+
+    /// Level in dB SPL of a full-scale signal (RMS of 1). Defaults to 100 dB SPL.
+    pub fn with_full_scale_level(mut self, level: f32) -> Self {
+        self.full_scale_level = level;
+        self
+    }
+
+    /// Hearing loss in dB HL at a given frequency
+    pub fn threshold(&self, freq: f32) -> f32 {
+        interpolate(&self.audiogram, freq)
+    }
+
+    /// Hearing threshold of the impaired ear in dB SPL at a given frequency
+    pub fn threshold_spl(&self, freq: f32) -> f32 {
+        normal_threshold(freq) + self.threshold(freq)
+    }
+
+    /// Simulate the hearing loss on a signal at sampling frequency `fs`,
+    /// with hann frames of `frame_length` samples and half overlap.
+    pub(crate) fn apply(&self, signal: &[f32], fs: usize, frame_length: usize) -> Vec<f32> {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let r2c = planner.plan_fft_forward(frame_length);
+        let c2r = planner.plan_fft_inverse(frame_length);
+        let hop = frame_length / 2;
+
+        // Periodic hann analysis window: overlapping windows sum to one with half overlap
+        let window = (0..frame_length)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / frame_length as f32).cos())
+            .collect::<Vec<_>>();
+
+        let n_bins = frame_length / 2 + 1;
+        let freqs = (0..n_bins)
+            .map(|k| k as f32 * fs as f32 / frame_length as f32)
+            .collect::<Vec<_>>();
+        let thresholds = freqs
+            .iter()
+            .map(|&f| (normal_threshold(f), self.threshold(f)))
+            .collect::<Vec<_>>();
+        let smearing = (self.smearing > 1.0).then(|| smearing_matrix(&freqs, self.smearing));
+
+        // Convert bin magnitudes to dB SPL: a full-scale sine peaks at frame_length / (2 sqrt 2)
+        let level_offset =
+            self.full_scale_level + 20.0 * (2.0 * 2.0_f32.sqrt() / frame_length as f32).log10();
+
+        // Pad the signal so that every sample is covered by two frames
+        let mut padded = vec![0.0; signal.len() + 2 * frame_length];
+        padded[hop..hop + signal.len()].copy_from_slice(signal);
+        let mut output = vec![0.0; padded.len()];
+
+        let mut input = r2c.make_input_vec();
+        let mut spectrum = r2c.make_output_vec();
+        let mut power = vec![0.0; n_bins];
+        let mut frame = c2r.make_output_vec();
+
+        for start in (0..padded.len() - frame_length + 1).step_by(hop) {
+            input
+                .iter_mut()
+                .zip(padded[start..start + frame_length].iter().zip(&window))
+                .for_each(|(i, (x, w))| *i = x * w);
+            r2c.process(&mut input, &mut spectrum).unwrap();
+
+            // Smeared power spectrum (or the original one)
+            power
+                .iter_mut()
+                .zip(&spectrum)
+                .for_each(|(p, s)| *p = s.norm_sqr());
+            if let Some(smearing) = &smearing {
+                let original = power.clone();
+                power.iter_mut().zip(smearing).for_each(|(p, weights)| {
+                    *p = weights.iter().zip(&original).map(|(w, o)| w * o).sum();
+                });
+            }
+
+            spectrum.iter_mut().zip(&power).zip(&thresholds).for_each(
+                |((s, &p), &(normal, loss))| {
+                    let level = 10.0 * p.log10() + level_offset;
+                    let gain = match self.impaired_level(level, normal, loss) {
+                        // Keep the original phase
+                        Some(impaired) => {
+                            10.0_f32.powf((impaired - level_offset) / 20.0)
+                                / (s.norm() + f32::EPSILON)
+                        }
+                        None => 0.0,
+                    };
+                    *s *= gain;
+                },
+            );
+            spectrum[0].im = 0.0;
+            spectrum[n_bins - 1].im = 0.0;
+
+            c2r.process(&mut spectrum, &mut frame).unwrap();
+            output[start..start + frame_length]
+                .iter_mut()
+                .zip(&frame)
+                .for_each(|(o, f)| *o += f / frame_length as f32);
+        }
+
+        output[hop..hop + signal.len()].to_vec()
+    }
+
+    /// Level in dB SPL that a normal ear would hear as the impaired ear hears `level`,
+    /// given the normal threshold in dB SPL and the hearing loss in dB HL,
+    /// or `None` if it is inaudible.
+    fn impaired_level(&self, level: f32, normal: f32, loss: f32) -> Option<f32> {
+        let threshold = normal + loss;
+        if level <= threshold {
+            return None;
+        }
+
+        if self.recruitment {
+            if threshold >= UNCOMFORTABLE_LEVEL {
+                return None;
+            }
+            Some(
+                normal
+                    + (UNCOMFORTABLE_LEVEL - normal) * (level - threshold)
+                        / (UNCOMFORTABLE_LEVEL - threshold),
+            )
+        } else {
+            Some(level - loss)
+        }
+    }
+}
+
+/// Normal hearing threshold in dB SPL at a given frequency
+fn normal_threshold(freq: f32) -> f32 {
+    interpolate(&RETSPL, freq)
+}
+
+/// Interpolate (frequency, level) pairs sorted by frequency linearly on a logarithmic
+/// frequency axis, keeping levels constant below the first and above the last frequencies.
+fn interpolate(table: &[(f32, f32)], freq: f32) -> f32 {
+    let (first, last) = (table[0], table[table.len() - 1]);
+    if freq <= first.0 {
+        return first.1;
+    }
+    if freq >= last.0 {
+        return last.1;
+    }
+
+    let i = table.partition_point(|&(f, _)| f <= freq);
+    let ((f0, l0), (f1, l1)) = (table[i - 1], table[i]);
+    let t = (freq / f0).log2() / (f1 / f0).log2();
+
+    l0 + t * (l1 - l0)
+}
+
+/// Equivalent rectangular bandwidth of the normal auditory filter at a frequency (Glasberg & Moore)
+fn erb(freq: f32) -> f32 {
+    24.7 * (4.37 * freq / 1000.0 + 1.0)
+}
+
+/// Normalized roex weights smearing each bin with auditory filters broadened by `factor`
+fn smearing_matrix(freqs: &[f32], factor: f32) -> Vec<Vec<f32>> {
+    freqs
+        .iter()
+        .map(|&center| {
+            let p = 4.0 * center.max(freqs[1]) / (factor * erb(center));
+            let weights = freqs
+                .iter()
+                .map(|&f| {
+                    let g = (f - center).abs() / center.max(freqs[1]);
+                    (1.0 + p * g) * (-p * g).exp()
+                })
+                .collect::<Vec<_>>();
+            let total = weights.iter().sum::<f32>();
+            weights.iter().map(|w| w / total).collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_in_spl() {
+        let loss = HearingLoss::new(&[(500.0, 20.0), (4000.0, 60.0)]);
+
+        assert!((loss.threshold(1000.0) - (20.0 + 40.0 / 3.0)).abs() < 1e-4);
+        assert!((loss.threshold_spl(1000.0) - (2.4 + 20.0 + 40.0 / 3.0)).abs() < 1e-4);
+        assert!((loss.threshold_spl(4000.0) - (-5.4 + 60.0)).abs() < 1e-4);
+
+        // Reference thresholds are interpolated on a logarithmic frequency axis
+        let between = (2000.0_f32 * 2500.0).sqrt();
+        assert!((normal_threshold(between) - (-1.3 - 4.2) / 2.0).abs() < 1e-4);
+        assert_eq!(normal_threshold(10.0), 78.1);
+        assert_eq!(normal_threshold(20_000.0), 73.2);
+    }
+
+    #[test]
+    fn normal_hearing_is_transparent() {
+        for recruitment in [false, true] {
+            let normal = HearingLoss::new(&[(1000.0, 0.0)]).with_recruitment(recruitment);
+
+            assert_eq!(normal.impaired_level(60.0, 2.4, 0.0), Some(60.0));
+            assert_eq!(normal.impaired_level(2.0, 2.4, 0.0), None);
+        }
+    }
+
+    #[test]
+    fn elevated_thresholds() {
+        let loss = HearingLoss::new(&[(1000.0, 40.0)]);
+        assert_eq!(loss.impaired_level(42.0, 2.4, 40.0), None);
+        assert_eq!(loss.impaired_level(70.0, 2.4, 40.0), Some(30.0));
+
+        // With recruitment, levels rise from the normal threshold to the uncomfortable level
+        let loss = loss.with_recruitment(true);
+        let just_audible = loss.impaired_level(42.5, 2.4, 40.0).unwrap();
+        assert!(just_audible > 2.4 && just_audible < 5.0, "{just_audible}");
+        assert_eq!(loss.impaired_level(100.0, 2.4, 40.0), Some(100.0));
+        assert_eq!(loss.impaired_level(100.0, 2.4, 100.0), None);
+    }
+}
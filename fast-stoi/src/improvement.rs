@@ -0,0 +1,159 @@
+//! STOI improvement from noisy to enhanced signals
+
+use faer::prelude::*;
+
+use crate::{
+    constants::{PARALLEL_MIN_FRAMES, SEGMENT_LENGTH},
+    engine::Engine,
+    errors::{NotEnoughFramesError, Result},
+    extended, parallel,
+    pipeline::Segments,
+    standard,
+};
+
+/// STOI contributions of each band and segment, of shape (num_bands, n_segments).
+/// The STOI score is their mean.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BandSegmentScores {
+    scores: Mat<f32>,
+}
+
+impl BandSegmentScores {
+    /// Contributions of shape (num_bands, n_segments)
+    pub fn as_mat(&self) -> MatRef<'_, f32> {
+        self.scores.as_ref()
+    }
+
+    /// Mean contribution of each band over segments
+    pub fn per_band(&self) -> Vec<f32> {
+        self.scores
+            .row_iter()
+            .map(|row| row.sum() / row.ncols() as f32)
+            .collect()
+    }
+
+    /// Mean contribution of each segment over bands
+    pub fn per_segment(&self) -> Vec<f32> {
+        self.scores
+            .col_iter()
+            .map(|col| col.sum() / col.nrows() as f32)
+            .collect()
+    }
+}
+
+/// STOI of the noisy and enhanced signals against the same clean reference.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StoiImprovement {
+    /// STOI of the noisy signal
+    pub noisy: f32,
+    /// STOI of the enhanced signal
+    pub enhanced: f32,
+    /// Improvement from the noisy to the enhanced signal
+    pub delta: f32,
+    /// Per band and segment contributions of the noisy signal, if requested
+    pub noisy_details: Option<BandSegmentScores>,
+    /// Per band and segment contributions of the enhanced signal, if requested
+    pub enhanced_details: Option<BandSegmentScores>,
+}
+
+/// Compute the STOI improvement from `noisy` to `enhanced` against the `clean` reference.
+pub fn stoi_improvement(
+    engine: &Engine,
+    clean: &[f32],
+    noisy: &[f32],
+    enhanced: &[f32],
+    fs_sig: usize,
+    extended: bool,
+    detailed: bool,
+) -> Result<StoiImprovement> {
+    assert!(
+        noisy.len() == clean.len() && enhanced.len() == clean.len(),
+        "Input signals must have the same length"
+    );
+
+    let resampled = [
+        engine.resample(clean, fs_sig),
+        engine.resample_processed(noisy, fs_sig),
+        engine.resample_processed(enhanced, fs_sig),
+    ];
+    let [clean, noisy, enhanced] = &resampled;
+
+    // The clean signal is processed once, and both degraded signals use its frames
+    let frames = engine.frames(clean);
+
+    if frames.len() < SEGMENT_LENGTH {
+        return Err(NotEnoughFramesError);
+    }
+
+    let degraded_segments = |signal: &[f32]| {
+        let envelopes = engine.octave_envelopes(&engine.spectrogram(signal, &frames));
+        engine.segments(&envelopes)
+    };
+
+    let parallel = frames.len() >= PARALLEL_MIN_FRAMES;
+    let (x_segments, (noisy_segments, enhanced_segments)) = parallel::join(
+        parallel,
+        || degraded_segments(clean),
+        || {
+            parallel::join(
+                parallel,
+                || degraded_segments(noisy),
+                || degraded_segments(enhanced),
+            )
+        },
+    );
+    let (x_segments, noisy_segments, enhanced_segments) =
+        (x_segments?, noisy_segments?, enhanced_segments?);
+
+    let score = |y_segments: Segments| {
+        if detailed {
+            let (score, details) = detailed_score(x_segments.clone(), y_segments, extended);
+            (score, Some(details))
+        } else {
+            let score = engine.stoi_from_segments(x_segments.clone(), y_segments, extended);
+            (score, None)
+        }
+    };
+
+    let ((noisy, noisy_details), (enhanced, enhanced_details)) = parallel::join(
+        parallel,
+        || score(noisy_segments),
+        || score(enhanced_segments),
+    );
+
+    Ok(StoiImprovement {
+        noisy,
+        enhanced,
+        delta: enhanced - noisy,
+        noisy_details,
+        enhanced_details,
+    })
+}
+
+/// Compute STOI and its per band and segment contributions from segments.
+/// The score is the same as the one from `Engine::stoi_from_segments`.
+fn detailed_score(mut x: Segments, mut y: Segments, extended: bool) -> (f32, BandSegmentScores) {
+    let num_bands = x.num_bands;
+    let n = x.segments.ncols();
+
+    let (score, scale, values) = if extended {
+        let correlations =
+            extended::correlations(x.segments.as_mut(), y.segments.as_mut(), num_bands);
+        let scale = num_bands as f32 / SEGMENT_LENGTH as f32;
+        let score = correlations.iter().sum::<f32>() / (n as f32) * scale;
+        (score, scale, correlations)
+    } else {
+        let similarities = standard::similarities(x.segments.as_mut(), y.segments.as_mut());
+        let score = similarities.iter().sum::<f32>() / n as f32;
+        (score, 1.0, similarities)
+    };
+
+    // Segment columns are grouped by segment, then by band
+    let scores = Mat::from_fn(num_bands, n / num_bands, |band, segment| {
+        values[segment * num_bands + band] * scale
+    });
+
+    (score, BandSegmentScores { scores })
+}
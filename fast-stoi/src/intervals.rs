@@ -0,0 +1,534 @@
+//! Scoring of labelled intervals inside long recordings
+//!
+//! Intervals can be read from CSV files (`start,end[,label]` in seconds, with an optional header),
+//! RTTM files (`SPEAKER` lines, labelled with the speaker name) and Praat TextGrid files
+//! (non-empty intervals of every interval tier, in long or short text format).
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::{constants::SEGMENT_LENGTH, errors::NotEnoughFramesError};
+
+/// Labelled time interval in seconds
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interval {
+    pub start: f64,
+    pub end: f64,
+    pub label: String,
+}
+
+/// Error while parsing a segmentation file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalParseError {
+    /// Line of the error (1-based)
+    pub line: usize,
+    /// Description of the error
+    pub message: String,
+}
+
+impl Display for IntervalParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for IntervalParseError {}
+
+/// Error while scoring an interval
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IntervalError {
+    /// The interval, clamped to the recordings, has fewer frames than a segment
+    TooShort { frames: usize },
+    /// Not enough non-silent frames
+    NotEnoughFrames(NotEnoughFramesError),
+}
+
+impl Display for IntervalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooShort { frames } => write!(
+                f,
+                "interval shorter than {SEGMENT_LENGTH} frames ({frames} frames)"
+            ),
+            Self::NotEnoughFrames(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for IntervalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NotEnoughFrames(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<NotEnoughFramesError> for IntervalError {
+    fn from(err: NotEnoughFramesError) -> Self {
+        Self::NotEnoughFrames(err)
+    }
+}
+
+/// STOI and ESTOI of one interval
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IntervalScore {
+    pub interval: Interval,
+    /// STOI of the interval, or an error if it is too short or silent
+    pub stoi: std::result::Result<f32, IntervalError>,
+    /// ESTOI of the interval, or an error if it is too short or silent
+    pub estoi: std::result::Result<f32, IntervalError>,
+}
+
+/// Per-interval scores and their duration-weighted aggregate
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IntervalScores {
+    pub intervals: Vec<IntervalScore>,
+    /// Duration-weighted mean STOI of the scored intervals (`None` if none could be scored)
+    pub stoi: Option<f32>,
+    /// Duration-weighted mean ESTOI of the scored intervals (`None` if none could be scored)
+    pub estoi: Option<f32>,
+    /// Total duration of the scored intervals in seconds
+    pub scored_duration: f64,
+}
+
+impl Interval {
+    /// Create an interval from its bounds in seconds
+    pub fn new(start: f64, end: f64, label: impl Into<String>) -> Self {
+        assert!(
+            0.0 <= start && start < end,
+            "Intervals must start at a non-negative time and not be empty"
+        );
+
+        Self {
+            start,
+            end,
+            label: label.into(),
+        }
+    }
+
+    /// Duration in seconds
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+
+    /// Parse `start,end[,label]` lines in seconds. Tabs and semicolons are also accepted
+    /// as delimiters. Empty lines, `#` comments and a header line are skipped.
+    pub fn from_csv(text: &str) -> std::result::Result<Vec<Self>, IntervalParseError> {
+        let mut intervals = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let delimiter = [',', '\t', ';']
+                .into_iter()
+                .find(|&d| line.contains(d))
+                .ok_or_else(|| error(i, "expected start and end fields"))?;
+            let mut fields = line.splitn(3, delimiter).map(str::trim);
+            let (start, end) = (fields.next().unwrap_or(""), fields.next().unwrap_or(""));
+            let label = fields.next().unwrap_or("").trim_matches('"');
+
+            match (start.parse::<f64>(), end.parse::<f64>()) {
+                (Ok(start), Ok(end)) => intervals.push(checked(i, start, end, label)?),
+                // Header line
+                _ if intervals.is_empty() && !has_data_before(text, i) => continue,
+                _ => return Err(error(i, "start and end must be numbers")),
+            }
+        }
+
+        Ok(intervals)
+    }
+
+    /// Parse the `SPEAKER` lines of an RTTM file, labelled with the speaker name.
+    /// Other line types are skipped.
+    pub fn from_rttm(text: &str) -> std::result::Result<Vec<Self>, IntervalParseError> {
+        let mut intervals = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.first() != Some(&"SPEAKER") {
+                continue;
+            }
+            if fields.len() < 8 {
+                return Err(error(i, "SPEAKER lines must have at least 8 fields"));
+            }
+
+            let (Ok(start), Ok(duration)) = (fields[3].parse::<f64>(), fields[4].parse::<f64>())
+            else {
+                return Err(error(i, "onset and duration must be numbers"));
+            };
+            intervals.push(checked(i, start, start + duration, fields[7])?);
+        }
+
+        Ok(intervals)
+    }
+
+    /// Parse the non-empty intervals of every interval tier of a Praat TextGrid,
+    /// in long or short text format, labelled with their text. Point tiers are skipped.
+    pub fn from_textgrid(text: &str) -> std::result::Result<Vec<Self>, IntervalParseError> {
+        let mut tokens = TextGridTokens::new(text);
+
+        // File type, object class, xmin, xmax and tier count
+        let file_type = tokens.string()?;
+        if file_type != "ooTextFile" || tokens.string()? != "TextGrid" {
+            return Err(error(0, "not a TextGrid text file"));
+        }
+        tokens.number()?;
+        tokens.number()?;
+        let tiers = tokens.count()?;
+
+        let mut intervals = Vec::new();
+        for _ in 0..tiers {
+            let class = tokens.string()?;
+            tokens.string()?; // Tier name
+            tokens.number()?;
+            tokens.number()?;
+            let n = tokens.count()?;
+
+            match class.as_str() {
+                "IntervalTier" => {
+                    for _ in 0..n {
+                        let (start, end) = (tokens.number()?, tokens.number()?);
+                        let label = tokens.string()?;
+                        if !label.trim().is_empty() {
+                            intervals.push(checked(tokens.line, start, end, label.trim())?);
+                        }
+                    }
+                }
+                "TextTier" => {
+                    for _ in 0..n {
+                        tokens.number()?;
+                        tokens.string()?;
+                    }
+                }
+                _ => return Err(error(tokens.line, "unknown tier class")),
+            }
+        }
+
+        Ok(intervals)
+    }
+}
+
+/// Aggregate per-interval scores, weighting them by duration
+pub(crate) fn aggregate(intervals: Vec<IntervalScore>) -> IntervalScores {
+    let weighted_mean = |score: fn(&IntervalScore) -> &std::result::Result<f32, IntervalError>| {
+        let (total, duration) = intervals
+            .iter()
+            .filter_map(|s| score(s).as_ref().ok().map(|v| (s.interval.duration(), *v)))
+            .fold((0.0, 0.0), |(total, duration), (d, v)| {
+                (total + d * v as f64, duration + d)
+            });
+        (duration > 0.0).then(|| (total / duration) as f32)
+    };
+
+    let scored_duration = intervals
+        .iter()
+        .filter(|s| s.stoi.is_ok())
+        .map(|s| s.interval.duration())
+        .sum();
+
+    IntervalScores {
+        stoi: weighted_mean(|s| &s.stoi),
+        estoi: weighted_mean(|s| &s.estoi),
+        scored_duration,
+        intervals,
+    }
+}
+
+/// Sample range of an interval in a signal of `len` samples at `fs`, or an error
+/// if it has fewer frames of `frame_length` samples than a segment once clamped to the signal.
+pub(crate) fn sample_range(
+    interval: &Interval,
+    fs: usize,
+    len: usize,
+    frame_length: usize,
+) -> std::result::Result<std::ops::Range<usize>, IntervalError> {
+    let start = ((interval.start * fs as f64).round() as usize).min(len);
+    let end = ((interval.end * fs as f64).round() as usize).min(len);
+
+    // Frames with half overlap, without the last one (see `FrameWindows::valid_frames`)
+    let frames = (end.saturating_sub(start))
+        .saturating_sub(frame_length)
+        .div_ceil(frame_length / 2)
+        .saturating_sub(1);
+    if frames < SEGMENT_LENGTH {
+        return Err(IntervalError::TooShort { frames });
+    }
+
+    Ok(start..end)
+}
+
+/// Whether a CSV text has a data line before line `index`
+fn has_data_before(text: &str, index: usize) -> bool {
+    text.lines()
+        .take(index)
+        .any(|line| !line.trim().is_empty() && !line.trim().starts_with('#'))
+}
+
+/// Create an interval, checking its bounds
+fn checked(
+    line: usize,
+    start: f64,
+    end: f64,
+    label: &str,
+) -> std::result::Result<Interval, IntervalParseError> {
+    if !(0.0 <= start && start < end) {
+        return Err(error(
+            line,
+            "intervals must start at a non-negative time and not be empty",
+        ));
+    }
+
+    Ok(Interval::new(start, end, label))
+}
+
+/// Create a parse error at a 0-based line index
+fn error(line: usize, message: &str) -> IntervalParseError {
+    IntervalParseError {
+        line: line + 1,
+        message: message.to_string(),
+    }
+}
+
+/// Values of a TextGrid in file order. Keys (`xmin = `), item headers (`intervals [1]:`)
+/// and the `<exists>` flag are skipped, so that long and short formats yield the same values.
+struct TextGridTokens<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+    line: usize,
+}
+
+impl<'a> TextGridTokens<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            lines: text.lines().enumerate(),
+            line: 0,
+        }
+    }
+
+    /// Next raw value
+    fn next(&mut self) -> std::result::Result<&'a str, IntervalParseError> {
+        for (i, line) in self.lines.by_ref() {
+            self.line = i;
+            let line = line.trim();
+            let value = match line.split_once('=') {
+                // Keep `=` inside strings in short format lines
+                Some((key, value)) if !key.trim_start().starts_with('"') => value.trim(),
+                _ => line,
+            };
+
+            if value.is_empty() || value.ends_with(':') || value.starts_with('<') {
+                continue;
+            }
+            // `tiers? <exists>` in long format
+            if value.starts_with("tiers?") {
+                continue;
+            }
+            return Ok(value);
+        }
+
+        Err(error(self.line, "unexpected end of file"))
+    }
+
+    fn string(&mut self) -> std::result::Result<String, IntervalParseError> {
+        let value = self.next()?;
+        match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(value) => Ok(value.replace("\"\"", "\"")),
+            None => Err(error(self.line, "expected a string")),
+        }
+    }
+
+    fn number(&mut self) -> std::result::Result<f64, IntervalParseError> {
+        let value = self.next()?;
+        value
+            .parse()
+            .map_err(|_| error(self.line, "expected a number"))
+    }
+
+    fn count(&mut self) -> std::result::Result<usize, IntervalParseError> {
+        let value = self.next()?;
+        value
+            .parse()
+            .map_err(|_| error(self.line, "expected a count"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LONG_TEXTGRID: &str = r#"File type = "ooTextFile"
+Object class = "TextGrid"
+
+xmin = 0
+xmax = 3
+tiers? <exists>
+size = 2
+item []:
+    item [1]:
+        class = "IntervalTier"
+        name = "words"
+        xmin = 0
+        xmax = 3
+        intervals: size = 3
+        intervals [1]:
+            xmin = 0
+            xmax = 1.5
+            text = "say ""hi"""
+        intervals [2]:
+            xmin = 1.5
+            xmax = 2
+            text = ""
+        intervals [3]:
+            xmin = 2
+            xmax = 3
+            text = "a = b"
+    item [2]:
+        class = "TextTier"
+        name = "events"
+        xmin = 0
+        xmax = 3
+        points: size = 1
+        points [1]:
+            number = 1.2
+            mark = "click"
+"#;
+
+    const SHORT_TEXTGRID: &str = r#"File type = "ooTextFile"
+Object class = "TextGrid"
+
+0
+3
+<exists>
+2
+"IntervalTier"
+"words"
+0
+3
+3
+0
+1.5
+"say ""hi"""
+1.5
+2
+""
+2
+3
+"a = b"
+"TextTier"
+"events"
+0
+3
+1
+1.2
+"click"
+"#;
+
+    #[test]
+    fn textgrid_formats() {
+        let expected = vec![
+            Interval::new(0.0, 1.5, "say \"hi\""),
+            Interval::new(2.0, 3.0, "a = b"),
+        ];
+        assert_eq!(Interval::from_textgrid(LONG_TEXTGRID).unwrap(), expected);
+        assert_eq!(Interval::from_textgrid(SHORT_TEXTGRID).unwrap(), expected);
+
+        let err = Interval::from_textgrid("File type = \"ooTextFile\"\n\"Pitch\"\n").unwrap_err();
+        assert_eq!(err.line, 1);
+        let err = Interval::from_textgrid(&LONG_TEXTGRID[..300]).unwrap_err();
+        assert_eq!(err.message, "unexpected end of file");
+    }
+
+    #[test]
+    fn csv_header_and_delimiters() {
+        let expected = vec![Interval::new(0.5, 1.25, "a"), Interval::new(2.0, 3.0, "")];
+        for delimiter in [",", "\t", ";"] {
+            let text = format!(
+                "start{d}end{d}label\n# comment\n\n0.5{d}1.25{d}\"a\"\n2{d}3\n",
+                d = delimiter
+            );
+            assert_eq!(
+                Interval::from_csv(&text).unwrap(),
+                expected,
+                "{delimiter:?}"
+            );
+        }
+
+        // No header
+        assert_eq!(Interval::from_csv("0.5,1.25,a\n2,3").unwrap(), expected);
+        // Labels may contain the delimiter
+        assert_eq!(
+            Interval::from_csv("0,1,a, b").unwrap(),
+            vec![Interval::new(0.0, 1.0, "a, b")]
+        );
+
+        let err = Interval::from_csv("0,1\nstart,end\n").unwrap_err();
+        assert_eq!(
+            (err.line, err.message.as_str()),
+            (2, "start and end must be numbers")
+        );
+        let err = Interval::from_csv("0 1\n").unwrap_err();
+        assert_eq!(err.line, 1);
+        let err = Interval::from_csv("2,1\n").unwrap_err();
+        assert_eq!(err.line, 1);
+    }
+
+    #[test]
+    fn rttm_speaker_lines() {
+        let text = "\
+SPKR-INFO meeting 1 <NA> <NA> <NA> unknown alice <NA> <NA>
+SPEAKER meeting 1 0.50 1.25 <NA> <NA> alice <NA> <NA>
+NOSCORE meeting 1 2.00 1.00 <NA> <NA> <NA> <NA> <NA>
+SPEAKER meeting 1 3.00 2.00 <NA> <NA> bob <NA> <NA>
+";
+        assert_eq!(
+            Interval::from_rttm(text).unwrap(),
+            vec![
+                Interval::new(0.5, 1.75, "alice"),
+                Interval::new(3.0, 5.0, "bob"),
+            ]
+        );
+
+        let err = Interval::from_rttm("SPEAKER meeting 1 0.5\n").unwrap_err();
+        assert_eq!(err.line, 1);
+        let err = Interval::from_rttm("\nSPEAKER meeting 1 x 1 <NA> <NA> a\n").unwrap_err();
+        assert_eq!(
+            (err.line, err.message.as_str()),
+            (2, "onset and duration must be numbers")
+        );
+    }
+
+    #[test]
+    fn intervals_shorter_than_a_segment() {
+        const FS: usize = 10_000;
+        // 31 frames of 256 samples with half overlap, the last of which is discarded
+        let min_length = 256 + SEGMENT_LENGTH * 128 + 1;
+
+        let interval = Interval::new(1.0, 1.0 + min_length as f64 / FS as f64, "");
+        assert_eq!(
+            sample_range(&interval, FS, 100_000, 256).unwrap(),
+            FS..FS + min_length
+        );
+
+        let interval = Interval::new(1.0, 1.0 + (min_length - 1) as f64 / FS as f64, "");
+        let Err(IntervalError::TooShort { frames }) = sample_range(&interval, FS, 100_000, 256)
+        else {
+            panic!("the interval must be too short");
+        };
+        assert_eq!(frames, SEGMENT_LENGTH - 1);
+
+        // Clamped to the signal
+        let interval = Interval::new(9.9, 20.0, "");
+        assert!(sample_range(&interval, FS, 100_000, 256).is_err());
+        let interval = Interval::new(20.0, 30.0, "");
+        assert!(matches!(
+            sample_range(&interval, FS, 100_000, 256),
+            Err(IntervalError::TooShort { frames: 0 })
+        ));
+    }
+}
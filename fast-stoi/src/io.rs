@@ -0,0 +1,361 @@
+//! Audio file decoding and scoring (`io` feature)
+//!
+//! WAV files (8, 16, 24 and 32-bit PCM, 32-bit float) are decoded with `hound`,
+//! FLAC and Ogg Vorbis files with `symphonia`. Samples are converted to `f32` in [-1, 1].
+
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use symphonia::core::{
+    audio::SampleBuffer, codecs::DecoderOptions, errors::Error as SymphoniaError,
+    formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+
+use crate::{
+    engine::Engine,
+    mixed_rate::{self, CropPolicy, MixedRateError},
+    multichannel::{self, ChannelPolicy, Multichannel, MultichannelStoi},
+    parallel,
+};
+
+/// Decoded audio file, with interleaved channels
+#[derive(Debug, Clone)]
+pub struct Audio {
+    /// Interleaved samples in [-1, 1]
+    pub samples: Vec<f32>,
+    /// Amount of channels
+    pub channels: usize,
+    /// Sampling frequency in Hz
+    pub fs: usize,
+}
+
+/// Error while reading or decoding an audio file
+#[derive(Debug, Clone)]
+pub struct DecodeError {
+    /// File that could not be decoded
+    pub path: PathBuf,
+    /// Description of the error
+    pub message: String,
+}
+
+/// Error while scoring a pair of clean and processed audio files
+#[derive(Debug, Clone)]
+pub enum FileError {
+    /// A file could not be read or decoded
+    Decode(DecodeError),
+    /// The files cannot be scored against each other with the given options
+    Mismatch {
+        /// Clean file
+        ref_path: PathBuf,
+        /// Processed file
+        deg_path: PathBuf,
+        /// Description of the mismatch
+        message: String,
+    },
+}
+
+/// Options of `stoi_files`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileOptions {
+    /// Whether to use the extended STOI measure
+    pub extended: bool,
+    /// How channels are scored. Defaults to the mean of all channels.
+    pub policy: ChannelPolicy,
+    /// How the signals are brought to the same length once resampled
+    pub crop: CropPolicy,
+}
+
+impl Default for FileOptions {
+    fn default() -> Self {
+        Self {
+            extended: false,
+            policy: ChannelPolicy::Mean,
+            crop: CropPolicy::Shortest,
+        }
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Could not decode {}: {}",
+            self.path.display(),
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl Display for FileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decode(err) => err.fmt(f),
+            Self::Mismatch {
+                ref_path,
+                deg_path,
+                message,
+            } => write!(
+                f,
+                "Could not score {} against {}: {message}",
+                deg_path.display(),
+                ref_path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for FileError {
+    fn from(err: DecodeError) -> Self {
+        Self::Decode(err)
+    }
+}
+
+impl Audio {
+    /// Read and decode an audio file. WAV files are recognized by their extension.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, DecodeError> {
+        let path = path.as_ref();
+        let error = |message: String| DecodeError {
+            path: path.to_path_buf(),
+            message,
+        };
+
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("wav" | "wave") => read_wav(path).map_err(|err| error(err.to_string())),
+            _ => read_symphonia(path, extension.as_deref()).map_err(|err| error(err.to_string())),
+        }
+    }
+
+    /// Channels of the audio file
+    pub fn multichannel(&self) -> Multichannel<'_> {
+        Multichannel::interleaved(&self.samples, self.channels)
+    }
+}
+
+/// Compute STOI between a clean and a processed audio file.
+pub(crate) fn stoi_files(
+    engine: &Engine,
+    ref_path: &Path,
+    deg_path: &Path,
+    options: &FileOptions,
+) -> Result<MultichannelStoi, FileError> {
+    // Both files are decoded in parallel
+    let (x, y) = parallel::join(true, || Audio::read(ref_path), || Audio::read(deg_path));
+    let (x, y) = (x?, y?);
+
+    check_files(&x, &y, options).map_err(|message| FileError::Mismatch {
+        ref_path: ref_path.to_path_buf(),
+        deg_path: deg_path.to_path_buf(),
+        message,
+    })?;
+
+    let result = multichannel::apply_policy(
+        &x.multichannel(),
+        &y.multichannel(),
+        options.policy,
+        |x_channels, y_channels| {
+            x_channels
+                .iter()
+                .zip(y_channels)
+                .map(|(x_channel, y_channel)| {
+                    match engine.stoi_mixed_rate(
+                        x_channel,
+                        x.fs,
+                        y_channel,
+                        y.fs,
+                        options.extended,
+                        options.crop,
+                    ) {
+                        Ok(score) => Ok(score),
+                        Err(MixedRateError::NotEnoughFrames(err)) => Err(err),
+                        Err(MixedRateError::DurationMismatch { .. }) => {
+                            unreachable!("Durations are checked before scoring")
+                        }
+                    }
+                })
+                .collect()
+        },
+    );
+
+    Ok(result)
+}
+
+/// Check that a clean and a processed file can be scored against each other with the
+/// channel policy of `options`, and that they have the same duration.
+fn check_files(x: &Audio, y: &Audio, options: &FileOptions) -> Result<(), String> {
+    if let ChannelPolicy::Reference(channel) = options.policy {
+        if channel >= x.channels {
+            return Err(format!(
+                "reference channel {channel} does not exist, the clean file has {} channels",
+                x.channels
+            ));
+        }
+    } else if x.channels != y.channels && x.channels != 1 {
+        return Err(format!(
+            "the clean file has {} channels and the processed file {}: \
+             the clean file must be mono or have as many channels",
+            x.channels, y.channels
+        ));
+    }
+
+    match options.policy {
+        ChannelPolicy::Channel(channel) if channel >= y.channels => {
+            return Err(format!(
+                "channel {channel} does not exist, the processed file has {} channels",
+                y.channels
+            ));
+        }
+        _ => {}
+    }
+
+    mixed_rate::check_durations(
+        x.samples.len() / x.channels,
+        x.fs,
+        y.samples.len() / y.channels,
+        y.fs,
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// Compute STOI between pairs of clean and processed audio files,
+/// in parallel when the `rayon` feature is enabled.
+pub(crate) fn batch_stoi_files(
+    engine: &Engine,
+    ref_paths: &[&Path],
+    deg_paths: &[&Path],
+    options: &FileOptions,
+) -> Vec<Result<MultichannelStoi, FileError>> {
+    assert!(
+        ref_paths.len() == deg_paths.len(),
+        "There must be as many clean files as processed files"
+    );
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+
+        ref_paths
+            .par_iter()
+            .zip(deg_paths)
+            .map(|(x, y)| stoi_files(engine, x, y, options))
+            .collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        ref_paths
+            .iter()
+            .zip(deg_paths)
+            .map(|(x, y)| stoi_files(engine, x, y, options))
+            .collect()
+    }
+}
+
+/// Decode a WAV file with `hound`
+fn read_wav(path: &Path) -> Result<Audio, hound::Error> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+
+    let samples = match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Float, 32) => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        (hound::SampleFormat::Int, bits @ 1..=32) => {
+            let scale = 1.0 / (1u64 << (bits - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 * scale))
+                .collect::<Result<_, _>>()?
+        }
+        _ => return Err(hound::Error::Unsupported),
+    };
+
+    Ok(Audio {
+        samples,
+        channels: spec.channels as usize,
+        fs: spec.sample_rate as usize,
+    })
+}
+
+/// Decode a FLAC or Ogg Vorbis file with `symphonia`
+fn read_symphonia(path: &Path, extension: Option<&str>) -> Result<Audio, SymphoniaError> {
+    let source = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = extension {
+        hint.with_extension(extension);
+    }
+
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format;
+    let track = format
+        .default_track()
+        .ok_or(SymphoniaError::Unsupported("no audio track"))?;
+    let track_id = track.id;
+    let mut fs = track.codec_params.sample_rate;
+    let mut channels = track.codec_params.channels.map(|channels| channels.count());
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let mut samples = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(err))
+                if err.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                break;
+            }
+            Err(err) => return Err(err),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Skip corrupted packets
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(err) => return Err(err),
+        };
+
+        let spec = *decoded.spec();
+        fs = Some(spec.rate);
+        channels = Some(spec.channels.count());
+
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend_from_slice(buffer.samples());
+    }
+
+    match (fs, channels) {
+        (Some(fs), Some(channels)) if channels > 0 => Ok(Audio {
+            samples,
+            channels,
+            fs: fs as usize,
+        }),
+        _ => Err(SymphoniaError::Unsupported(
+            "unknown sampling rate or channels",
+        )),
+    }
+}
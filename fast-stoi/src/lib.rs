@@ -1,73 +1,489 @@
 //! Rust STOI implementation
+//!
+//! Without the default `std` feature, the crate is `no_std` and only needs an allocator:
+//! float math uses `libm`, and the STOI measure is computed with an [`Engine`] whose windows,
+//! FFT plan and [`Resampler`] filters are built explicitly instead of cached globally.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod batch;
+#[cfg(feature = "std")]
+mod binaural;
+#[cfg(feature = "cache")]
+mod cache;
 mod constants;
+#[cfg(feature = "std")]
+mod drift;
+#[cfg(feature = "std")]
+mod dtw;
+mod engine;
 mod errors;
+#[cfg(feature = "std")]
+mod evaluate;
 mod extended;
+#[cfg(not(feature = "std"))]
+mod fft;
 mod frames;
+#[cfg(feature = "std")]
+mod hearing;
+#[cfg(feature = "std")]
+mod improvement;
+#[cfg(feature = "std")]
+mod intervals;
+#[cfg(feature = "io")]
+mod io;
+#[cfg(feature = "std")]
+mod mapping;
+#[cfg(not(feature = "std"))]
+mod math;
+#[cfg(feature = "std")]
+mod mixed_rate;
+#[cfg(feature = "std")]
+mod multichannel;
+#[cfg(feature = "std")]
+mod ncm;
 mod octave;
+mod parallel;
+pub mod pipeline;
+#[cfg(feature = "std")]
+mod pit;
 mod resample;
+#[cfg(feature = "std")]
+mod siib;
+#[cfg(feature = "std")]
+mod snr;
 mod standard;
 mod stft;
+#[cfg(feature = "std")]
+mod sweep;
 mod upfirdn;
+#[cfg(feature = "std")]
+mod vocoder;
 
-use crate::{
-    constants::{FS, SEGMENT_LENGTH},
+pub use faer;
+#[cfg(any(feature = "io", feature = "cache"))]
+use std::path::Path;
+
+#[cfg(feature = "cache")]
+pub use crate::cache::{CACHE_VERSION, CacheError, Compatibility, ReferenceCache};
+#[cfg(feature = "std")]
+use crate::engine::CLASSIC;
+#[cfg(feature = "io")]
+pub use crate::io::{Audio, DecodeError, FileError, FileOptions};
+pub use crate::{
+    batch::LANES,
+    constants::{FFT_BINS, FFT_LENGTH, FS, NUM_BANDS, SEGMENT_LENGTH},
+    engine::{Config, Engine},
     errors::{NotEnoughFramesError, Result},
+    pipeline::{Envelopes, Frames, Reference, Segments, Spectrogram},
+    resample::Resampler,
+};
+#[cfg(feature = "std")]
+pub use crate::{
+    drift::{ClockDrift, DriftCompensatedStoi},
+    dtw::DtwStoi,
+    evaluate::{Metric, Scores},
+    hearing::HearingLoss,
+    improvement::{BandSegmentScores, StoiImprovement},
+    intervals::{Interval, IntervalError, IntervalParseError, IntervalScore, IntervalScores},
+    mapping::Mapping,
+    mixed_rate::{CropPolicy, MixedRateError},
+    multichannel::{ChannelPolicy, Layout, Multichannel, MultichannelStoi},
+    pipeline::{octave_envelopes, stoi_from_envelopes, stoi_from_segments, stoi_from_spectrogram},
+    pit::PitStoi,
+    sweep::{Noise, ProcessedLengthError, SnrSweep, SweepError},
+    vocoder::{Carrier, Vocoder},
 };
 
-/// Do the full computation post resampling to 10kHz
-fn compute(x: &[f32], y: &[f32], extended: bool) -> Result<f32> {
-    // Compute frames
-    let (x_frames, y_frames, mask, count) = frames::process_frames(x, y);
+/// Compute the Short-Time Objective Intelligibility (STOI) measure between two signals.
+///
+/// Args:
+/// * `x` - Clean speech signal
+/// * `y` - Processed speech signal
+/// * `fs_sig` - Sampling frequency of the signals
+/// * `extended` - Whether to use the extended STOI measure
+///
+/// Signals longer than 20s are split across threads when the `rayon` feature
+/// is enabled. The result does not depend on the amount of threads.
+///
+/// This uses the classic STOI configuration. See [`Engine`] for other configurations.
+#[cfg(feature = "std")]
+pub fn stoi(x: &[f32], y: &[f32], fs_sig: usize, extended: bool) -> Result<f32> {
+    CLASSIC.stoi(x, y, fs_sig, extended)
+}
+
+/// Compute the STOI measure between signals at different sampling frequencies,
+/// such as a 48kHz clean corpus and 16kHz processed outputs.
+///
+/// Each signal is resampled to the internal 10kHz rate with the polyphase resampler
+/// used by `stoi`, and the few samples of length difference left by resampling
+/// are handled with the `crop` policy.
+///
+/// Args:
+/// * `x` - Clean speech signal
+/// * `fs_x` - Sampling frequency of the clean signal
+/// * `y` - Processed speech signal
+/// * `fs_y` - Sampling frequency of the processed signal
+/// * `extended` - Whether to use the extended STOI measure
+/// * `crop` - How both signals are brought to the same length
+///
+/// Fails with [`MixedRateError::DurationMismatch`] if the durations of the signals differ
+/// by more than 20ms, or if there are not enough non-silent frames.
+#[cfg(feature = "std")]
+pub fn stoi_mixed_rate(
+    x: &[f32],
+    fs_x: usize,
+    y: &[f32],
+    fs_y: usize,
+    extended: bool,
+    crop: CropPolicy,
+) -> std::result::Result<f32, MixedRateError> {
+    CLASSIC.stoi_mixed_rate(x, fs_x, y, fs_y, extended, crop)
+}
 
-    if count < SEGMENT_LENGTH {
-        return Err(NotEnoughFramesError);
-    }
+/// Compute the STOI measure between a clean and a processed audio file (`io` feature).
+///
+/// WAV files (8, 16, 24 and 32-bit PCM, 32-bit float) and FLAC or Ogg Vorbis files are decoded
+/// in pure Rust, both files in parallel. The sampling frequencies are read from the files and may
+/// differ (see [`stoi_mixed_rate`]), and channels are scored with the channel policy of `options`.
+///
+/// Args:
+/// * `ref_path` - Clean speech file
+/// * `deg_path` - Processed speech file
+/// * `options` - Extended measure, channel policy and crop policy
+///
+/// Fails with [`FileError::Decode`] if a file cannot be decoded, and with [`FileError::Mismatch`]
+/// if the channels of the files do not fit the channel policy or their durations differ by more
+/// than 20ms.
+#[cfg(feature = "io")]
+pub fn stoi_files(
+    ref_path: impl AsRef<Path>,
+    deg_path: impl AsRef<Path>,
+    options: &FileOptions,
+) -> std::result::Result<MultichannelStoi, FileError> {
+    CLASSIC.stoi_files(ref_path, deg_path, options)
+}
 
-    // Compute spectrograms
-    let x_spec = stft::compute_frame_rffts(x_frames.as_ref(), mask.as_ref(), count);
-    let y_spec = stft::compute_frame_rffts(y_frames.as_ref(), mask.as_ref(), count);
+/// Compute the STOI measure between pairs of clean and processed audio files (`io` feature).
+/// See [`stoi_files`].
+///
+/// Pairs are decoded and scored in parallel when the `rayon` feature is enabled.
+#[cfg(feature = "io")]
+pub fn batch_stoi_files<P: AsRef<Path>>(
+    ref_paths: &[P],
+    deg_paths: &[P],
+    options: &FileOptions,
+) -> Vec<std::result::Result<MultichannelStoi, FileError>> {
+    CLASSIC.batch_stoi_files(ref_paths, deg_paths, options)
+}
 
-    // Accumulate into octave bands
-    let x_bands = octave::compute_octave_bands(x_spec.as_ref());
-    let y_bands = octave::compute_octave_bands(y_spec.as_ref());
+/// Write the precomputed references of a clean corpus to a cache file (`cache` feature),
+/// to score new processed signals against the corpus without processing it again.
+///
+/// The cache records the format version, the compatibility mode and the configuration.
+/// References are stored under unique keys, such as the paths of the clean files.
+///
+/// Args:
+/// * `path` - Cache file to create
+/// * `references` - Keys and references computed with [`pipeline::reference`]
+#[cfg(feature = "cache")]
+pub fn write_reference_cache<K: AsRef<str>>(
+    path: impl AsRef<Path>,
+    references: &[(K, Reference)],
+) -> std::result::Result<(), CacheError> {
+    CLASSIC.write_reference_cache(path, references)
+}
 
-    // Slice into segments
-    let mut x_segments = frames::segments(x_bands.transpose());
-    let mut y_segments = frames::segments(y_bands.transpose());
+/// Memory-map a reference cache written with the classic STOI configuration (`cache` feature).
+///
+/// Caches written with another format version, compatibility mode or configuration are rejected.
+/// References are decoded on demand with [`ReferenceCache::get`], and scored with
+/// [`pipeline::stoi_from_reference`]. The file must not be modified while the cache is open.
+#[cfg(feature = "cache")]
+pub fn open_reference_cache(
+    path: impl AsRef<Path>,
+) -> std::result::Result<ReferenceCache, CacheError> {
+    CLASSIC.open_reference_cache(path)
+}
 
-    if extended {
-        Ok(extended::from_segments(
-            x_segments.as_mut(),
-            y_segments.as_mut(),
-        ))
-    } else {
-        Ok(standard::from_segments(
-            x_segments.as_mut(),
-            y_segments.as_mut(),
-        ))
-    }
+/// Compute the STOI measure for a batch of clean and processed signal pairs.
+///
+/// Standard STOI scores are computed `LANES` clips at a time with batched
+/// kernels that process one clip per SIMD lane, which is faster than
+/// `stoi` for many short clips. The scores are identical to the ones from `stoi`.
+/// Extended STOI scores are computed clip by clip.
+///
+/// Args:
+/// * `x` - Clean speech signals
+/// * `y` - Processed speech signals
+/// * `fs_sig` - Sampling frequency of the signals
+/// * `extended` - Whether to use the extended STOI measure
+///
+/// Groups of `LANES` clips are processed in parallel when the `rayon` feature is enabled.
+#[cfg(feature = "std")]
+pub fn batch_stoi(x: &[&[f32]], y: &[&[f32]], fs_sig: usize, extended: bool) -> Vec<Result<f32>> {
+    CLASSIC.batch_stoi(x, y, fs_sig, extended)
 }
 
-/// Compute the Short-Time Objective Intelligibility (STOI) measure between two signals.
+/// Compute several metrics between two signals in a single pass.
+///
+/// STOI and ESTOI share their resampling, frame selection, spectrograms and octave band
+/// segments. SI-SDR is computed on the input signals, and fwSegSNR on the input signals with
+/// the framing of Loizou's `comp_fwseg` (30ms frames with 75% overlap, silent frames kept),
+/// so that its values are comparable with `comp_fwseg`.
 ///
 /// Args:
 /// * `x` - Clean speech signal
 /// * `y` - Processed speech signal
 /// * `fs_sig` - Sampling frequency of the signals
+/// * `metrics` - Metrics to compute, the others are `None` in the returned scores
+///
+/// Fails if STOI or ESTOI is requested and there are not enough non-silent frames,
+/// or if fwSegSNR is requested and the signals are shorter than a 30ms frame.
+#[cfg(feature = "std")]
+pub fn evaluate(x: &[f32], y: &[f32], fs_sig: usize, metrics: &[Metric]) -> Result<Scores> {
+    CLASSIC.evaluate(x, y, fs_sig, metrics)
+}
+
+/// Compute STOI between multichannel signals (stereo, microphone arrays), with an explicit
+/// channel policy: per-channel scores, a single channel, a mean downmix, the best channel,
+/// or every channel against a chosen clean reference channel.
+///
+/// Args:
+/// * `x` - Clean speech signal, mono or with as many channels as `y`
+///   (any amount with [`ChannelPolicy::Reference`]).
+///   A mono clean signal is the reference of every processed channel.
+/// * `y` - Processed speech signal
+/// * `fs_sig` - Sampling frequency of the signals
 /// * `extended` - Whether to use the extended STOI measure
-pub fn stoi(x: &[f32], y: &[f32], fs_sig: usize, extended: bool) -> Result<f32> {
-    assert!(
-        x.len() == y.len(),
-        "Input signals must have the same length"
-    );
-
-    if fs_sig != FS {
-        let x = resample::resample(x, fs_sig, FS);
-        let y = resample::resample(y, fs_sig, FS);
-
-        compute(&x, &y, extended)
-    } else {
-        compute(x, y, extended)
-    }
+/// * `policy` - How channels are scored
+#[cfg(feature = "std")]
+pub fn stoi_multichannel(
+    x: &Multichannel,
+    y: &Multichannel,
+    fs_sig: usize,
+    extended: bool,
+    policy: ChannelPolicy,
+) -> MultichannelStoi {
+    CLASSIC.stoi_multichannel(x, y, fs_sig, extended, policy)
+}
+
+/// Compute the STOI improvement (ΔSTOI) from a noisy to an enhanced signal.
+///
+/// The clean reference is processed once, and both degraded signals are scored
+/// with the same non-silent frames of the clean reference.
+///
+/// Args:
+/// * `clean` - Clean speech signal
+/// * `noisy` - Noisy speech signal
+/// * `enhanced` - Enhanced speech signal
+/// * `fs_sig` - Sampling frequency of the signals
+/// * `extended` - Whether to use the extended STOI measure
+/// * `detailed` - Whether to also return the contributions of each band and segment
+#[cfg(feature = "std")]
+pub fn stoi_improvement(
+    clean: &[f32],
+    noisy: &[f32],
+    enhanced: &[f32],
+    fs_sig: usize,
+    extended: bool,
+    detailed: bool,
+) -> Result<StoiImprovement> {
+    CLASSIC.stoi_improvement(clean, noisy, enhanced, fs_sig, extended, detailed)
+}
+
+/// Compute STOI after compensating the clock drift of the processed signal.
+///
+/// Use this when the processed signal was re-recorded by a device whose clock differs from
+/// the one of the clean recording. The lag of `y` is tracked with block-wise cross-correlations,
+/// a clock skew is fitted, and `y` is resampled to cancel it before scoring.
+/// The estimated drift in ppm is returned with the score.
+///
+/// Args:
+/// * `x` - Clean speech signal
+/// * `y` - Processed speech signal
+/// * `fs_sig` - Sampling frequency of the signals
+/// * `extended` - Whether to use the extended STOI measure
+/// * `crop` - How the compensated processed signal is brought to the length of the clean signal
+///   (recordings from another device rarely have the same length)
+#[cfg(feature = "std")]
+pub fn stoi_with_drift_compensation(
+    x: &[f32],
+    y: &[f32],
+    fs_sig: usize,
+    extended: bool,
+    crop: CropPolicy,
+) -> Result<DriftCompensatedStoi> {
+    CLASSIC.stoi_with_drift_compensation(x, y, fs_sig, extended, crop)
+}
+
+/// Compute STOI between signals that are not aligned in time, such as text-to-speech
+/// or voice conversion outputs and a reference recording, with dynamic time warping
+/// of the processed octave band envelopes onto the clean ones.
+///
+/// **This is non-standard**: scores are not comparable with the classic STOI.
+///
+/// Args:
+/// * `x` - Clean speech signal
+/// * `y` - Processed speech signal, of any length
+/// * `fs_sig` - Sampling frequency of the signals
+/// * `extended` - Whether to use the extended STOI measure
+/// * `window` - Sakoe-Chiba window in seconds: maximum time offset of the warping path
+///   from the diagonal, scaled to the lengths of both signals (widened to keep the path
+///   connected). Without a window, the warping path search uses memory proportional
+///   to the product of the signal lengths (about 2GB for two 10 minute signals).
+///
+/// The mean frame distance along the warping path is returned with the score.
+#[cfg(feature = "std")]
+pub fn stoi_dtw(
+    x: &[f32],
+    y: &[f32],
+    fs_sig: usize,
+    extended: bool,
+    window: Option<f32>,
+) -> Result<DtwStoi> {
+    CLASSIC.stoi_dtw(x, y, fs_sig, extended, window)
+}
+
+/// Compute STOI and ESTOI of labelled intervals (utterances) inside two long aligned recordings,
+/// such as meetings or broadcasts segmented in CSV, RTTM or TextGrid files
+/// (see [`Interval::from_csv`], [`Interval::from_rttm`] and [`Interval::from_textgrid`]).
+///
+/// Both recordings are resampled once, and each interval is scored as a separate utterance.
+/// Intervals shorter than a segment get an [`IntervalError::TooShort`], and intervals
+/// with too few non-silent frames an [`IntervalError::NotEnoughFrames`].
+/// The scores of the other intervals are aggregated with a mean weighted by their durations.
+///
+/// Args:
+/// * `x` - Clean speech recording
+/// * `y` - Processed speech recording
+/// * `fs_sig` - Sampling frequency of the recordings
+/// * `intervals` - Intervals to score, in seconds
+#[cfg(feature = "std")]
+pub fn stoi_intervals(
+    x: &[f32],
+    y: &[f32],
+    fs_sig: usize,
+    intervals: &[Interval],
+) -> IntervalScores {
+    CLASSIC.stoi_intervals(x, y, fs_sig, intervals)
+}
+
+/// Prepare a sweep of STOI as a function of the SNR, to evaluate a processing under test.
+///
+/// The clean speech is mixed with a recorded noise or with speech-shaped noise generated
+/// from its long-term spectrum, reproducibly from `seed`. The clean reference is processed once
+/// and shared by all the conditions. See [`SnrSweep::sweep`] and [`SnrSweep::srt`].
+///
+/// Args:
+/// * `clean` - Clean speech signal
+/// * `noise` - Noise to mix with the clean speech
+/// * `fs_sig` - Sampling frequency of the signals
+/// * `extended` - Whether to use the extended STOI measure
+/// * `seed` - Seed of the noise segment choice and speech-shaped noise generation
+///
+/// Fails with [`SweepError::NoiseTooShort`] if the recorded noise is shorter than the clean
+/// speech, and with [`SweepError::NotEnoughFrames`] if the clean speech is too short.
+#[cfg(feature = "std")]
+pub fn snr_sweep<'a>(
+    clean: &'a [f32],
+    noise: Noise,
+    fs_sig: usize,
+    extended: bool,
+    seed: u64,
+) -> std::result::Result<SnrSweep<'a>, SweepError> {
+    CLASSIC.snr_sweep(clean, noise, fs_sig, extended, seed)
+}
+
+/// Compute the permutation-invariant STOI between reference and estimated sources.
+///
+/// The STOI of every (reference, estimate) pair is computed, sharing the preprocessing
+/// of each reference across its row. The assignment of estimates to references with the
+/// highest total STOI is then found with the Hungarian algorithm.
+///
+/// Args:
+/// * `refs` - Reference sources
+/// * `ests` - Estimated sources, in any order
+/// * `fs_sig` - Sampling frequency of the signals
+/// * `extended` - Whether to use the extended STOI measure
+///
+/// There must be at least one reference, and as many estimates as references.
+/// Fails if a reference does not have enough non-silent frames.
+#[cfg(feature = "std")]
+pub fn pit_stoi(
+    refs: &[&[f32]],
+    ests: &[&[f32]],
+    fs_sig: usize,
+    extended: bool,
+) -> Result<PitStoi> {
+    CLASSIC.pit_stoi(refs, ests, fs_sig, extended)
+}
+
+/// Compute the permutation-invariant STOI for a batch of source separation examples.
+///
+/// Examples are processed in parallel when the `rayon` feature is enabled.
+#[cfg(feature = "std")]
+pub fn batch_pit_stoi(
+    refs: &[&[&[f32]]],
+    ests: &[&[&[f32]]],
+    fs_sig: usize,
+    extended: bool,
+) -> Vec<Result<PitStoi>> {
+    CLASSIC.batch_pit_stoi(refs, ests, fs_sig, extended)
+}
+
+/// Compute the Normalized Covariance Measure (NCM) between two signals.
+///
+/// The correlation between the clean and processed third octave band envelopes
+/// (computed over the non-silent frames of the clean signal) is converted to an
+/// apparent SNR per band, and averaged with the ANSI S3.5 band-importance weights.
+///
+/// Args:
+/// * `x` - Clean speech signal
+/// * `y` - Processed speech signal
+/// * `fs_sig` - Sampling frequency of the signals
+#[cfg(feature = "std")]
+pub fn ncm(x: &[f32], y: &[f32], fs_sig: usize) -> Result<f32> {
+    CLASSIC.ncm(x, y, fs_sig)
+}
+
+/// Compute the Deterministic Binaural STOI (DBSTOI) measure between two binaural signals.
+///
+/// Both ears are combined by an equalization-cancellation stage that compensates
+/// interaural time and level differences, searched on a grid for each band and segment,
+/// or by the better ear when its clean envelope varies more than the EC output.
+///
+/// Args:
+/// * `xl`, `xr` - Clean speech signal at the left and right ears
+/// * `yl`, `yr` - Processed speech signal at the left and right ears
+/// * `fs_sig` - Sampling frequency of the signals
+#[cfg(feature = "std")]
+pub fn dbstoi(xl: &[f32], xr: &[f32], yl: &[f32], yr: &[f32], fs_sig: usize) -> Result<f32> {
+    CLASSIC.dbstoi(xl, xr, yl, yr, fs_sig)
+}
+
+/// Compute the Speech Intelligibility In Bits (SIIB) measure between two signals, in bits/s.
+///
+/// SIIB estimates the information rate shared by the clean and processed signals
+/// from the mutual information of their decorrelated gammatone envelopes.
+/// The k-nearest-neighbour estimator is biased for short signals:
+/// signals should last at least 20s.
+///
+/// Args:
+/// * `x` - Clean speech signal
+/// * `y` - Processed speech signal
+/// * `fs_sig` - Sampling frequency of the signals
+#[cfg(feature = "std")]
+pub fn siib(x: &[f32], y: &[f32], fs_sig: usize) -> Result<f32> {
+    siib::siib(x, y, fs_sig)
+}
+
+/// Compute the SIIB measure for a batch of clean and processed signal pairs, in bits/s.
+///
+/// Clips are processed in parallel when the `rayon` feature is enabled.
+#[cfg(feature = "std")]
+pub fn batch_siib(x: &[&[f32]], y: &[&[f32]], fs_sig: usize) -> Vec<Result<f32>> {
+    siib::batch_siib(x, y, fs_sig)
 }
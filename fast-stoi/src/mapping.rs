@@ -0,0 +1,252 @@
+//! Psychometric mapping from STOI scores to intelligibility
+
+/// Logistic mapping `100 / (1 + exp(a * d + b))` from a STOI score `d`
+/// to a predicted word-recognition percentage.
+///
+/// The parameters depend on the speech material and listening test,
+/// so they should be fitted per corpus with [`Mapping::fit`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mapping {
+    pub a: f32,
+    pub b: f32,
+}
+
+/// Maximum amount of Levenberg-Marquardt iterations when fitting
+const MAX_ITERATIONS: usize = 100;
+
+impl Mapping {
+    /// Danish Dantale II sentences with ideal time-frequency segregation
+    /// (Kjems et al., 2009), fitted in the STOI paper (Taal et al., 2011)
+    pub const DANTALE_II: Mapping = Mapping {
+        a: -17.4906,
+        b: 9.6921,
+    };
+
+    /// English IEEE sentences processed by single-channel noise reduction
+    /// (Hu & Loizou, 2007), fitted in the STOI paper (Taal et al., 2011)
+    pub const IEEE: Mapping = Mapping {
+        a: -13.1903,
+        b: 6.5293,
+    };
+
+    /// Create a mapping from its parameters
+    pub fn new(a: f32, b: f32) -> Self {
+        Self { a, b }
+    }
+
+    /// Predict the word-recognition percentage for a STOI score
+    pub fn predict_intelligibility(&self, score: f32) -> f32 {
+        100.0 / (1.0 + (self.a * score + self.b).exp())
+    }
+
+    /// Predict the word-recognition percentages for a batch of STOI scores
+    pub fn predict_batch(&self, scores: &[f32]) -> Vec<f32> {
+        scores
+            .iter()
+            .map(|&score| self.predict_intelligibility(score))
+            .collect()
+    }
+
+    /// Fit the mapping parameters by least squares on pairs of STOI scores
+    /// and measured word-recognition percentages.
+    ///
+    /// The parameters are initialized with a linear fit in the logit domain,
+    /// and refined with Levenberg-Marquardt iterations.
+    pub fn fit(scores: &[f32], intelligibility: &[f32]) -> Self {
+        assert!(
+            scores.len() == intelligibility.len(),
+            "There must be as many scores as intelligibility measurements"
+        );
+        assert!(scores.len() >= 2, "At least two points are required");
+
+        let points = scores
+            .iter()
+            .zip(intelligibility)
+            .map(|(&d, &p)| (d as f64, p as f64))
+            .collect::<Vec<_>>();
+
+        let (mut a, mut b) = logit_fit(&points);
+        let mut error = squared_error(&points, a, b);
+        let mut damping = 1e-3;
+
+        for _ in 0..MAX_ITERATIONS {
+            // Accumulate the normal equations J^T J and J^T r
+            let (mut jaa, mut jab, mut jbb, mut ga, mut gb) = (0.0, 0.0, 0.0, 0.0, 0.0);
+            for &(d, p) in &points {
+                let e = (a * d + b).exp();
+                let f = 100.0 / (1.0 + e);
+                // Derivative of f with respect to (a * d + b)
+                let df = -100.0 * e / (1.0 + e).powi(2);
+                let (da, db) = (df * d, df);
+                let r = p - f;
+
+                jaa += da * da;
+                jab += da * db;
+                jbb += db * db;
+                ga += da * r;
+                gb += db * r;
+            }
+
+            // Solve the damped 2x2 system
+            let (maa, mbb) = (jaa * (1.0 + damping), jbb * (1.0 + damping));
+            let det = maa * mbb - jab * jab;
+            if det.abs() < f64::EPSILON {
+                break;
+            }
+            let step_a = (mbb * ga - jab * gb) / det;
+            let step_b = (maa * gb - jab * ga) / det;
+
+            let new_error = squared_error(&points, a + step_a, b + step_b);
+            if new_error < error {
+                a += step_a;
+                b += step_b;
+                damping /= 10.0;
+
+                let converged = error - new_error < 1e-12 * error.max(1.0);
+                error = new_error;
+                if converged {
+                    break;
+                }
+            } else {
+                damping *= 10.0;
+            }
+        }
+
+        Self::new(a as f32, b as f32)
+    }
+}
+
+/// Initial parameters from a linear least squares fit of `ln(100 / p - 1) = a * d + b`
+fn logit_fit(points: &[(f64, f64)]) -> (f64, f64) {
+    let n = points.len() as f64;
+    let logits = points
+        .iter()
+        .map(|&(d, p)| {
+            // Keep percentages away from 0 and 100 where the logit diverges
+            let p = p.clamp(0.5, 99.5);
+            (d, (100.0 / p - 1.0).ln())
+        })
+        .collect::<Vec<_>>();
+
+    let d_mean = logits.iter().map(|&(d, _)| d).sum::<f64>() / n;
+    let l_mean = logits.iter().map(|&(_, l)| l).sum::<f64>() / n;
+    let covariance = logits
+        .iter()
+        .map(|&(d, l)| (d - d_mean) * (l - l_mean))
+        .sum::<f64>();
+    let variance = logits
+        .iter()
+        .map(|&(d, _)| (d - d_mean).powi(2))
+        .sum::<f64>();
+
+    let a = if variance > 0.0 {
+        covariance / variance
+    } else {
+        0.0
+    };
+
+    (a, l_mean - a * d_mean)
+}
+
+/// Sum of squared errors between the measured and predicted percentages
+fn squared_error(points: &[(f64, f64)], a: f64, b: f64) -> f64 {
+    points
+        .iter()
+        .map(|&(d, p)| (p - 100.0 / (1.0 + (a * d + b).exp())).powi(2))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// STOI scores spanning the steep part of the mapping
+    fn scores() -> Vec<f32> {
+        (0..21).map(|i| 0.3 + 0.03 * i as f32).collect()
+    }
+
+    #[test]
+    fn recovers_known_parameters() {
+        for mapping in [Mapping::DANTALE_II, Mapping::IEEE] {
+            let scores = scores();
+            let fitted = Mapping::fit(&scores, &mapping.predict_batch(&scores));
+            assert!(
+                (fitted.a - mapping.a).abs() < 1e-2,
+                "{fitted:?} != {mapping:?}"
+            );
+            assert!(
+                (fitted.b - mapping.b).abs() < 1e-2,
+                "{fitted:?} != {mapping:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn fit_improves_on_the_logit_initialization() {
+        // Noisy measurements, with saturated 0% and 100% points
+        let scores = scores();
+        let mut intelligibility = Mapping::DANTALE_II.predict_batch(&scores);
+        intelligibility
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, p)| *p = (*p + if i % 2 == 0 { 4.0 } else { -4.0 }).clamp(0.0, 100.0));
+        intelligibility[0] = 0.0;
+        intelligibility[20] = 100.0;
+
+        let points = scores
+            .iter()
+            .zip(&intelligibility)
+            .map(|(&d, &p)| (d as f64, p as f64))
+            .collect::<Vec<_>>();
+        let (a, b) = logit_fit(&points);
+
+        let fitted = Mapping::fit(&scores, &intelligibility);
+        let error = squared_error(&points, fitted.a as f64, fitted.b as f64);
+        assert!(error <= squared_error(&points, a, b), "{fitted:?}");
+        assert!((fitted.a - Mapping::DANTALE_II.a).abs() < 2.0, "{fitted:?}");
+    }
+
+    #[test]
+    fn equal_scores() {
+        // The slope cannot be identified: the mapping is flat
+        let fitted = Mapping::fit(&[0.6; 5], &[40.0, 50.0, 60.0, 50.0, 50.0]);
+        assert!(fitted.a.is_finite() && fitted.b.is_finite(), "{fitted:?}");
+        assert_eq!(fitted.a, 0.0);
+        let prediction = fitted.predict_intelligibility(0.6);
+        assert!((prediction - 50.0).abs() < 1.0, "{prediction}");
+    }
+
+    #[test]
+    fn saturated_measurements() {
+        let fitted = Mapping::fit(&[0.2, 0.4, 0.8, 0.9], &[0.0, 0.0, 100.0, 100.0]);
+        assert!(fitted.a.is_finite() && fitted.b.is_finite(), "{fitted:?}");
+        assert!(fitted.predict_intelligibility(0.2) < 5.0, "{fitted:?}");
+        assert!(fitted.predict_intelligibility(0.9) > 95.0, "{fitted:?}");
+    }
+
+    #[test]
+    #[should_panic(expected = "as many scores")]
+    fn mismatched_lengths() {
+        Mapping::fit(&[0.5, 0.6, 0.7], &[10.0, 20.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "At least two points")]
+    fn single_point() {
+        Mapping::fit(&[0.5], &[10.0]);
+    }
+
+    #[test]
+    fn batch_matches_single_predictions() {
+        let scores = scores();
+        let batch = Mapping::IEEE.predict_batch(&scores);
+        assert_eq!(batch.len(), scores.len());
+        for (&score, prediction) in scores.iter().zip(batch) {
+            assert_eq!(prediction, Mapping::IEEE.predict_intelligibility(score));
+        }
+
+        // Presets map perfect scores to near perfect intelligibility
+        assert!(Mapping::DANTALE_II.predict_intelligibility(1.0) > 99.0);
+        assert!(Mapping::IEEE.predict_intelligibility(0.0) < 1.0);
+    }
+}
@@ -0,0 +1,127 @@
+//! Float math and window functions for `no_std` builds, backed by `libm`
+//!
+//! The window functions follow the `windowfunctions` crate used with `std`.
+
+use core::f32::consts::PI;
+
+/// Float methods that `core` lacks without `std`
+pub(crate) trait Float: Sized {
+    fn sqrt(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn powf(self, n: Self) -> Self;
+    fn log10(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn ceil(self) -> Self;
+}
+
+impl Float for f32 {
+    fn sqrt(self) -> Self {
+        libm::sqrtf(self)
+    }
+
+    /// Repeated multiplication, as `std` does for small exponents
+    fn powi(self, n: i32) -> Self {
+        let power = (0..n.unsigned_abs()).fold(1.0, |power, _| power * self);
+        if n < 0 { 1.0 / power } else { power }
+    }
+
+    fn powf(self, n: Self) -> Self {
+        libm::powf(self, n)
+    }
+
+    fn log10(self) -> Self {
+        libm::log10f(self)
+    }
+
+    fn sin(self) -> Self {
+        libm::sinf(self)
+    }
+
+    fn cos(self) -> Self {
+        libm::cosf(self)
+    }
+
+    fn ceil(self) -> Self {
+        libm::ceilf(self)
+    }
+}
+
+impl Float for f64 {
+    fn sqrt(self) -> Self {
+        libm::sqrt(self)
+    }
+
+    /// Repeated multiplication, as `std` does for small exponents
+    fn powi(self, n: i32) -> Self {
+        let power = (0..n.unsigned_abs()).fold(1.0, |power, _| power * self);
+        if n < 0 { 1.0 / power } else { power }
+    }
+
+    fn powf(self, n: Self) -> Self {
+        libm::pow(self, n)
+    }
+
+    fn log10(self) -> Self {
+        libm::log10(self)
+    }
+
+    fn sin(self) -> Self {
+        libm::sin(self)
+    }
+
+    fn cos(self) -> Self {
+        libm::cos(self)
+    }
+
+    fn ceil(self) -> Self {
+        libm::ceil(self)
+    }
+}
+
+/// Window functions used by the crate
+#[derive(Clone, Copy)]
+pub(crate) enum WindowFunction {
+    Hann,
+    Kaiser { beta: f32 },
+}
+
+/// Window symmetry (only symmetric windows are used)
+#[derive(Clone, Copy)]
+pub(crate) enum Symmetry {
+    Symmetric,
+}
+
+/// Symmetric window of `length` samples
+pub(crate) fn window(
+    length: usize,
+    function: WindowFunction,
+    _symmetry: Symmetry,
+) -> impl Iterator<Item = f32> {
+    let last = (length - 1) as f32;
+
+    (0..length).map(move |n| match function {
+        WindowFunction::Hann => 0.5 - 0.5 * (2.0 * PI * n as f32 / last).cos(),
+        WindowFunction::Kaiser { beta } => {
+            let x = n as f32 / (last / 2.0) - 1.0;
+            bessel_i0(beta * (1.0 - x.powi(2)).sqrt()) / bessel_i0(beta)
+        }
+    })
+}
+
+/// Modified Bessel function of order 0
+fn bessel_i0(x: f32) -> f32 {
+    let base = x * x / 4.0;
+    let mut term = 1.0;
+    let mut result = 1.0;
+    for i in 1..1000 {
+        term *= base / (i * i) as f32;
+        let previous = result;
+        result += term;
+        if result == previous {
+            break;
+        }
+    }
+
+    result
+}
@@ -0,0 +1,106 @@
+//! Signals at different sampling frequencies
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::errors::NotEnoughFramesError;
+
+/// Maximum length difference in seconds between both signals once resampled,
+/// beyond which they are not considered to be the same recording
+const MAX_MISMATCH_SECONDS: f64 = 0.02;
+
+/// How signals resampled from different rates are brought to the same length
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CropPolicy {
+    /// Crop the longer signal to the length of the shorter one
+    #[default]
+    Shortest,
+    /// Crop or zero-pad the processed signal to the length of the clean signal
+    Reference,
+}
+
+/// Error while scoring signals at different sampling frequencies
+#[derive(Debug, Clone)]
+pub enum MixedRateError {
+    /// The signals do not have the same duration (up to 20ms), in seconds
+    DurationMismatch { clean: f64, processed: f64 },
+    /// Not enough non-silent frames
+    NotEnoughFrames(NotEnoughFramesError),
+}
+
+impl Display for MixedRateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DurationMismatch { clean, processed } => write!(
+                f,
+                "Input signals must have the same duration (up to {}ms): \
+                 the clean signal lasts {clean:.3}s and the processed signal {processed:.3}s",
+                MAX_MISMATCH_SECONDS * 1000.0
+            ),
+            Self::NotEnoughFrames(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for MixedRateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NotEnoughFrames(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<NotEnoughFramesError> for MixedRateError {
+    fn from(err: NotEnoughFramesError) -> Self {
+        Self::NotEnoughFrames(err)
+    }
+}
+
+/// Check that signals of `x_len` samples at `fs_x` Hz and `y_len` samples at `fs_y` Hz
+/// have the same duration, up to 20ms.
+pub(crate) fn check_durations(
+    x_len: usize,
+    fs_x: usize,
+    y_len: usize,
+    fs_y: usize,
+) -> Result<(), MixedRateError> {
+    let (clean, processed) = (x_len as f64 / fs_x as f64, y_len as f64 / fs_y as f64);
+
+    if (clean - processed).abs() > MAX_MISMATCH_SECONDS {
+        return Err(MixedRateError::DurationMismatch { clean, processed });
+    }
+
+    Ok(())
+}
+
+/// Bring both signals at the same sampling frequency to the same length with the given policy.
+/// Callers that need signals of the same duration check them first with `check_durations`.
+pub(crate) fn crop(x: &mut Vec<f32>, y: &mut Vec<f32>, policy: CropPolicy) {
+    match policy {
+        CropPolicy::Shortest => {
+            let len = x.len().min(y.len());
+            x.truncate(len);
+            y.truncate(len);
+        }
+        CropPolicy::Reference => y.resize(x.len(), 0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_up_to_20ms_apart() {
+        assert!(check_durations(48_000, 48_000, 16_000, 16_000).is_ok());
+        assert!(check_durations(48_000, 48_000, 16_300, 16_000).is_ok());
+        assert!(check_durations(48_000, 48_000, 15_700, 16_000).is_ok());
+
+        let Err(MixedRateError::DurationMismatch { clean, processed }) =
+            check_durations(48_000, 48_000, 16_400, 16_000)
+        else {
+            panic!("durations 25ms apart must be rejected");
+        };
+        assert_eq!((clean, processed), (1.0, 1.025));
+    }
+}
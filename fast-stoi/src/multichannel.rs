@@ -0,0 +1,230 @@
+//! Multichannel signals and channel policies
+
+use std::borrow::Cow;
+
+use crate::errors::{NotEnoughFramesError, Result};
+
+/// Sample layout of a multichannel buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Samples of all channels alternate: `[l0, r0, l1, r1, ...]`
+    Interleaved,
+    /// Channels follow each other: `[l0, l1, ..., r0, r1, ...]`
+    Planar,
+}
+
+/// How the channels of multichannel signals are scored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelPolicy {
+    /// Score every channel. The selected score is the mean of the channel scores.
+    PerChannel,
+    /// Score a single channel
+    Channel(usize),
+    /// Score the mean of all channels, which is the mid channel `(left + right) / 2`
+    /// of stereo signals
+    Mean,
+    /// Score every processed channel against a single channel of the clean signal,
+    /// such as a close-talk microphone. The selected score is the mean of the channel scores.
+    Reference(usize),
+    /// Score every channel and select the best one
+    Best,
+}
+
+/// Multichannel signal borrowed from an interleaved or planar buffer
+#[derive(Debug, Clone, Copy)]
+pub struct Multichannel<'a> {
+    data: &'a [f32],
+    channels: usize,
+    layout: Layout,
+}
+
+/// STOI of multichannel signals
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultichannelStoi {
+    /// Score of each channel for the per-channel and best policies,
+    /// or the single score of the other policies
+    pub channel_scores: Vec<Result<f32>>,
+    /// Score selected by the policy
+    pub score: Result<f32>,
+    /// Channel of the selected score, for the single channel and best policies
+    pub channel: Option<usize>,
+}
+
+impl<'a> Multichannel<'a> {
+    /// Wrap a buffer of `channels` channels with the given layout
+    pub fn new(data: &'a [f32], channels: usize, layout: Layout) -> Self {
+        assert!(channels > 0, "There must be at least one channel");
+        assert!(
+            data.len().is_multiple_of(channels),
+            "The buffer length must be a multiple of the amount of channels"
+        );
+
+        Self {
+            data,
+            channels,
+            layout,
+        }
+    }
+
+    /// Wrap an interleaved buffer
+    pub fn interleaved(data: &'a [f32], channels: usize) -> Self {
+        Self::new(data, channels, Layout::Interleaved)
+    }
+
+    /// Wrap a planar buffer
+    pub fn planar(data: &'a [f32], channels: usize) -> Self {
+        Self::new(data, channels, Layout::Planar)
+    }
+
+    /// Amount of channels
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Amount of samples per channel
+    pub fn len(&self) -> usize {
+        self.data.len() / self.channels
+    }
+
+    /// Whether the channels are empty
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Samples of a channel (borrowed for planar buffers)
+    pub fn channel(&self, channel: usize) -> Cow<'a, [f32]> {
+        assert!(channel < self.channels, "Channel {channel} does not exist");
+
+        match self.layout {
+            Layout::Planar => {
+                let len = self.len();
+                Cow::Borrowed(&self.data[channel * len..(channel + 1) * len])
+            }
+            Layout::Interleaved => Cow::Owned(
+                self.data
+                    .iter()
+                    .skip(channel)
+                    .step_by(self.channels)
+                    .copied()
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Mean of all channels
+    pub fn mean(&self) -> Cow<'a, [f32]> {
+        if self.channels == 1 {
+            return self.channel(0);
+        }
+
+        let mut mean = vec![0.0; self.len()];
+        for channel in 0..self.channels {
+            mean.iter_mut()
+                .zip(self.channel(channel).iter())
+                .for_each(|(m, x)| *m += x);
+        }
+        mean.iter_mut().for_each(|m| *m /= self.channels as f32);
+
+        Cow::Owned(mean)
+    }
+}
+
+/// Apply a channel policy, scoring pairs of clean and processed signals with `score`.
+/// A mono clean signal is the reference of every processed channel,
+/// as is the chosen clean channel of the reference policy.
+pub(crate) fn apply_policy<F>(
+    x: &Multichannel,
+    y: &Multichannel,
+    policy: ChannelPolicy,
+    score: F,
+) -> MultichannelStoi
+where
+    F: Fn(&[&[f32]], &[&[f32]]) -> Vec<Result<f32>>,
+{
+    if let ChannelPolicy::Reference(channel) = policy {
+        assert!(
+            channel < x.channels(),
+            "Reference channel {channel} does not exist"
+        );
+    } else {
+        assert!(
+            x.channels() == y.channels() || x.channels() == 1,
+            "The clean signal must be mono or have as many channels as the processed signal"
+        );
+    }
+
+    let x_channel = |channel: usize| match policy {
+        ChannelPolicy::Reference(reference) => x.channel(reference),
+        _ if x.channels() == 1 => x.channel(0),
+        _ => x.channel(channel),
+    };
+    let score_one = |x: &[f32], y: &[f32]| score(&[x], &[y]).remove(0);
+
+    match policy {
+        ChannelPolicy::PerChannel | ChannelPolicy::Reference(_) | ChannelPolicy::Best => {
+            let (x_channels, y_channels): (Vec<_>, Vec<_>) = (0..y.channels())
+                .map(|channel| (x_channel(channel), y.channel(channel)))
+                .unzip();
+            let x_channels = x_channels.iter().map(|x| x.as_ref()).collect::<Vec<_>>();
+            let y_channels = y_channels.iter().map(|y| y.as_ref()).collect::<Vec<_>>();
+
+            let scores = score(&x_channels, &y_channels);
+            if policy == ChannelPolicy::Best {
+                MultichannelStoi::best(scores)
+            } else {
+                MultichannelStoi::per_channel(scores)
+            }
+        }
+        ChannelPolicy::Channel(channel) => MultichannelStoi::single(
+            score_one(&x_channel(channel), &y.channel(channel)),
+            Some(channel),
+        ),
+        ChannelPolicy::Mean => MultichannelStoi::single(score_one(&x.mean(), &y.mean()), None),
+    }
+}
+
+impl MultichannelStoi {
+    /// Select the score of a single scored signal
+    pub(crate) fn single(score: Result<f32>, channel: Option<usize>) -> Self {
+        Self {
+            channel_scores: vec![score.clone()],
+            score,
+            channel,
+        }
+    }
+
+    /// Select the mean of the channel scores that could be computed
+    pub(crate) fn per_channel(channel_scores: Vec<Result<f32>>) -> Self {
+        let scores = channel_scores
+            .iter()
+            .filter_map(|score| score.as_ref().ok())
+            .collect::<Vec<_>>();
+        let score = if scores.is_empty() {
+            Err(NotEnoughFramesError)
+        } else {
+            Ok(scores.iter().copied().sum::<f32>() / scores.len() as f32)
+        };
+
+        Self {
+            channel_scores,
+            score,
+            channel: None,
+        }
+    }
+
+    /// Select the best channel score
+    pub(crate) fn best(channel_scores: Vec<Result<f32>>) -> Self {
+        let best = channel_scores
+            .iter()
+            .enumerate()
+            .filter_map(|(channel, score)| score.as_ref().ok().map(|&score| (channel, score)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        Self {
+            score: best.map(|(_, score)| score).ok_or(NotEnoughFramesError),
+            channel: best.map(|(channel, _)| channel),
+            channel_scores,
+        }
+    }
+}
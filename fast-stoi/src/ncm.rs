@@ -0,0 +1,147 @@
+//! Normalized Covariance Measure (NCM)
+//!
+//! Implementation of the NCM from Holube & Kollmeier (1996), as evaluated by
+//! Ma, Hu & Loizou, "Objective measures for predicting speech intelligibility
+//! in noisy conditions based on new band-importance functions" (JASA, 2009).
+//!
+//! The correlation between the clean and processed band envelopes is converted
+//! to an apparent SNR per band, mapped to a transmission index and averaged
+//! with band-importance weights.
+
+use faer::prelude::*;
+
+/// Apparent SNR range in dB
+const SNR_MIN: f32 = -15.0;
+const SNR_MAX: f32 = 15.0;
+
+/// One-third octave band importance function from ANSI S3.5-1997 (Table 3),
+/// as (nominal center frequency, importance) pairs.
+const BAND_IMPORTANCE: [(f32, f32); 18] = [
+    (160.0, 0.0083),
+    (200.0, 0.0095),
+    (250.0, 0.0150),
+    (315.0, 0.0289),
+    (400.0, 0.0440),
+    (500.0, 0.0578),
+    (630.0, 0.0653),
+    (800.0, 0.0711),
+    (1000.0, 0.0818),
+    (1250.0, 0.0844),
+    (1600.0, 0.0882),
+    (2000.0, 0.0898),
+    (2500.0, 0.0868),
+    (3150.0, 0.0844),
+    (4000.0, 0.0771),
+    (5000.0, 0.0527),
+    (6300.0, 0.0364),
+    (8000.0, 0.0185),
+];
+
+/// Importance weights of `num_bands` third octave bands with center frequencies
+/// starting from `min_freq`. Each band takes the importance of the closest ANSI band.
+pub fn band_importance(num_bands: usize, min_freq: f32) -> Vec<f32> {
+    (0..num_bands)
+        .map(|k| {
+            let center = min_freq * 2.0_f32.powf(k as f32 / 3.0);
+            BAND_IMPORTANCE
+                .iter()
+                .min_by(|(a, _), (b, _)| {
+                    (a / center).ln().abs().total_cmp(&(b / center).ln().abs())
+                })
+                .unwrap()
+                .1
+        })
+        .collect()
+}
+
+/// Compute the NCM from the band envelopes of the clean and processed signals,
+/// of shape (num_bands, frames), with the given band importance weights.
+pub fn from_envelopes(x_bands: MatRef<f32>, y_bands: MatRef<f32>, weights: &[f32]) -> f32 {
+    let transmission = x_bands.row_iter().zip(y_bands.row_iter()).map(|(x, y)| {
+        // Normalized covariance between the envelopes
+        let x_mean = x.sum() / x.ncols() as f32;
+        let y_mean = y.sum() / y.ncols() as f32;
+        let (mut xy, mut xx, mut yy) = (0.0, 0.0, 0.0);
+        x.iter().zip(y.iter()).for_each(|(x, y)| {
+            let (x, y) = (x - x_mean, y - y_mean);
+            xy += x * y;
+            xx += x * x;
+            yy += y * y;
+        });
+        let r2 = (xy * xy / (xx * yy + f32::EPSILON)).min(1.0);
+
+        // Apparent SNR mapped to a transmission index
+        let snr = (10.0 * (r2 / (1.0 - r2)).log10()).clamp(SNR_MIN, SNR_MAX);
+        (snr - SNR_MIN) / (SNR_MAX - SNR_MIN)
+    });
+
+    let weighted = transmission.zip(weights).map(|(t, w)| t * w).sum::<f32>();
+
+    weighted / weights.iter().sum::<f32>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Envelope rows `x` and `x + noise * z`, with `z` orthogonal to `x`:
+    /// their squared correlation is `1 / (1 + noise²)`, an apparent SNR of `-20 log10(noise)` dB
+    fn envelopes(noise: &[f32]) -> (Mat<f32>, Mat<f32>) {
+        let x = [1.0, -1.0, 1.0, -1.0];
+        let z = [1.0, 1.0, -1.0, -1.0];
+        let x_bands = Mat::from_fn(noise.len(), 4, |_, j| 10.0 + x[j]);
+        let y_bands = Mat::from_fn(noise.len(), 4, |i, j| 10.0 + x[j] + noise[i] * z[j]);
+
+        (x_bands, y_bands)
+    }
+
+    #[test]
+    fn apparent_snr_is_clamped() {
+        for (noise, expected) in [
+            (1.0, 0.5),
+            (10.0, 0.0),
+            (0.01, 1.0),
+            (1e-3, 1.0),
+            (1e3, 0.0),
+        ] {
+            let (x, y) = envelopes(&[noise]);
+            let ncm = from_envelopes(x.as_ref(), y.as_ref(), &[1.0]);
+            assert!(
+                (ncm - expected).abs() < 1e-5,
+                "{noise}: {ncm} != {expected}"
+            );
+        }
+
+        // -10dB is within the range
+        let (x, y) = envelopes(&[10.0_f32.sqrt()]);
+        let ncm = from_envelopes(x.as_ref(), y.as_ref(), &[1.0]);
+        assert!((ncm - 1.0 / 6.0).abs() < 1e-5, "{ncm}");
+    }
+
+    #[test]
+    fn weights_are_normalized() {
+        let (x, y) = envelopes(&[1e-3, 1e3]);
+        for (weights, expected) in [([1.0, 1.0], 0.5), ([3.0, 1.0], 0.75), ([0.3, 0.1], 0.75)] {
+            let ncm = from_envelopes(x.as_ref(), y.as_ref(), &weights);
+            assert!(
+                (ncm - expected).abs() < 1e-5,
+                "{weights:?}: {ncm} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn bands_take_the_nearest_ansi_importance() {
+        // Classic STOI bands: 150Hz to 3.8kHz centers, which round to the 160Hz to 4kHz ANSI bands
+        let weights = band_importance(15, 150.0);
+        let expected = BAND_IMPORTANCE[..15]
+            .iter()
+            .map(|&(_, w)| w)
+            .collect::<Vec<_>>();
+        assert_eq!(weights, expected);
+
+        // Bands above 8kHz keep the importance of the last ANSI band
+        let weights = band_importance(3, 6300.0);
+        assert_eq!(weights, [0.0364, 0.0185, 0.0185]);
+    }
+}
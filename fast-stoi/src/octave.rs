@@ -1,12 +1,20 @@
 //! Third octave bands
 
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
 use faer::prelude::*;
 
-use crate::constants::NUM_BANDS;
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+#[cfg(feature = "rayon")]
+use crate::constants::PARALLEL_MIN_FRAMES;
+use crate::constants::{FFT_LENGTH, FS, MIN_FREQ, NUM_BANDS};
 
 /// Octave band indices in FFT spectrums of length 512
 /// (precomputed from the original STOI implementation)
-const OCTAVE_BANDS: [(usize, usize); NUM_BANDS] = [
+pub const OCTAVE_BANDS: [(usize, usize); NUM_BANDS] = [
     (7, 9),
     (9, 11),
     (11, 14),
@@ -24,27 +32,143 @@ const OCTAVE_BANDS: [(usize, usize); NUM_BANDS] = [
     (174, 219),
 ];
 
-/// Merge FFT spectrogram into octave bands specified by the index ranges in `OCTAVE_BANDS`.
+/// Compute the third octave band indices in FFT spectrums of length `fft_length`
+/// for `num_bands` bands with center frequencies starting from `min_freq`,
+/// following the original STOI implementation.
+/// Each band covers the FFT bins in `start..end`.
+pub fn third_octave_bands(
+    fs: usize,
+    fft_length: usize,
+    num_bands: usize,
+    min_freq: f32,
+) -> Vec<(usize, usize)> {
+    let bin_width = fs as f64 / fft_length as f64;
+    let n_bins = fft_length / 2 + 1;
+
+    // Closest FFT bin to the given frequency (first one on ties)
+    let closest_bin = |freq: f64| {
+        (0..n_bins)
+            .map(|i| (i, (i as f64 * bin_width - freq).powi(2)))
+            .fold(
+                (0, f64::INFINITY),
+                |best, bin| if bin.1 < best.1 { bin } else { best },
+            )
+            .0
+    };
+
+    (0..num_bands)
+        .map(|k| {
+            let k = k as f64;
+            let low = min_freq as f64 * 2.0_f64.powf((2.0 * k - 1.0) / 6.0);
+            let high = min_freq as f64 * 2.0_f64.powf((2.0 * k + 1.0) / 6.0);
+
+            (closest_bin(low), closest_bin(high))
+        })
+        .collect()
+}
+
+/// Third octave band indices for a band layout. The classic layout
+/// (10kHz, 512-point FFT, 15 bands from 150Hz) uses the precomputed `OCTAVE_BANDS`.
+pub fn band_table(
+    fs: usize,
+    fft_length: usize,
+    num_bands: usize,
+    min_freq: f32,
+) -> Vec<(usize, usize)> {
+    if (fs, fft_length, num_bands, min_freq) == (FS, FFT_LENGTH, NUM_BANDS, MIN_FREQ) {
+        OCTAVE_BANDS.to_vec()
+    } else {
+        third_octave_bands(fs, fft_length, num_bands, min_freq)
+    }
+}
+
+/// Merge FFT spectrogram into octave bands specified by the index ranges in `bands`.
 /// Input spectrograms have shape (FFT_BINS, num_frames).
-/// The merged output has shape (NUM_BANDS, num_frames).
-pub fn compute_octave_bands(spectrogram: MatRef<f32>) -> Mat<f32> {
+/// The merged output has shape (num_bands, num_frames).
+pub fn compute_octave_bands(spectrogram: MatRef<f32>, bands: &[(usize, usize)]) -> Mat<f32> {
     let num_frames = spectrogram.ncols();
-    let mut band_spectrogram = Mat::<f32>::zeros(NUM_BANDS, num_frames);
+    let mut band_spectrogram = Mat::<f32>::zeros(bands.len(), num_frames);
+
+    // Long signals: pool frames in parallel
+    #[cfg(feature = "rayon")]
+    if num_frames >= PARALLEL_MIN_FRAMES {
+        use rayon::prelude::*;
+
+        spectrogram
+            .par_col_iter()
+            .zip(band_spectrogram.par_col_iter_mut())
+            .for_each(|(rfft, band_values)| pool_frame(rfft, band_values, bands));
+
+        return band_spectrogram;
+    }
 
     // Iterate over each frame
     spectrogram
         .col_iter()
         .zip(band_spectrogram.col_iter_mut())
-        .for_each(|(rfft, bands)| {
-            bands
-                .iter_mut()
-                .zip(OCTAVE_BANDS.iter())
-                .for_each(|(band, &(start, end))| {
-                    // The spectrogram contains squared magnitudes,
-                    // so we just need to sum and sqrt instead of norm_l2
-                    *band = rfft.subrows(start, end - start).sum().sqrt();
-                });
-        });
+        .for_each(|(rfft, band_values)| pool_frame(rfft, band_values, bands));
 
     band_spectrogram
 }
+
+/// Pool the cross power spectrum `a * conj(b)` of two complex spectrograms into octave bands
+/// specified by the index ranges in `bands`. With `a == b`, this gives the band powers.
+/// Input spectrograms have shape (fft_bins, num_frames).
+/// The pooled output has shape (num_bands, num_frames).
+#[cfg(feature = "std")]
+pub fn compute_octave_cross_bands(
+    a: MatRef<c32>,
+    b: MatRef<c32>,
+    bands: &[(usize, usize)],
+) -> Mat<c64> {
+    Mat::from_fn(bands.len(), a.ncols(), |band, frame| {
+        let (start, end) = bands[band];
+        a.col(frame)
+            .subrows(start, end - start)
+            .iter()
+            .zip(b.col(frame).subrows(start, end - start).iter())
+            .map(|(a, b)| {
+                let a = c64::new(a.re as f64, a.im as f64);
+                let b = c64::new(b.re as f64, b.im as f64);
+                a * b.conj()
+            })
+            .sum()
+    })
+}
+
+/// Merge a single FFT frame into octave bands.
+fn pool_frame(rfft: ColRef<f32>, band_values: ColMut<f32>, bands: &[(usize, usize)]) {
+    band_values
+        .iter_mut()
+        .zip(bands.iter())
+        .for_each(|(band, &(start, end))| {
+            // The spectrogram contains squared magnitudes,
+            // so we just need to sum and sqrt instead of norm_l2
+            *band = rfft.subrows(start, end - start).sum().sqrt();
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classic_bands_match_the_precomputed_table() {
+        assert_eq!(
+            third_octave_bands(FS, FFT_LENGTH, NUM_BANDS, MIN_FREQ),
+            OCTAVE_BANDS
+        );
+    }
+
+    #[test]
+    fn band_table_depends_on_the_band_layout_only() {
+        assert_eq!(
+            band_table(FS, FFT_LENGTH, NUM_BANDS, MIN_FREQ),
+            OCTAVE_BANDS
+        );
+        assert_eq!(
+            band_table(16_000, 820, 17, MIN_FREQ),
+            third_octave_bands(16_000, 820, 17, MIN_FREQ)
+        );
+    }
+}
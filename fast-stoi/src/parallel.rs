@@ -0,0 +1,20 @@
+//! Intra-signal parallelism helpers
+
+/// Run both closures, concurrently if `parallel` is set
+/// and the `rayon` feature is enabled.
+pub fn join<A, B, RA, RB>(parallel: bool, a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    #[cfg(feature = "rayon")]
+    if parallel {
+        return rayon::join(a, b);
+    }
+    #[cfg(not(feature = "rayon"))]
+    let _ = parallel;
+
+    (a(), b())
+}
@@ -0,0 +1,307 @@
+//! Staged STOI pipeline
+//!
+//! The STOI computation is split into stages that can be run separately,
+//! in order to score intermediate representations (for instance spectrograms
+//! or envelopes predicted by a neural model) or to cache them:
+//! 1. [`resample`]: resample a signal to the internal 10kHz rate
+//! 2. [`frames`]: select the non-silent frames of the clean signal
+//! 3. [`spectrogram`]: compute the squared magnitude spectrogram of the selected frames
+//! 4. [`octave_envelopes`]: pool a spectrogram into third octave band envelopes
+//! 5. [`segments`]: slice envelopes into overlapping segments
+//! 6. [`stoi_from_segments`]: score the segments of the clean and processed signals
+//!
+//! Both signals must be processed with the frames selected from the clean signal.
+//! To score several processed signals against the same clean signal,
+//! precompute it once with [`reference`] and use [`stoi_from_reference`].
+//!
+//! The functions of this module use the classic STOI configuration (`std` feature).
+//! The same stages are available on [`crate::Engine`] for other configurations.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use faer::prelude::*;
+
+#[cfg(feature = "serde")]
+use crate::constants::SEGMENT_LENGTH;
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+#[cfg(feature = "std")]
+use crate::{engine::CLASSIC, errors::Result};
+
+/// Non-silent frames selected from a clean signal at the internal rate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Frames {
+    pub(crate) starts: Vec<usize>,
+}
+
+impl Frames {
+    /// Start indices of the selected frames in the signal at the internal rate
+    pub fn starts(&self) -> &[usize] {
+        &self.starts
+    }
+
+    /// Amount of selected frames
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Whether no frame was selected
+    pub fn is_empty(&self) -> bool {
+        self.starts.is_empty()
+    }
+}
+
+/// Squared magnitude spectrogram of shape (fft_bins, frames).
+/// With the classic configuration, it is computed from a 10kHz signal
+/// with a 512-point FFT (`FFT_BINS` frequency bins).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spectrogram {
+    pub(crate) power: Mat<f32>,
+}
+
+impl Spectrogram {
+    /// Create a spectrogram from squared magnitudes of shape (fft_bins, frames)
+    pub fn from_power(power: Mat<f32>) -> Self {
+        Self { power }
+    }
+
+    /// Create a spectrogram from magnitudes of shape (fft_bins, frames)
+    pub fn from_magnitude(magnitude: MatRef<f32>) -> Self {
+        Self::from_power(Mat::from_fn(
+            magnitude.nrows(),
+            magnitude.ncols(),
+            |i, j| magnitude[(i, j)].powi(2),
+        ))
+    }
+
+    /// Squared magnitudes of shape (fft_bins, frames)
+    pub fn power(&self) -> MatRef<'_, f32> {
+        self.power.as_ref()
+    }
+
+    /// Amount of frames
+    pub fn n_frames(&self) -> usize {
+        self.power.ncols()
+    }
+}
+
+/// Third octave band envelopes of shape (num_bands, frames).
+/// The classic configuration has `NUM_BANDS` bands.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Envelopes {
+    pub(crate) bands: Mat<f32>,
+}
+
+impl Envelopes {
+    /// Create envelopes from band magnitudes of shape (num_bands, frames)
+    pub fn new(bands: Mat<f32>) -> Self {
+        Self { bands }
+    }
+
+    /// Band magnitudes of shape (num_bands, frames)
+    pub fn bands(&self) -> MatRef<'_, f32> {
+        self.bands.as_ref()
+    }
+
+    /// Amount of frames
+    pub fn n_frames(&self) -> usize {
+        self.bands.ncols()
+    }
+}
+
+/// Overlapping envelope segments of shape (SEGMENT_LENGTH, n_segments * num_bands).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Segments {
+    pub(crate) segments: Mat<f32>,
+    pub(crate) num_bands: usize,
+}
+
+impl Segments {
+    /// Segment values of shape (SEGMENT_LENGTH, n_segments * num_bands)
+    pub fn as_mat(&self) -> MatRef<'_, f32> {
+        self.segments.as_ref()
+    }
+
+    /// Amount of segments
+    pub fn n_segments(&self) -> usize {
+        self.segments.ncols() / self.num_bands
+    }
+
+    /// Envelopes the segments were sliced from, of shape (num_bands, frames)
+    #[cfg(feature = "serde")]
+    fn envelopes(&self) -> Mat<f32> {
+        let n_segments = self.n_segments();
+        let last = (n_segments - 1) * self.num_bands;
+
+        // Each frame is the first row of its segment, and the last frames are in the last segment
+        Mat::from_fn(
+            self.num_bands,
+            n_segments + SEGMENT_LENGTH - 1,
+            |band, frame| {
+                if frame < n_segments {
+                    self.segments[(0, frame * self.num_bands + band)]
+                } else {
+                    self.segments[(frame + 1 - n_segments, last + band)]
+                }
+            },
+        )
+    }
+}
+
+/// Precomputed clean reference, to score several processed signals
+/// against the same clean signal without processing it again.
+/// With the `serde` feature, references are serialized with their envelopes
+/// instead of their segments, which are sliced again when deserializing.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub(crate) fs_sig: usize,
+    pub(crate) len: usize,
+    pub(crate) frames: Frames,
+    pub(crate) segments: Segments,
+}
+
+impl Reference {
+    /// Sampling frequency of the clean signal
+    pub fn fs_sig(&self) -> usize {
+        self.fs_sig
+    }
+
+    /// Length of the clean signal
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the clean signal is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Non-silent frames selected from the clean signal
+    pub fn frames(&self) -> &Frames {
+        &self.frames
+    }
+}
+
+/// Serialized reference. Envelopes are stored instead of their overlapping segments,
+/// which repeat each frame `SEGMENT_LENGTH` times.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Reference")]
+struct ReferenceData {
+    fs_sig: usize,
+    len: usize,
+    frames: Frames,
+    envelopes: Mat<f32>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Reference {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        ReferenceData {
+            fs_sig: self.fs_sig,
+            len: self.len,
+            frames: self.frames.clone(),
+            envelopes: self.segments.envelopes(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Reference {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> core::result::Result<Self, D::Error> {
+        let data = ReferenceData::deserialize(deserializer)?;
+        if data.envelopes.nrows() == 0
+            || data.envelopes.ncols() != data.frames.len()
+            || data.frames.len() < SEGMENT_LENGTH
+        {
+            return Err(serde::de::Error::custom(
+                "reference envelopes must have one column per frame, and enough frames for a segment",
+            ));
+        }
+
+        Ok(Self {
+            fs_sig: data.fs_sig,
+            len: data.len,
+            frames: data.frames,
+            segments: Segments {
+                segments: crate::frames::segments(data.envelopes.transpose()),
+                num_bands: data.envelopes.nrows(),
+            },
+        })
+    }
+}
+
+/// Resample a signal to the internal 10kHz rate with the polyphase resampler used by `stoi`.
+#[cfg(feature = "std")]
+pub fn resample(signal: &[f32], fs_sig: usize) -> Vec<f32> {
+    CLASSIC.resample(signal, fs_sig)
+}
+
+/// Select the non-silent frames of a clean 10kHz signal.
+#[cfg(feature = "std")]
+pub fn frames(x: &[f32]) -> Frames {
+    CLASSIC.frames(x)
+}
+
+/// Compute the squared magnitude spectrogram of the selected frames of a 10kHz signal.
+#[cfg(feature = "std")]
+pub fn spectrogram(signal: &[f32], frames: &Frames) -> Spectrogram {
+    CLASSIC.spectrogram(signal, frames)
+}
+
+/// Pool a spectrogram into third octave band envelopes.
+#[cfg(feature = "std")]
+pub fn octave_envelopes(spectrogram: &Spectrogram) -> Envelopes {
+    CLASSIC.octave_envelopes(spectrogram)
+}
+
+/// Slice envelopes into overlapping segments.
+/// Fails if there are not enough frames to form a single segment.
+#[cfg(feature = "std")]
+pub fn segments(envelopes: &Envelopes) -> Result<Segments> {
+    CLASSIC.segments(envelopes)
+}
+
+/// Precompute the clean reference of a signal.
+/// Fails if there are not enough non-silent frames.
+#[cfg(feature = "std")]
+pub fn reference(x: &[f32], fs_sig: usize) -> Result<Reference> {
+    CLASSIC.reference(x, fs_sig)
+}
+
+/// Compute STOI between a precomputed clean reference and a processed signal.
+#[cfg(feature = "std")]
+pub fn stoi_from_reference(reference: &Reference, y: &[f32], extended: bool) -> f32 {
+    CLASSIC.stoi_from_reference(reference, y, extended)
+}
+
+/// Compute STOI from the segments of the clean and processed signals.
+#[cfg(feature = "std")]
+pub fn stoi_from_segments(x: Segments, y: Segments, extended: bool) -> f32 {
+    CLASSIC.stoi_from_segments(x, y, extended)
+}
+
+/// Compute STOI from the third octave band envelopes of the clean and processed signals.
+#[cfg(feature = "std")]
+pub fn stoi_from_envelopes(x: &Envelopes, y: &Envelopes, extended: bool) -> Result<f32> {
+    CLASSIC.stoi_from_envelopes(x, y, extended)
+}
+
+/// Compute STOI from the spectrograms of the clean and processed signals.
+/// Spectrograms must be computed at 10kHz with a 512-point FFT
+/// over the non-silent frames of the clean signal.
+#[cfg(feature = "std")]
+pub fn stoi_from_spectrogram(x: &Spectrogram, y: &Spectrogram, extended: bool) -> Result<f32> {
+    CLASSIC.stoi_from_spectrogram(x, y, extended)
+}
@@ -0,0 +1,197 @@
+//! Permutation-invariant STOI for source separation
+
+use faer::prelude::*;
+
+/// Result of the permutation-invariant STOI between reference and estimated sources.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PitStoi {
+    /// STOI matrix of shape (references, estimates)
+    pub scores: Mat<f32>,
+    /// Index of the estimate assigned to each reference
+    pub permutation: Vec<usize>,
+    /// Mean STOI of the assigned pairs
+    pub mean: f32,
+}
+
+impl PitStoi {
+    /// Find the assignment of estimates to references with the highest total STOI.
+    /// There must be at least one reference, and at least as many estimates as references.
+    pub fn from_scores(scores: Mat<f32>) -> Self {
+        assert!(scores.nrows() > 0, "There must be at least one reference");
+        assert!(
+            scores.nrows() <= scores.ncols(),
+            "There must be at least as many estimates as references"
+        );
+
+        let permutation = best_assignment(scores.as_ref());
+        let mean = permutation
+            .iter()
+            .enumerate()
+            .map(|(i, &j)| scores[(i, j)])
+            .sum::<f32>()
+            / permutation.len() as f32;
+
+        Self {
+            scores,
+            permutation,
+            mean,
+        }
+    }
+}
+
+/// Find the assignment maximizing the total score of a matrix with at least as many
+/// columns as rows, with the Hungarian algorithm (O(n²m)).
+/// Returns the column assigned to each row.
+fn best_assignment(scores: MatRef<f32>) -> Vec<usize> {
+    let (n, m) = (scores.nrows(), scores.ncols());
+    // Minimize the negated scores, with 1-based indices (0 is a sentinel)
+    let cost = |i: usize, j: usize| -scores[(i - 1, j - 1)] as f64;
+
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; m + 1];
+    // Row assigned to each column
+    let mut p = vec![0; m + 1];
+    let mut way = vec![0; m + 1];
+
+    for i in 1..=n {
+        p[0] = i;
+        let mut j0 = 0;
+        let mut min_v = vec![f64::INFINITY; m + 1];
+        let mut used = vec![false; m + 1];
+
+        // Find an augmenting path from row i
+        loop {
+            used[j0] = true;
+            let i0 = p[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+
+            for j in 1..=m {
+                if used[j] {
+                    continue;
+                }
+                let reduced = cost(i0, j) - u[i0] - v[j];
+                if reduced < min_v[j] {
+                    min_v[j] = reduced;
+                    way[j] = j0;
+                }
+                if min_v[j] < delta {
+                    delta = min_v[j];
+                    j1 = j;
+                }
+            }
+
+            for j in 0..=m {
+                if used[j] {
+                    u[p[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_v[j] -= delta;
+                }
+            }
+
+            j0 = j1;
+            if p[j0] == 0 {
+                break;
+            }
+        }
+
+        // Flip the augmenting path
+        while j0 != 0 {
+            let j1 = way[j0];
+            p[j0] = p[j1];
+            j0 = j1;
+        }
+    }
+
+    let mut assignment = vec![0; n];
+    for j in (1..=m).filter(|&j| p[j] != 0) {
+        assignment[p[j] - 1] = j - 1;
+    }
+
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Best total score over all the assignments, by brute force
+    fn brute_force_best(scores: MatRef<f32>) -> f32 {
+        fn search(scores: MatRef<f32>, row: usize, used: &mut [bool]) -> f32 {
+            if row == scores.nrows() {
+                return 0.0;
+            }
+
+            let mut best = f32::NEG_INFINITY;
+            for j in 0..scores.ncols() {
+                if !used[j] {
+                    used[j] = true;
+                    best = best.max(scores[(row, j)] + search(scores, row + 1, used));
+                    used[j] = false;
+                }
+            }
+            best
+        }
+
+        search(scores, 0, &mut vec![false; scores.ncols()])
+    }
+
+    #[test]
+    fn permuted_square_scores() {
+        // Estimates 0, 1, 2 match references 2, 0, 1
+        let scores = mat![[0.2, 0.3, 0.9], [0.8, 0.1, 0.4], [0.3, 0.7, 0.2_f32]];
+        let pit = PitStoi::from_scores(scores.clone());
+
+        assert_eq!(pit.permutation, vec![2, 0, 1]);
+        assert!((pit.mean - 0.8).abs() < 1e-6);
+        assert!((pit.mean * 3.0 - brute_force_best(scores.as_ref())).abs() < 1e-6);
+    }
+
+    #[test]
+    fn greedy_choice_is_not_optimal() {
+        // Assigning the best pair (0, 0) first loses overall
+        let scores = mat![[0.9, 0.8], [0.7, 0.1_f32]];
+
+        assert_eq!(PitStoi::from_scores(scores).permutation, vec![1, 0]);
+    }
+
+    #[test]
+    fn rectangular_scores() {
+        // More estimates than references: the extra estimates are left out
+        let scores = mat![[0.1, 0.6, 0.5, 0.2], [0.2, 0.7, 0.3, 0.9_f32]];
+        let pit = PitStoi::from_scores(scores.clone());
+
+        assert_eq!(pit.permutation, vec![1, 3]);
+        assert!((pit.mean * 2.0 - brute_force_best(scores.as_ref())).abs() < 1e-6);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut state = 1u64;
+        let mut value = || {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (state >> 40) as f32 / (1u64 << 24) as f32
+        };
+
+        for (n, m) in [(1, 1), (4, 4), (5, 5), (3, 6), (5, 7)] {
+            let scores = Mat::from_fn(n, m, |_, _| value());
+            let pit = PitStoi::from_scores(scores.clone());
+
+            let mut columns = pit.permutation.clone();
+            columns.sort_unstable();
+            columns.dedup();
+            assert_eq!(columns.len(), n, "each estimate is assigned once");
+            assert!((pit.mean * n as f32 - brute_force_best(scores.as_ref())).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one reference")]
+    fn empty_scores() {
+        PitStoi::from_scores(Mat::zeros(0, 0));
+    }
+}
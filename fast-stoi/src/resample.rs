@@ -1,17 +1,25 @@
 //! Sinc poly resampling
 
-use std::f32::consts::PI;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::f32::consts::PI;
 
+#[cfg(feature = "std")]
 use dashmap::DashMap;
+#[cfg(feature = "std")]
 use lazy_static::lazy_static;
 use num::integer;
+#[cfg(feature = "std")]
 use windowfunctions::{Symmetry, WindowFunction, window};
 
+#[cfg(not(feature = "std"))]
+use crate::math::{Float, Symmetry, WindowFunction, window};
 use crate::upfirdn::upfirdn;
 
+#[cfg(feature = "std")]
 lazy_static! {
-    /// Cache filters for different from rates
-    static ref WINDOWS: DashMap<usize, Vec<f32>> = DashMap::new();
+    /// Cache resamplers for different (up, down) resampling ratios
+    static ref RESAMPLERS: DashMap<(usize, usize), Resampler> = DashMap::new();
 }
 
 const REJECTION_DB: f32 = 60.0;
@@ -41,7 +49,7 @@ fn kaiser(beta: f32, half_length: usize) -> impl Iterator<Item = f32> {
 /// Generates an apodized Kaiser window collected into a Row.
 fn apodized_kaiser_window(f: f32, beta: f32, half_length: usize) -> Vec<f32> {
     let sinc_iter = ideal_sinc(f, half_length);
-    let kaiser_iter = kaiser(beta as f32, half_length);
+    let kaiser_iter = kaiser(beta, half_length);
 
     sinc_iter
         .zip(kaiser_iter)
@@ -55,18 +63,23 @@ fn generate_filter_phases(up: usize, down: usize) -> Vec<f32> {
     let stopband_cutoff_freq = 1.0 / (2.0 * up.max(down) as f32);
     let roll_off_width = stopband_cutoff_freq / 10.0;
 
-    // Compute the filter
+    lowpass_filter(stopband_cutoff_freq, roll_off_width)
+}
+
+/// Design a zero-phase Kaiser windowed sinc low-pass filter with unit DC gain,
+/// given its normalized cutoff frequency and roll-off width.
+pub(crate) fn lowpass_filter(cutoff: f32, roll_off_width: f32) -> Vec<f32> {
     let filter_half_length = ((REJECTION_DB - 8.0) / (28.714 * roll_off_width)).ceil() as u32;
     let beta = 0.1102 * (REJECTION_DB - 8.7);
-    let mut filter =
-        apodized_kaiser_window(stopband_cutoff_freq, beta, filter_half_length as usize);
+    let mut filter = apodized_kaiser_window(cutoff, beta, filter_half_length as usize);
     let sum: f32 = filter.iter().sum();
     filter.iter_mut().for_each(|v| *v /= sum);
 
     filter
 }
 
-/// Polyphase resampling.
+/// Polyphase resampler from one sampling frequency to another,
+/// holding its Kaiser windowed sinc filter.
 ///
 /// About this resampling operation:
 /// - zero-phase => the window is symmetric (does not introduce any shift)
@@ -75,24 +88,63 @@ fn generate_filter_phases(up: usize, down: usize) -> Vec<f32> {
 /// - low-pass => when upsampling by inserting zeros, if we upsample times n,
 ///   we create high frequency signals.
 ///   The window must smooth them out and remove these high frequencies
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    up: usize,
+    down: usize,
+    filter: Vec<f32>,
+}
+
+impl Resampler {
+    /// Design the filter to resample signals from `from` Hz to `to` Hz
+    pub fn new(from: usize, to: usize) -> Self {
+        assert!(from > 0 && to > 0, "Sampling frequencies must be positive");
+
+        // Compute upsampling and dowsampling ratios
+        let gcd = integer::gcd(from, to);
+        let up = to / gcd;
+        let down = from / gcd;
+
+        Self {
+            up,
+            down,
+            filter: generate_filter_phases(up, down),
+        }
+    }
+
+    /// Resample a signal
+    pub fn process(&self, x: &[f32]) -> Vec<f32> {
+        upfirdn(&self.filter, x, self.up, self.down)
+    }
+}
+
+/// Polyphase resampling, with the resampler of each ratio cached across calls.
+#[cfg(feature = "std")]
 pub fn resample(x: &[f32], from: usize, to: usize) -> Vec<f32> {
     // Compute upsampling and dowsampling ratios
     let gcd = integer::gcd(from, to);
     let up = to / gcd;
     let down = from / gcd;
 
-    // Get the filters
-    // If filters are missing, they are inserted and fetched
+    // Get the resampler
+    // If it is missing, it is inserted and fetched
     // again to drop the exclusive mutable ref held by entry
-    let filter = match WINDOWS.get(&from) {
-        Some(f) => f,
+    let resampler = match RESAMPLERS.get(&(up, down)) {
+        Some(r) => r,
         None => {
-            let _ = WINDOWS
-                .entry(from)
-                .or_insert_with(|| generate_filter_phases(up, down));
-            WINDOWS.get(&from).unwrap()
+            let _ = RESAMPLERS
+                .entry((up, down))
+                .or_insert_with(|| Resampler::new(from, to));
+            RESAMPLERS.get(&(up, down)).unwrap()
         }
     };
 
-    upfirdn(&filter, x, up, down)
+    resampler.process(x)
+}
+
+/// Polyphase resampling, designing the filter for each call.
+/// Use a [`Resampler`] to design it once.
+#[cfg(not(feature = "std"))]
+pub fn resample(x: &[f32], from: usize, to: usize) -> Vec<f32> {
+    Resampler::new(from, to).process(x)
 }
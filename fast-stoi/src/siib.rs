@@ -0,0 +1,456 @@
+//! Speech Intelligibility In Bits (SIIB)
+//!
+//! Implementation of the SIIB measure from Van Kuyk et al., "An instrumental
+//! intelligibility metric based on information theory" (IEEE Signal Processing Letters, 2018).
+//!
+//! SIIB estimates the information rate shared by the clean and processed signals, in bits/s:
+//! 1. Both signals are resampled to 16kHz, framed with plain hann windows (the silent frames
+//!    of the clean signal are removed) and transformed with an RFFT.
+//!    Unlike STOI, the frames are not overlap-added back into a signal before the RFFT.
+//! 2. Log energy envelopes are extracted in ERB-spaced gammatone bands.
+//! 3. Consecutive envelope frames are stacked to account for temporal context.
+//! 4. The stacked vectors are decorrelated with the Karhunen-Loève transform (KLT)
+//!    of the clean vectors, which is applied to both signals.
+//! 5. The mutual information of each KLT component is estimated with the
+//!    k-nearest-neighbour estimator from Kraskov et al., and summed over components.
+//! 6. The information per stacked vector is converted to an information rate in bits/s.
+//!
+//! SIIB shares the 512-point RFFT plan of the classic STOI engine, but keeps its own analysis:
+//! its 16kHz rate, 25ms frames and gammatone filterbank differ from the 10kHz, 256-sample frames
+//! and third octave bands of the STOI engines, so their windows and bands cannot be reused.
+
+use faer::{Side, prelude::*};
+use lazy_static::lazy_static;
+
+use crate::{
+    constants::{self, PARALLEL_MIN_FRAMES, PARALLEL_MIN_LENGTH},
+    engine::CLASSIC,
+    errors::{NotEnoughFramesError, Result},
+    frames::FrameWindows,
+    parallel, resample, stft,
+};
+
+/// Internal sampling frequency
+const FS: usize = 16_000;
+/// Frame length (25ms)
+const FRAME_LENGTH: usize = 400;
+/// FFT length, shared with the classic STOI engine
+const FFT_LENGTH: usize = constants::FFT_LENGTH;
+/// Frequency range of the gammatone filterbank
+const MIN_FREQ: f64 = 100.0;
+const MAX_FREQ: f64 = 6500.0;
+/// Amount of stacked consecutive frames (250ms of temporal context)
+const STACK_LENGTH: usize = 20;
+
+lazy_static! {
+    static ref ANALYSIS: Analysis = Analysis::new();
+}
+
+/// Precomputed windows and gammatone filterbank
+struct Analysis {
+    windows: FrameWindows,
+    /// Gammatone power responses of shape (num_filters, fft_bins)
+    filterbank: Mat<f32>,
+}
+
+impl Analysis {
+    fn new() -> Self {
+        Self {
+            windows: FrameWindows::plain(FRAME_LENGTH),
+            filterbank: gammatone_filterbank(),
+        }
+    }
+
+    /// Compute the log gammatone envelopes of shape (num_filters, frames) of a 16kHz signal
+    fn envelopes(&self, signal: &[f32], frames: &[usize]) -> Mat<f32> {
+        let power = stft::compute_frame_rffts(signal, frames, &self.windows, CLASSIC.r2c());
+        let mut envelopes = &self.filterbank * &power;
+
+        envelopes
+            .col_iter_mut()
+            .for_each(|col| col.iter_mut().for_each(|v| *v = (*v + f32::EPSILON).ln()));
+
+        envelopes
+    }
+}
+
+/// ERB-rate scale (number of ERBs below a frequency)
+fn erb_rate(freq: f64) -> f64 {
+    21.4 * (1.0 + 0.00437 * freq).log10()
+}
+
+/// Build the power responses of 4th order gammatone filters with ERB-spaced
+/// center frequencies between `MIN_FREQ` and `MAX_FREQ`.
+fn gammatone_filterbank() -> Mat<f32> {
+    let (low, high) = (erb_rate(MIN_FREQ), erb_rate(MAX_FREQ));
+    let num_filters = (high - low).round() as usize;
+
+    Mat::from_fn(num_filters, FFT_LENGTH / 2 + 1, |j, bin| {
+        let center = (10.0_f64
+            .powf((low + (high - low) * j as f64 / (num_filters - 1) as f64) / 21.4)
+            - 1.0)
+            / 0.00437;
+        let bandwidth = 1.019 * 24.7 * (4.37 * center / 1000.0 + 1.0);
+        let freq = bin as f64 * FS as f64 / FFT_LENGTH as f64;
+
+        (1.0 + ((freq - center) / bandwidth).powi(2)).powi(-4) as f32
+    })
+}
+
+/// Compute SIIB between a clean signal `x` and a processed signal `y`, in bits/s.
+pub fn siib(x: &[f32], y: &[f32], fs_sig: usize) -> Result<f32> {
+    assert!(
+        x.len() == y.len(),
+        "Input signals must have the same length"
+    );
+
+    let (x, y) = if fs_sig != FS {
+        parallel::join(
+            x.len() * constants::FS / fs_sig >= PARALLEL_MIN_LENGTH,
+            || resample::resample(x, fs_sig, FS),
+            || resample::resample(y, fs_sig, FS),
+        )
+    } else {
+        (x.to_vec(), y.to_vec())
+    };
+
+    // Select frames
+    let frames = ANALYSIS.windows.valid_frames(&x);
+
+    if frames.len() < 2 * STACK_LENGTH {
+        return Err(NotEnoughFramesError);
+    }
+
+    let (x_envelopes, y_envelopes) = parallel::join(
+        frames.len() >= PARALLEL_MIN_FRAMES,
+        || ANALYSIS.envelopes(&x, &frames),
+        || ANALYSIS.envelopes(&y, &frames),
+    );
+
+    let x_stacked = stacked(x_envelopes.as_ref());
+    let y_stacked = stacked(y_envelopes.as_ref());
+
+    // KLT: project both signals onto the eigenvectors of the clean covariance
+    // (the covariance scale does not change the eigenvectors)
+    let covariance = &x_stacked * x_stacked.transpose();
+    let klt = covariance
+        .self_adjoint_eigen(Side::Lower)
+        .expect("Eigendecomposition of the clean covariance failed");
+    let x_components = x_stacked.transpose() * klt.U();
+    let y_components = y_stacked.transpose() * klt.U();
+
+    let vectors = x_components.nrows();
+    let neighbours = vectors.div_ceil(100).max(2);
+    let component_information = |d: usize| {
+        let x = x_components.col(d).iter().copied().collect::<Vec<_>>();
+        let y = y_components.col(d).iter().copied().collect::<Vec<_>>();
+        mutual_information(&x, &y, neighbours)
+    };
+
+    // There are hundreds of components, so they are always estimated in parallel.
+    // Sum them in order so that the result stays deterministic
+    #[cfg(feature = "rayon")]
+    let information = {
+        use rayon::prelude::*;
+        (0..x_components.ncols())
+            .into_par_iter()
+            .map(component_information)
+            .collect::<Vec<_>>()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let information = (0..x_components.ncols())
+        .map(component_information)
+        .collect::<Vec<_>>();
+
+    Ok(information_rate(information.iter().sum()).max(0.0) as f32)
+}
+
+/// Convert mutual information in nats per stacked vector to bits/s.
+///
+/// A stacked vector spans `STACK_LENGTH` frames, and consecutive vectors are one frame apart:
+/// its information is shared by the `STACK_LENGTH` frames it spans. Dividing by `STACK_LENGTH`
+/// gives the information per frame, and the frame rate (80Hz) converts it to a rate.
+fn information_rate(nats: f64) -> f64 {
+    let frame_rate = FS as f64 / (FRAME_LENGTH / 2) as f64;
+    let bits = nats / std::f64::consts::LN_2;
+
+    bits * frame_rate / STACK_LENGTH as f64
+}
+
+/// Compute SIIB for a batch of clean and processed signal pairs, in bits/s.
+pub fn batch_siib(x: &[&[f32]], y: &[&[f32]], fs_sig: usize) -> Vec<Result<f32>> {
+    assert!(
+        x.len() == y.len(),
+        "Input batches must have the same length"
+    );
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+
+        x.par_iter()
+            .zip(y)
+            .map(|(x, y)| siib(x, y, fs_sig))
+            .collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        x.iter().zip(y).map(|(x, y)| siib(x, y, fs_sig)).collect()
+    }
+}
+
+/// Stack `STACK_LENGTH` consecutive envelope frames into mean-removed vectors
+/// of shape (num_filters * STACK_LENGTH, frames - STACK_LENGTH + 1).
+fn stacked(envelopes: MatRef<f32>) -> Mat<f64> {
+    let num_filters = envelopes.nrows();
+    let mut stacked = Mat::from_fn(
+        num_filters * STACK_LENGTH,
+        envelopes.ncols() - STACK_LENGTH + 1,
+        |i, t| envelopes[(i % num_filters, t + i / num_filters)] as f64,
+    );
+
+    stacked.row_iter_mut().for_each(|row| {
+        let mean = row.sum() / row.ncols() as f64;
+        row.iter_mut().for_each(|v| *v -= mean);
+    });
+
+    stacked
+}
+
+/// Estimate the mutual information (in nats) between two scalar variables
+/// with the first k-nearest-neighbour estimator from Kraskov et al. (max-norm).
+fn mutual_information(x: &[f64], y: &[f64], k: usize) -> f64 {
+    let x = standardized(x);
+    let y = standardized(y);
+    let n = x.len();
+
+    // Sort points by x to search neighbours outwards from each point
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| x[a].total_cmp(&x[b]));
+    let x_sorted = order.iter().map(|&i| x[i]).collect::<Vec<_>>();
+    let y_by_x = order.iter().map(|&i| y[i]).collect::<Vec<_>>();
+    let mut y_sorted = y.clone();
+    y_sorted.sort_by(f64::total_cmp);
+
+    let mut distances = Vec::with_capacity(k + 1);
+    let mut marginal_digammas = 0.0;
+
+    for p in 0..n {
+        let (xp, yp) = (x_sorted[p], y_by_x[p]);
+
+        // Find the distance to the k-th nearest neighbour in the joint space
+        distances.clear();
+        let (mut left, mut right) = (p, p + 1);
+        loop {
+            let left_dx = left
+                .checked_sub(1)
+                .map_or(f64::INFINITY, |q| xp - x_sorted[q]);
+            let right_dx = x_sorted.get(right).map_or(f64::INFINITY, |&v| v - xp);
+            let dx = left_dx.min(right_dx);
+            if distances.len() == k && dx >= distances[k - 1] || dx == f64::INFINITY {
+                break;
+            }
+
+            let q = if left_dx <= right_dx {
+                left -= 1;
+                left
+            } else {
+                right += 1;
+                right - 1
+            };
+            let distance = dx.max((yp - y_by_x[q]).abs());
+            let index = distances.partition_point(|&d| d <= distance);
+            distances.insert(index, distance);
+            distances.truncate(k);
+        }
+        let epsilon = distances[k - 1];
+
+        // Count the neighbours strictly closer than epsilon in each marginal space
+        marginal_digammas += digamma(count_within(&x_sorted, xp, epsilon) as f64 + 1.0)
+            + digamma(count_within(&y_sorted, yp, epsilon) as f64 + 1.0);
+    }
+
+    digamma(k as f64) + digamma(n as f64) - marginal_digammas / n as f64
+}
+
+/// Standardize values to zero mean and unit variance
+fn standardized(values: &[f64]) -> Vec<f64> {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let std = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+    let scale = if std > 0.0 { 1.0 / std } else { 0.0 };
+
+    values.iter().map(|v| (v - mean) * scale).collect()
+}
+
+/// Amount of sorted values strictly closer than `epsilon` to `center`, excluding itself.
+/// Distances are compared as differences, the way `epsilon` was computed.
+/// No value is strictly closer than a zero `epsilon` (duplicated points).
+fn count_within(sorted: &[f64], center: f64, epsilon: f64) -> usize {
+    let high = sorted.partition_point(|&v| v - center < epsilon);
+    let low = sorted.partition_point(|&v| center - v >= epsilon);
+    high.saturating_sub(low).saturating_sub(1)
+}
+
+/// Digamma function, using the recurrence relation and its asymptotic expansion
+fn digamma(mut x: f64) -> f64 {
+    let mut result = 0.0;
+    while x < 6.0 {
+        result -= 1.0 / x;
+        x += 1.0;
+    }
+
+    let f = 1.0 / (x * x);
+    result + x.ln()
+        - 0.5 / x
+        - f * (1.0 / 12.0 - f * (1.0 / 120.0 - f * (1.0 / 252.0 - f * (1.0 / 240.0 - f / 132.0))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic uniform values in [0, 1)
+    fn uniform(len: usize, seed: u64) -> Vec<f64> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                (state >> 11) as f64 / (1u64 << 53) as f64
+            })
+            .collect()
+    }
+
+    /// Deterministic standard normal values (Box-Muller)
+    fn normal(len: usize, seed: u64) -> Vec<f64> {
+        uniform(2 * len, seed)
+            .chunks_exact(2)
+            .map(|u| (-2.0 * (1.0 - u[0]).ln()).sqrt() * (2.0 * std::f64::consts::PI * u[1]).cos())
+            .collect()
+    }
+
+    #[test]
+    fn frames_are_windowed_with_plain_hann() {
+        let signal = uniform(4 * FRAME_LENGTH, 1)
+            .iter()
+            .map(|&v| v as f32 - 0.5)
+            .collect::<Vec<_>>();
+        let frames = [0, FRAME_LENGTH / 2, 2 * FRAME_LENGTH];
+        let power = stft::compute_frame_rffts(&signal, &frames, &ANALYSIS.windows, CLASSIC.r2c());
+
+        // Direct DFT of the frames windowed with hann(FRAME_LENGTH + 2)[1:-1]
+        for (f, &start) in frames.iter().enumerate() {
+            for bin in [0, 10, 100, FFT_LENGTH / 2] {
+                let (mut re, mut im) = (0.0, 0.0);
+                for n in 0..FRAME_LENGTH {
+                    let w = 0.5
+                        - 0.5
+                            * (2.0 * std::f64::consts::PI * (n + 1) as f64
+                                / (FRAME_LENGTH + 1) as f64)
+                                .cos();
+                    let angle = -2.0 * std::f64::consts::PI * (bin * n) as f64 / FFT_LENGTH as f64;
+                    re += w * signal[start + n] as f64 * angle.cos();
+                    im += w * signal[start + n] as f64 * angle.sin();
+                }
+                let expected = re * re + im * im;
+                let actual = power[(bin, f)] as f64;
+                assert!(
+                    (actual - expected).abs() <= 1e-4 * expected.max(1.0),
+                    "frame {f}, bin {bin}: {actual} != {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn information_rate_in_bits_per_second() {
+        // One bit per frame, at 80 frames per second
+        let nats = STACK_LENGTH as f64 * std::f64::consts::LN_2;
+        assert!((information_rate(nats) - 80.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn digamma_reference_values() {
+        for (x, expected) in [
+            (1.0, -0.577_215_664_901_532_9),
+            (0.5, -1.963_510_026_021_423_5),
+            (10.0, 2.251_752_589_066_721),
+            (1000.0, 6.907_255_195_648_812),
+        ] {
+            assert!((digamma(x) - expected).abs() < 1e-10, "digamma({x})");
+        }
+    }
+
+    /// Kraskov estimator with a brute force neighbour search
+    fn brute_force_mutual_information(x: &[f64], y: &[f64], k: usize) -> f64 {
+        let (x, y) = (standardized(x), standardized(y));
+        let n = x.len();
+
+        let marginal_digammas = (0..n)
+            .map(|i| {
+                let mut distances = (0..n)
+                    .filter(|&j| j != i)
+                    .map(|j| (x[i] - x[j]).abs().max((y[i] - y[j]).abs()))
+                    .collect::<Vec<_>>();
+                distances.sort_by(f64::total_cmp);
+                let epsilon = distances[k - 1];
+
+                let nx = (0..n).filter(|&j| j != i && (x[i] - x[j]).abs() < epsilon);
+                let ny = (0..n).filter(|&j| j != i && (y[i] - y[j]).abs() < epsilon);
+                digamma(nx.count() as f64 + 1.0) + digamma(ny.count() as f64 + 1.0)
+            })
+            .sum::<f64>();
+
+        digamma(k as f64) + digamma(n as f64) - marginal_digammas / n as f64
+    }
+
+    #[test]
+    fn neighbour_search_matches_brute_force() {
+        let x = normal(500, 1);
+        let y = x
+            .iter()
+            .zip(normal(500, 2))
+            .map(|(x, n)| x + 0.5 * n)
+            .collect::<Vec<_>>();
+
+        for k in [2, 5] {
+            let expected = brute_force_mutual_information(&x, &y, k);
+            let actual = mutual_information(&x, &y, k);
+            assert!((actual - expected).abs() < 1e-12, "{actual} != {expected}");
+        }
+    }
+
+    #[test]
+    fn duplicated_points() {
+        // Constant components have every neighbour at distance 0
+        let constant = vec![0.0; 100];
+        assert!(mutual_information(&constant, &constant, 2).is_finite());
+
+        let repeated = normal(50, 6).repeat(2);
+        let expected = brute_force_mutual_information(&repeated, &repeated, 2);
+        let actual = mutual_information(&repeated, &repeated, 2);
+        assert!((actual - expected).abs() < 1e-12, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn gaussian_mutual_information() {
+        // Correlated gaussian variables: I = -ln(1 - rho²) / 2 nats
+        let rho: f64 = 0.8;
+        let x = normal(4000, 3);
+        let y = x
+            .iter()
+            .zip(normal(4000, 4))
+            .map(|(x, n)| rho * x + (1.0 - rho * rho).sqrt() * n)
+            .collect::<Vec<_>>();
+
+        let expected = -0.5 * (1.0 - rho * rho).ln();
+        let actual = mutual_information(&x, &y, 40);
+        assert!((actual - expected).abs() < 0.03, "{actual} != {expected}");
+
+        // Independent variables
+        let independent = mutual_information(&x, &normal(4000, 5), 40);
+        assert!(independent.abs() < 0.03, "{independent}");
+    }
+}
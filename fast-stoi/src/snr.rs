@@ -0,0 +1,286 @@
+//! SNR-based quality measures
+
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use faer::prelude::*;
+use lazy_static::lazy_static;
+
+use crate::{
+    errors::{NotEnoughFramesError, Result},
+    frames::FrameWindows,
+    stft::{self, FftPlan},
+};
+
+lazy_static! {
+    /// Cache the fwSegSNR analysis of each sampling frequency
+    static ref ANALYSES: DashMap<usize, Arc<Analysis>> = DashMap::new();
+}
+
+/// Critical band center frequencies and bandwidths in Hz, from Loizou's `comp_fwseg`
+const CRITICAL_BANDS: [(f32, f32); 25] = [
+    (50.0, 70.0),
+    (120.0, 70.0),
+    (190.0, 70.0),
+    (260.0, 70.0),
+    (330.0, 70.0),
+    (400.0, 70.0),
+    (470.0, 70.0),
+    (540.0, 77.3724),
+    (617.372, 86.0056),
+    (703.378, 95.3398),
+    (798.717, 105.411),
+    (904.128, 116.256),
+    (1020.38, 127.914),
+    (1148.3, 140.423),
+    (1288.72, 153.823),
+    (1442.54, 168.154),
+    (1610.7, 183.457),
+    (1794.16, 199.776),
+    (1993.93, 217.153),
+    (2211.08, 235.631),
+    (2446.71, 255.255),
+    (2701.97, 276.072),
+    (2978.04, 298.126),
+    (3276.17, 321.465),
+    (3597.63, 346.136),
+];
+
+/// Frequency weighting exponent of the clean critical band magnitudes
+const GAMMA: f64 = 0.2;
+/// Frame SNR range in dB
+const SNR_MIN: f64 = -10.0;
+const SNR_MAX: f64 = 35.0;
+
+/// Compute the scale-invariant signal-to-distortion ratio (SI-SDR) in dB
+/// between a clean signal `x` and a processed signal `y`.
+pub fn si_sdr(x: &[f32], y: &[f32]) -> f32 {
+    let x_mean = x.iter().map(|&v| v as f64).sum::<f64>() / x.len() as f64;
+    let y_mean = y.iter().map(|&v| v as f64).sum::<f64>() / y.len() as f64;
+
+    let (mut xy, mut xx) = (0.0, 0.0);
+    x.iter().zip(y).for_each(|(&x, &y)| {
+        let (x, y) = (x as f64 - x_mean, y as f64 - y_mean);
+        xy += x * y;
+        xx += x * x;
+    });
+
+    // Project y onto x to get the scaled target
+    let alpha = xy / (xx + f64::EPSILON);
+    let (mut target, mut distortion) = (0.0, 0.0);
+    x.iter().zip(y).for_each(|(&x, &y)| {
+        let t = alpha * (x as f64 - x_mean);
+        target += t * t;
+        distortion += (y as f64 - y_mean - t).powi(2);
+    });
+
+    (10.0 * ((target + f64::EPSILON) / (distortion + f64::EPSILON)).log10()) as f32
+}
+
+/// fwSegSNR analysis at a sampling frequency, with the framing of Loizou's `comp_fwseg`:
+/// 30ms hann windows with 75% overlap, and an FFT of twice the window length
+/// rounded up to a power of two.
+struct Analysis {
+    windows: FrameWindows,
+    r2c: FftPlan,
+    /// Critical band filters of shape (25, fft_length / 2)
+    filters: Mat<f64>,
+}
+
+impl Analysis {
+    fn new(fs: usize) -> Self {
+        let window_length = ((30 * fs + 500) / 1000).max(1);
+        let fft_length = (2 * window_length).next_power_of_two();
+
+        Self {
+            windows: FrameWindows::plain(window_length),
+            r2c: stft::plan(fft_length),
+            filters: critical_band_filters(fs, fft_length / 2),
+        }
+    }
+
+    /// Get the cached analysis of a sampling frequency
+    fn get(fs: usize) -> Arc<Self> {
+        ANALYSES
+            .entry(fs)
+            .or_insert_with(|| Arc::new(Self::new(fs)))
+            .clone()
+    }
+
+    /// Hop length between consecutive frames (75% overlap)
+    fn hop_length(&self) -> usize {
+        (self.windows.frame_length() / 4).max(1)
+    }
+}
+
+/// Build the gaussian critical band filters of shape (25, fft_length / 2) from Loizou's
+/// `comp_fwseg`, for spectrums of `fft_length / 2 + 1` bins at the sampling frequency `fs`.
+/// As in `comp_fwseg`, the Nyquist bin is left out.
+fn critical_band_filters(fs: usize, half_fft: usize) -> Mat<f64> {
+    let max_freq = fs as f64 / 2.0;
+    let min_bandwidth = CRITICAL_BANDS[0].1 as f64;
+    let min_factor = (-30.0_f64 / (2.0 * 2.303)).exp();
+
+    Mat::from_fn(CRITICAL_BANDS.len(), half_fft, |i, j| {
+        let (center, bandwidth) = (CRITICAL_BANDS[i].0 as f64, CRITICAL_BANDS[i].1 as f64);
+        let f0 = (center / max_freq * half_fft as f64).floor();
+        let bw = bandwidth / max_freq * half_fft as f64;
+        let norm_factor = min_bandwidth.ln() - bandwidth.ln();
+
+        let value = (-11.0 * ((j as f64 - f0) / bw).powi(2) + norm_factor).exp();
+        if value > min_factor { value } else { 0.0 }
+    })
+}
+
+/// Compute the frequency-weighted segmental SNR (fwSegSNR) in dB between a clean signal `x`
+/// and a processed signal `y` at rate `fs`, as Loizou's `comp_fwseg` does.
+///
+/// Unlike STOI, the signals are analysed at their own rate with the framing of `comp_fwseg`,
+/// and silent frames are kept. Fails if the signals are shorter than a frame.
+pub fn fw_seg_snr(x: &[f32], y: &[f32], fs: usize) -> Result<f32> {
+    let analysis = Analysis::get(fs);
+    let hop_length = analysis.hop_length();
+    let num_frames =
+        (x.len() / hop_length).saturating_sub(analysis.windows.frame_length() / hop_length);
+    if num_frames == 0 {
+        return Err(NotEnoughFramesError);
+    }
+
+    let frames = (0..num_frames).map(|i| i * hop_length).collect::<Vec<_>>();
+    let x_power = stft::compute_frame_rffts(x, &frames, &analysis.windows, analysis.r2c.as_ref());
+    let y_power = stft::compute_frame_rffts(y, &frames, &analysis.windows, analysis.r2c.as_ref());
+
+    Ok(from_spectrograms(
+        x_power.as_ref(),
+        y_power.as_ref(),
+        analysis.filters.as_ref(),
+    ))
+}
+
+/// Compute fwSegSNR from the squared magnitude spectrograms of the clean and processed signals,
+/// of shape (fft_bins, frames), with critical band filters of shape (25, fft_bins - 1).
+///
+/// The magnitude spectrums of each frame are normalized to a unit area before the critical
+/// band filters, which makes the measure invariant to the gain of either signal.
+/// The SNR of each frame is clipped to [-10, 35] dB, and silent clean frames have an SNR of 0dB.
+fn from_spectrograms(x_power: MatRef<f32>, y_power: MatRef<f32>, filters: MatRef<f64>) -> f32 {
+    let half_fft = filters.ncols();
+    let normalized = |power: MatRef<f32>| {
+        let mut magnitude = Mat::from_fn(half_fft, power.ncols(), |i, j| {
+            (power[(i, j)] as f64).sqrt()
+        });
+        magnitude.col_iter_mut().for_each(|col| {
+            let area = col.sum();
+            if area > 0.0 {
+                col.iter_mut().for_each(|v| *v /= area);
+            }
+        });
+        magnitude
+    };
+
+    let x_bands = filters * normalized(x_power);
+    let y_bands = filters * normalized(y_power);
+
+    let frame_snrs = x_bands.col_iter().zip(y_bands.col_iter()).map(|(x, y)| {
+        let (mut weighted, mut weights) = (0.0, 0.0);
+        // Bands without clean energy have no weight (`comp_fwseg` returns NaN for silent frames)
+        x.iter()
+            .zip(y.iter())
+            .filter(|(x, _)| **x > 0.0)
+            .for_each(|(&x, &y)| {
+                let error = (x - y).powi(2).max(f64::EPSILON);
+                let weight = x.powf(GAMMA);
+                weighted += weight * 10.0 * (x * x / error).log10();
+                weights += weight;
+            });
+        (weighted / (weights + f64::EPSILON)).clamp(SNR_MIN, SNR_MAX)
+    });
+
+    (frame_snrs.sum::<f64>() / x_bands.ncols() as f64) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FS: usize = 10_000;
+
+    /// Deterministic white noise in [-1, 1]
+    fn noise(len: usize, seed: u64) -> Vec<f32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                (state >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+            })
+            .collect()
+    }
+
+    /// Clean signal with loud and quiet parts, and the same signal with noise.
+    /// Must match `signals` in `tests/reference/comp_fwseg.py`.
+    fn signals(len: usize, gain: f32) -> (Vec<f32>, Vec<f32>) {
+        let x = noise(len, 1)
+            .iter()
+            .enumerate()
+            .map(|(i, v)| if (i / 700) % 2 == 0 { 0.25 * v } else { *v })
+            .collect::<Vec<_>>();
+        let y = x
+            .iter()
+            .zip(noise(len, 2))
+            .map(|(x, n)| x + gain * n)
+            .collect();
+        (x, y)
+    }
+
+    /// Scores of `tests/reference/comp_fwseg.py` for noise gains of 0.1 and 0.5
+    const REFERENCE_SCORES: [(f32, f32); 2] = [(0.1, 25.332_561), (0.5, 14.466_996)];
+
+    #[test]
+    fn matches_comp_fwseg() {
+        for (gain, expected) in REFERENCE_SCORES {
+            let (x, y) = signals(3000, gain);
+            let score = fw_seg_snr(&x, &y, FS).unwrap();
+            assert!((score - expected).abs() < 1e-3, "{score} != {expected}");
+        }
+    }
+
+    #[test]
+    fn comp_fwseg_framing() {
+        // 30ms windows with 75% overlap and an FFT of twice their length
+        for (fs, window_length, hop_length, fft_length) in [
+            (10_000, 300, 75, 1024),
+            (16_000, 480, 120, 1024),
+            (8000, 240, 60, 512),
+        ] {
+            let analysis = Analysis::new(fs);
+            assert_eq!(analysis.windows.frame_length(), window_length);
+            assert_eq!(analysis.hop_length(), hop_length);
+            assert_eq!(analysis.r2c.len(), fft_length);
+            assert_eq!(analysis.filters.shape(), (25, fft_length / 2));
+        }
+
+        // Signals shorter than a frame
+        let (x, y) = signals(299, 0.1);
+        assert!(fw_seg_snr(&x, &y, FS).is_err());
+    }
+
+    #[test]
+    fn gain_invariance() {
+        let (x, y) = signals(3000, 0.3);
+        let score = fw_seg_snr(&x, &y, FS).unwrap();
+
+        for gain in [0.01_f32, 0.5, 8.0] {
+            let scaled = y.iter().map(|v| v * gain).collect::<Vec<_>>();
+            let scaled_score = fw_seg_snr(&x, &scaled, FS).unwrap();
+            assert!(
+                (scaled_score - score).abs() < 1e-3,
+                "{scaled_score} != {score}"
+            );
+
+            let scaled_score = fw_seg_snr(&scaled, &scaled, FS).unwrap();
+            assert_eq!(scaled_score, SNR_MAX as f32);
+        }
+    }
+}
@@ -1,10 +1,13 @@
 //! Standard STOI computation from octave segment spectrograms
 
-use std::f32::EPSILON;
-
 use faer::prelude::*;
 
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
 use crate::constants::{BETA, SEGMENT_LENGTH};
+#[cfg(feature = "rayon")]
+use crate::constants::{NUM_BANDS, PARALLEL_MIN_FRAMES};
 
 /// Compute the standard STOI from octave segment spectrograms of the clean and processed signals.
 /// The segments have shapes (segment_length, num_segments * num_bands).
@@ -12,54 +15,104 @@ pub fn from_segments(x_segments: MatMut<f32>, y_segments: MatMut<f32>) -> f32 {
     let clip_value = 10.0_f32.powf(-BETA / 20.0);
     let n = x_segments.ncols();
 
-    let mut similarity = 0.0;
+    // Long signals: compute the per-segment similarities in parallel,
+    // and sum them in order so that the result stays deterministic
+    #[cfg(feature = "rayon")]
+    if n >= NUM_BANDS * PARALLEL_MIN_FRAMES {
+        use rayon::prelude::*;
+
+        let similarities = x_segments
+            .par_col_iter_mut()
+            .zip(y_segments.par_col_iter_mut())
+            .map(|(x_segment, y_segment)| segment_similarity(x_segment, y_segment, clip_value))
+            .collect::<Vec<_>>();
+
+        return similarities.iter().sum::<f32>() / n as f32;
+    }
 
     // Perform the per-segment processing
-    x_segments
+    let similarity: f32 = x_segments
         .col_iter_mut()
         .zip(y_segments.col_iter_mut())
-        .for_each(|(mut x_segment, mut y_segment)| {
-            // Normalize y so that it has the same norm as x
-            // and then clip y
-            // NOTE: faer's .norm_l2 is very slow for such small vectors
-            let xnorm2 = (x_segment.as_ref().iter().map(|x| x * x).sum::<f32>()).sqrt() + EPSILON;
-            let ynorm2 = (y_segment.as_ref().iter().map(|x| x * x).sum::<f32>()).sqrt() + EPSILON;
-            let ratio = xnorm2 / (ynorm2 + EPSILON);
-            let mut x_sum = 0.0;
-            let mut y_sum = 0.0;
-            zip!(&x_segment, &mut y_segment).for_each(|unzip!(x, y)| {
-                *y = (*y * ratio).min(x * (1.0 + clip_value));
-                x_sum += x;
-                y_sum += *y;
-            });
-
-            // Compute means
-            let x_mean = x_sum / SEGMENT_LENGTH as f32;
-            let y_mean = y_sum / SEGMENT_LENGTH as f32;
-
-            // Subtract mean and start computing resulting norm
-            // at the same time
-            let mut x_sq_sum = 0.0;
-            let mut y_sq_sum = 0.0;
-            zip!(&mut x_segment, &mut y_segment).for_each(|unzip!(x, y)| {
-                *x -= x_mean;
-                *y -= y_mean;
-                x_sq_sum += x.powi(2);
-                y_sq_sum += y.powi(2);
-            });
-
-            let x_norm = x_sq_sum.sqrt() + EPSILON;
-            let y_norm = y_sq_sum.sqrt() + EPSILON;
-
-            // Compute pre-normalization similarity
-            let mut s = 0.0;
-            zip!(&x_segment, &y_segment).for_each(|unzip!(x, y)| {
-                s += x * y;
-            });
-
-            // Aggregate similarity and apply normalization
-            similarity += s / (x_norm * y_norm);
-        });
+        .map(|(x_segment, y_segment)| segment_similarity(x_segment, y_segment, clip_value))
+        .sum();
 
     similarity / n as f32
 }
+
+/// Compute the similarity of each clean and processed segment pair (one per segment column).
+/// Summing them in order and dividing by their amount gives the same result as `from_segments`.
+#[cfg(feature = "std")]
+pub fn similarities(x_segments: MatMut<f32>, y_segments: MatMut<f32>) -> Vec<f32> {
+    let clip_value = 10.0_f32.powf(-BETA / 20.0);
+
+    #[cfg(feature = "rayon")]
+    if x_segments.ncols() >= NUM_BANDS * PARALLEL_MIN_FRAMES {
+        use rayon::prelude::*;
+
+        return x_segments
+            .par_col_iter_mut()
+            .zip(y_segments.par_col_iter_mut())
+            .map(|(x_segment, y_segment)| segment_similarity(x_segment, y_segment, clip_value))
+            .collect();
+    }
+
+    x_segments
+        .col_iter_mut()
+        .zip(y_segments.col_iter_mut())
+        .map(|(x_segment, y_segment)| segment_similarity(x_segment, y_segment, clip_value))
+        .collect()
+}
+
+/// Compute the similarity between a clean and a processed segment.
+fn segment_similarity(x_segment: ColMut<f32>, mut y_segment: ColMut<f32>, clip_value: f32) -> f32 {
+    // Normalize y so that it has the same norm as x
+    // and then clip y
+    // NOTE: faer's .norm_l2 is very slow for such small vectors
+    let xnorm2 = (x_segment.as_ref().iter().map(|x| x * x).sum::<f32>()).sqrt() + f32::EPSILON;
+    let ynorm2 = (y_segment.as_ref().iter().map(|x| x * x).sum::<f32>()).sqrt() + f32::EPSILON;
+    let ratio = xnorm2 / (ynorm2 + f32::EPSILON);
+    zip!(&x_segment, &mut y_segment).for_each(|unzip!(x, y)| {
+        *y = (*y * ratio).min(x * (1.0 + clip_value));
+    });
+
+    correlation(x_segment, y_segment)
+}
+
+/// Compute the correlation between a clean and a processed segment:
+/// both are centered, and their dot product is normalized by their norms.
+pub fn correlation(mut x_segment: ColMut<f32>, mut y_segment: ColMut<f32>) -> f32 {
+    let mut x_sum = 0.0;
+    let mut y_sum = 0.0;
+    zip!(&x_segment, &y_segment).for_each(|unzip!(x, y)| {
+        x_sum += x;
+        y_sum += y;
+    });
+
+    // Compute means
+    let x_mean = x_sum / SEGMENT_LENGTH as f32;
+    let y_mean = y_sum / SEGMENT_LENGTH as f32;
+
+    // Subtract mean and start computing resulting norm
+    // at the same time
+    let mut x_sq_sum = 0.0;
+    let mut y_sq_sum = 0.0;
+    zip!(&mut x_segment, &mut y_segment).for_each(|unzip!(x, y)| {
+        *x -= x_mean;
+        *y -= y_mean;
+        x_sq_sum += x.powi(2);
+        y_sq_sum += y.powi(2);
+    });
+
+    let x_norm = x_sq_sum.sqrt() + f32::EPSILON;
+    let y_norm = y_sq_sum.sqrt() + f32::EPSILON;
+
+    // Compute pre-normalization similarity
+    let mut s = 0.0;
+    zip!(&x_segment, &y_segment).for_each(|unzip!(x, y)| {
+        s += x * y;
+    });
+
+    // Apply normalization
+    s / (x_norm * y_norm)
+}
@@ -1,61 +1,156 @@
 //! STFT computation on frames
 
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+#[cfg(feature = "std")]
 use std::sync::Arc;
 
-use faer::prelude::*;
-use lazy_static::lazy_static;
-use num::complex::ComplexFloat;
+use faer::{prelude::*, traits::ComplexField};
+#[cfg(feature = "std")]
 use realfft::{RealFftPlanner, RealToComplex};
 
-use crate::constants::{FFT_BINS, FFT_LENGTH};
+#[cfg(feature = "rayon")]
+use crate::constants::PARALLEL_MIN_FRAMES;
+use crate::frames::FrameWindows;
+#[cfg(not(feature = "std"))]
+use crate::{fft::Rfft, math::Float};
 
-lazy_static! {
-    static ref R2C: Arc<dyn RealToComplex<f32>> =
-        RealFftPlanner::<f32>::new().plan_fft_forward(FFT_LENGTH);
+/// Forward real FFT
+#[cfg(feature = "std")]
+pub type Fft = dyn RealToComplex<f32>;
+#[cfg(not(feature = "std"))]
+pub type Fft = Rfft;
+
+/// Forward real FFT plan owned by an engine
+#[cfg(feature = "std")]
+pub type FftPlan = Arc<Fft>;
+#[cfg(not(feature = "std"))]
+pub type FftPlan = Box<Fft>;
+
+/// Plan a forward real FFT of `fft_length` points
+pub fn plan(fft_length: usize) -> FftPlan {
+    #[cfg(feature = "std")]
+    {
+        RealFftPlanner::<f32>::new().plan_fft_forward(fft_length)
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        Box::new(Rfft::new(fft_length))
+    }
 }
 
-/// Compute the RFFT of each valid frame as indicated by the mask.
-/// Input frames have shape (frame_length, n_frames).
+/// Amount of frames processed by each parallel task
+#[cfg(feature = "rayon")]
+const PARALLEL_CHUNK: usize = 256;
+
+/// Compute the RFFT of each valid frame of the signal.
+/// Frames are sliced from the signal at the given start indices and windowed
+/// on the fly, so that they are handed straight to the FFT.
 /// Returns a real valued squared magnitude spectrogram
-/// of shape (FFT_BINS, frames).
-pub fn compute_frame_rffts(frames: MatRef<f32>, mask: ColRef<bool>, count: usize) -> Mat<f32> {
-    // Create buffers
-    let mut scratch_buffer = R2C.make_scratch_vec();
-    let mut input_buffer = R2C.make_input_vec();
-    let mut output_buffer = R2C.make_output_vec();
+/// of shape (fft_length / 2 + 1, frames).
+pub fn compute_frame_rffts(
+    signal: &[f32],
+    frames: &[usize],
+    windows: &FrameWindows,
+    r2c: &Fft,
+) -> Mat<f32> {
+    compute_frames(signal, frames, windows, r2c, |complex| {
+        complex.re.powi(2) + complex.im.powi(2)
+    })
+}
 
+/// Compute the complex RFFT of each valid frame of the signal, like `compute_frame_rffts`.
+/// Returns a complex spectrogram of shape (fft_length / 2 + 1, frames).
+#[cfg(feature = "std")]
+pub fn compute_frame_spectra(
+    signal: &[f32],
+    frames: &[usize],
+    windows: &FrameWindows,
+    r2c: &Fft,
+) -> Mat<c32> {
+    compute_frames(signal, frames, windows, r2c, |complex| complex)
+}
+
+/// Compute the RFFT of each valid frame of the signal,
+/// storing `bin(value)` for each frequency bin.
+fn compute_frames<T: ComplexField>(
+    signal: &[f32],
+    frames: &[usize],
+    windows: &FrameWindows,
+    r2c: &Fft,
+    bin: impl Fn(c32) -> T + Sync,
+) -> Mat<T> {
     // Create output array as column-major for faster writes
-    let mut spectrogram = Mat::<f32>::zeros(FFT_BINS, count);
-    let mut index = 0; // destination row index (skips invalid frames)
+    let mut spectrogram = Mat::<T>::zeros(r2c.complex_len(), frames.len());
+
+    // Long signals: compute chunks of frames in parallel
+    #[cfg(feature = "rayon")]
+    if frames.len() >= PARALLEL_MIN_FRAMES {
+        use rayon::prelude::*;
+
+        spectrogram
+            .as_mut()
+            .par_col_chunks_mut(PARALLEL_CHUNK)
+            .zip(frames.par_chunks(PARALLEL_CHUNK))
+            .enumerate()
+            .for_each(|(i, (chunk, frames))| {
+                compute_chunk(signal, frames, i == 0, windows, r2c, chunk, &bin)
+            });
+
+        return spectrogram;
+    }
+
+    compute_chunk(
+        signal,
+        frames,
+        true,
+        windows,
+        r2c,
+        spectrogram.as_mut(),
+        &bin,
+    );
+
+    spectrogram
+}
+
+/// Compute the RFFT of a contiguous chunk of frames.
+/// `first_chunk` indicates whether the chunk starts with the first valid frame.
+fn compute_chunk<T: ComplexField>(
+    signal: &[f32],
+    frames: &[usize],
+    first_chunk: bool,
+    windows: &FrameWindows,
+    r2c: &Fft,
+    spectrogram: MatMut<T>,
+    bin: &impl Fn(c32) -> T,
+) {
+    // Create buffers
+    let mut scratch_buffer = r2c.make_scratch_vec();
+    let mut input_buffer = r2c.make_input_vec();
+    let mut output_buffer = r2c.make_output_vec();
 
     // Iterate over valid frames and compute their RFFT
     frames
-        .col_iter()
-        .zip(mask.iter())
-        .for_each(|(frame, &valid)| {
-            if !valid {
-                return;
-            }
-
-            // Copy frame into input buffer with zero padding
-            input_buffer[..frame.nrows()]
-                .copy_from_slice(frame.try_as_col_major().unwrap().as_slice());
+        .iter()
+        .zip(spectrogram.col_iter_mut())
+        .enumerate()
+        .for_each(|(i, (&start, column))| {
+            // Window the frame into the input buffer with zero padding
+            // (the input buffer is used as scratch space by the RFFT)
+            let (frame, padding) = input_buffer.split_at_mut(windows.frame_length());
+            windows.window_frame(signal, start, first_chunk && i == 0, frame);
+            padding.fill(0.0);
 
             // Perform RFFT
-            R2C.process_with_scratch(&mut input_buffer, &mut output_buffer, &mut scratch_buffer)
+            r2c.process_with_scratch(&mut input_buffer, &mut output_buffer, &mut scratch_buffer)
                 .unwrap();
 
-            // Copy squared magnitude spectrum to output spectrogram
-            spectrogram
-                .col_mut(index)
+            // Copy the spectrum to the output spectrogram
+            column
                 .iter_mut()
                 .zip(&output_buffer)
-                .for_each(|(real, complex)| {
-                    *real = complex.re().powi(2) + complex.im().powi(2);
+                .for_each(|(value, &complex)| {
+                    *value = bin(complex);
                 });
-
-            index += 1;
         });
-
-    spectrogram
 }
@@ -0,0 +1,306 @@
+//! SNR sweeps and speech reception threshold (SRT) estimation
+
+use std::{
+    f32::consts::PI,
+    fmt::{self, Display, Formatter},
+};
+
+use num::complex::Complex;
+use realfft::RealFftPlanner;
+
+use crate::{engine::Engine, errors::NotEnoughFramesError, pipeline::Reference};
+
+/// Frame length used to estimate the long-term spectrum and synthesize speech-shaped noise
+const NOISE_FRAME: usize = 512;
+/// Maximum amount of bisection steps when searching the SRT
+const MAX_BISECTIONS: usize = 64;
+
+/// Noise to mix with the clean speech
+#[derive(Debug, Clone, Copy)]
+pub enum Noise<'a> {
+    /// Recorded noise, at least as long as the clean speech.
+    /// A segment of the same length as the clean speech is picked at a seeded random offset.
+    Signal(&'a [f32]),
+    /// Stationary noise with the long-term spectrum of the clean speech
+    SpeechShaped,
+}
+
+/// Error while preparing an SNR sweep
+#[derive(Debug, Clone)]
+pub enum SweepError {
+    /// The recorded noise is shorter than the clean speech, in samples
+    NoiseTooShort { noise: usize, clean: usize },
+    /// The clean speech does not have enough non-silent frames
+    NotEnoughFrames(NotEnoughFramesError),
+}
+
+impl Display for SweepError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoiseTooShort { noise, clean } => write!(
+                f,
+                "The noise must be at least as long as the clean speech: \
+                 it has {noise} samples instead of {clean}"
+            ),
+            Self::NotEnoughFrames(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SweepError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NotEnoughFrames(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<NotEnoughFramesError> for SweepError {
+    fn from(err: NotEnoughFramesError) -> Self {
+        Self::NotEnoughFrames(err)
+    }
+}
+
+/// Error returned when the processing under test does not preserve the length of the mixture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessedLengthError {
+    /// Length of the noisy mixture
+    pub expected: usize,
+    /// Length of the processed signal
+    pub found: usize,
+}
+
+impl Display for ProcessedLengthError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The processed signal has {} samples instead of the {} samples of the mixture",
+            self.found, self.expected
+        )
+    }
+}
+
+impl std::error::Error for ProcessedLengthError {}
+
+/// Small deterministic pseudo-random generator (SplitMix64), so that
+/// noise generation only depends on the seed.
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in [0, 1)
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// STOI of a processing under test as a function of the SNR.
+///
+/// The clean reference is processed once and shared by all the conditions.
+pub struct SnrSweep<'a> {
+    engine: &'a Engine,
+    clean: &'a [f32],
+    noise: Vec<f32>,
+    reference: Reference,
+    extended: bool,
+}
+
+impl<'a> SnrSweep<'a> {
+    /// Prepare a sweep of the clean speech `clean` mixed with `noise`.
+    /// The `seed` makes the noise segment choice and speech-shaped noise reproducible.
+    /// Fails if the recorded noise is shorter than the clean speech,
+    /// or if the clean speech does not have enough non-silent frames.
+    pub fn new(
+        engine: &'a Engine,
+        clean: &'a [f32],
+        noise: Noise,
+        fs_sig: usize,
+        extended: bool,
+        seed: u64,
+    ) -> std::result::Result<Self, SweepError> {
+        let mut rng = SplitMix64(seed);
+
+        let noise = match noise {
+            Noise::Signal(noise) => {
+                if noise.len() < clean.len() {
+                    return Err(SweepError::NoiseTooShort {
+                        noise: noise.len(),
+                        clean: clean.len(),
+                    });
+                }
+                let offset = (rng.next_u64() % (noise.len() - clean.len() + 1) as u64) as usize;
+                noise[offset..offset + clean.len()].to_vec()
+            }
+            Noise::SpeechShaped => speech_shaped_noise(clean, &mut rng),
+        };
+
+        Ok(Self {
+            engine,
+            clean,
+            noise,
+            reference: engine.reference(clean, fs_sig)?,
+            extended,
+        })
+    }
+
+    /// Mix the clean speech with the noise at the given SNR in dB
+    pub fn mixture(&self, snr: f32) -> Vec<f32> {
+        let clean_power = self.clean.iter().map(|x| x * x).sum::<f32>();
+        let noise_power = self.noise.iter().map(|x| x * x).sum::<f32>() + f32::EPSILON;
+        let gain = (clean_power / noise_power / 10.0_f32.powf(snr / 10.0)).sqrt();
+
+        self.clean
+            .iter()
+            .zip(&self.noise)
+            .map(|(x, n)| x + gain * n)
+            .collect()
+    }
+
+    /// Score the processing under test on the mixture at the given SNR in dB.
+    /// `process` receives the noisy mixture and returns the processed signal,
+    /// which must have the same length as the mixture.
+    pub fn score<F>(&self, snr: f32, process: F) -> std::result::Result<f32, ProcessedLengthError>
+    where
+        F: Fn(&[f32]) -> Vec<f32>,
+    {
+        let processed = process(&self.mixture(snr));
+        if processed.len() != self.clean.len() {
+            return Err(ProcessedLengthError {
+                expected: self.clean.len(),
+                found: processed.len(),
+            });
+        }
+
+        Ok(self
+            .engine
+            .stoi_from_reference(&self.reference, &processed, self.extended))
+    }
+
+    /// Score the processing under test at each SNR in dB. See [`Self::score`].
+    /// Conditions are processed in parallel when the `rayon` feature is enabled.
+    pub fn sweep<F>(
+        &self,
+        snrs: &[f32],
+        process: F,
+    ) -> std::result::Result<Vec<f32>, ProcessedLengthError>
+    where
+        F: Fn(&[f32]) -> Vec<f32> + Sync,
+    {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+
+            snrs.par_iter()
+                .map(|&snr| self.score(snr, &process))
+                .collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            snrs.iter().map(|&snr| self.score(snr, &process)).collect()
+        }
+    }
+
+    /// Find the SNR in dB at which STOI crosses `target` by bisection in `[low, high]`,
+    /// within `tolerance` dB. STOI is assumed to increase with the SNR.
+    ///
+    /// Returns `None` if the target is not reached within the interval.
+    /// See [`Self::score`].
+    pub fn srt<F>(
+        &self,
+        target: f32,
+        low: f32,
+        high: f32,
+        tolerance: f32,
+        process: F,
+    ) -> std::result::Result<Option<f32>, ProcessedLengthError>
+    where
+        F: Fn(&[f32]) -> Vec<f32>,
+    {
+        assert!(low < high, "The SNR interval must not be empty");
+
+        if self.score(low, &process)? > target || self.score(high, &process)? < target {
+            return Ok(None);
+        }
+
+        let (mut low, mut high) = (low, high);
+        for _ in 0..MAX_BISECTIONS {
+            if high - low <= tolerance {
+                break;
+            }
+
+            let middle = (low + high) / 2.0;
+            if self.score(middle, &process)? < target {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+
+        Ok(Some((low + high) / 2.0))
+    }
+}
+
+/// Generate stationary noise with the long-term spectrum of `speech`, of the same length.
+///
+/// The long-term spectrum is averaged over hann windowed frames, and the noise is synthesized
+/// by overlap-adding frames with this magnitude spectrum and random phases.
+fn speech_shaped_noise(speech: &[f32], rng: &mut SplitMix64) -> Vec<f32> {
+    let mut planner = RealFftPlanner::<f32>::new();
+    let r2c = planner.plan_fft_forward(NOISE_FRAME);
+    let c2r = planner.plan_fft_inverse(NOISE_FRAME);
+    let hop = NOISE_FRAME / 2;
+
+    // Periodic hann window: overlapping windows sum to one with half overlap
+    let window = (0..NOISE_FRAME)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / NOISE_FRAME as f32).cos())
+        .collect::<Vec<_>>();
+
+    // Long-term average power spectrum
+    let mut input = r2c.make_input_vec();
+    let mut spectrum = r2c.make_output_vec();
+    let mut power = vec![0.0; spectrum.len()];
+    for frame in speech.windows(NOISE_FRAME).step_by(hop) {
+        input
+            .iter_mut()
+            .zip(frame.iter().zip(&window))
+            .for_each(|(i, (x, w))| *i = x * w);
+        r2c.process(&mut input, &mut spectrum).unwrap();
+        power
+            .iter_mut()
+            .zip(&spectrum)
+            .for_each(|(p, s)| *p += s.norm_sqr());
+    }
+    let magnitude = power.iter().map(|p| p.sqrt()).collect::<Vec<_>>();
+
+    // Overlap-add frames with random phases
+    let mut noise = vec![0.0; speech.len() + NOISE_FRAME];
+    let mut output = c2r.make_output_vec();
+    let last_bin = spectrum.len() - 1;
+    for start in (0..speech.len()).step_by(hop) {
+        spectrum.iter_mut().enumerate().for_each(|(k, s)| {
+            *s = if k == 0 || k == last_bin {
+                // DC and Nyquist bins must be real
+                Complex::new(magnitude[k], 0.0)
+            } else {
+                Complex::from_polar(magnitude[k], 2.0 * PI * rng.next_f32())
+            };
+        });
+        c2r.process(&mut spectrum, &mut output).unwrap();
+
+        noise[start..start + NOISE_FRAME]
+            .iter_mut()
+            .zip(output.iter().zip(&window))
+            .for_each(|(n, (o, w))| *n += o * w);
+    }
+
+    noise.truncate(speech.len());
+    noise
+}
@@ -1,7 +1,20 @@
 //! Upfirdn implementation
 
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
 use faer::prelude::*;
 
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+#[cfg(feature = "rayon")]
+use crate::constants::PARALLEL_MIN_LENGTH;
+
+/// Amount of target samples filtered by each parallel task
+#[cfg(feature = "rayon")]
+const CHUNK_LENGTH: usize = 16_384;
+
 /// Upfirdn implementation to mimic scipy.signal.resample_poly
 /// internal behavior (not directly scipy.signal.upfirdn).
 ///
@@ -28,29 +41,50 @@ pub fn upfirdn(h: &[f32], x: &[f32], up: usize, down: usize) -> Vec<f32> {
     // Create output vector
     let mut target = vec![0.0; x.len() * up / down];
 
-    // Prepare iteration indices
-    let mut phase: usize = (h.len() / 2) % up;
+    let start_phase = (h.len() / 2) % up;
     let phase_step = down % up; // Phase step within 0..up
     let x_step = down / up; // Base input step
-    let mut x_start: usize = 0; // Padding ensures it starts at 0
-
-    // Iterate over target samples
-    for y in target.iter_mut() {
-        let p = phase * phase_length;
-
-        *y = RowRef::<f32>::from_slice(&phases[p..p + phase_length])
-            * ColRef::<f32>::from_slice(&padded_x[x_start..x_start + phase_length])
-            * up as f32;
-
-        // Update phase and input start index
-        x_start += x_step;
-        if phase >= phase_step {
-            phase -= phase_step;
-        } else {
-            phase += up - phase_step;
-            x_start += 1; // Carry over
+
+    // Filter the target samples starting at index `offset`
+    let filter = |offset: usize, target: &mut [f32]| {
+        // Prepare iteration indices
+        // (the padding ensures that the first target sample starts at 0)
+        let mut x_start = (offset * down + up - 1 - start_phase) / up;
+        let mut phase = x_start * up + start_phase - offset * down;
+
+        // Iterate over target samples
+        for y in target.iter_mut() {
+            let p = phase * phase_length;
+
+            *y = RowRef::<f32>::from_slice(&phases[p..p + phase_length])
+                * ColRef::<f32>::from_slice(&padded_x[x_start..x_start + phase_length])
+                * up as f32;
+
+            // Update phase and input start index
+            x_start += x_step;
+            if phase >= phase_step {
+                phase -= phase_step;
+            } else {
+                phase += up - phase_step;
+                x_start += 1; // Carry over
+            }
         }
+    };
+
+    // Long signals: filter chunks of target samples in parallel
+    #[cfg(feature = "rayon")]
+    if target.len() >= PARALLEL_MIN_LENGTH {
+        use rayon::prelude::*;
+
+        target
+            .par_chunks_mut(CHUNK_LENGTH)
+            .enumerate()
+            .for_each(|(i, chunk)| filter(i * CHUNK_LENGTH, chunk));
+
+        return target;
     }
 
+    filter(0, &mut target);
+
     target
 }
@@ -0,0 +1,266 @@
+//! Cochlear implant vocoder simulation
+//!
+//! The signal is split into channels by band-pass filters spaced on the Greenwood
+//! cochlear frequency map. The temporal envelope of each channel is extracted by full-wave
+//! rectification and low-pass filtering, and modulates a noise or sine carrier.
+//! Each modulated channel is scaled to the RMS level of its analysis band,
+//! and channels are summed back (Shannon et al., 1995).
+
+use std::f32::consts::PI;
+
+use crate::{resample::lowpass_filter, sweep::SplitMix64, upfirdn::upfirdn};
+
+/// Roll-off width of the vocoder band-pass filters in Hz
+const TRANSITION_WIDTH: f32 = 100.0;
+
+/// Carrier modulated by the channel envelopes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Carrier {
+    /// White noise filtered by the channel analysis filter
+    Noise,
+    /// Sine at the channel center frequency
+    Sine,
+}
+
+/// Channel vocoder approximating what cochlear implant users hear.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vocoder {
+    channels: usize,
+    envelope_cutoff: f32,
+    carrier: Carrier,
+    min_freq: f32,
+    max_freq: f32,
+    seed: u64,
+}
+
+impl Vocoder {
+    /// Create a vocoder with `channels` channels from 100Hz to 8kHz,
+    /// whose envelopes are low-pass filtered at `envelope_cutoff` Hz.
+    pub fn new(channels: usize, envelope_cutoff: f32, carrier: Carrier) -> Self {
+        assert!(channels > 0, "The vocoder must have at least one channel");
+        assert!(
+            envelope_cutoff > 0.0,
+            "The envelope cutoff frequency must be positive"
+        );
+
+        Self {
+            channels,
+            envelope_cutoff,
+            carrier,
+            min_freq: 100.0,
+            max_freq: 8000.0,
+            seed: 0,
+        }
+    }
+
+    /// Set the frequency range covered by the channels.
+    /// The upper edge is lowered below the Nyquist frequency when needed.
+    pub fn with_frequency_range(mut self, min_freq: f32, max_freq: f32) -> Self {
+        assert!(
+            0.0 < min_freq && min_freq < max_freq,
+            "The frequency range must be positive and not empty"
+        );
+        self.min_freq = min_freq;
+        self.max_freq = max_freq;
+        self
+    }
+
+    /// Seed of the noise carriers, for reproducible outputs
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Amount of channels
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Envelope low-pass cutoff frequency in Hz
+    pub fn envelope_cutoff(&self) -> f32 {
+        self.envelope_cutoff
+    }
+
+    /// Carrier type
+    pub fn carrier(&self) -> Carrier {
+        self.carrier
+    }
+
+    /// Channel edge frequencies in Hz at sampling frequency `fs` (channels + 1 values),
+    /// equally spaced on the Greenwood cochlear map.
+    pub fn channel_edges(&self, fs: usize) -> Vec<f32> {
+        let max_freq = self.max_freq.min(fs as f32 / 2.0 - TRANSITION_WIDTH);
+        assert!(
+            self.min_freq < max_freq,
+            "The vocoder frequency range is above the Nyquist frequency"
+        );
+
+        let (low, high) = (greenwood_place(self.min_freq), greenwood_place(max_freq));
+        (0..=self.channels)
+            .map(|i| greenwood_freq(low + (high - low) * i as f32 / self.channels as f32))
+            .collect()
+    }
+
+    /// Vocode a signal at sampling frequency `fs`
+    pub fn process(&self, signal: &[f32], fs: usize) -> Vec<f32> {
+        let edges = self.channel_edges(fs);
+        let fs_f = fs as f32;
+
+        let roll_off_width = TRANSITION_WIDTH / fs_f;
+        let envelope_filter = lowpass_filter(
+            self.envelope_cutoff / fs_f,
+            TRANSITION_WIDTH.min(self.envelope_cutoff) / fs_f,
+        );
+
+        let channel = |i: usize| {
+            let (low, high) = (edges[i], edges[i + 1]);
+            let band_filter = bandpass_filter(low / fs_f, high / fs_f, roll_off_width);
+            let band = upfirdn(&band_filter, signal, 1, 1);
+
+            let rectified = band.iter().map(|x| x.abs()).collect::<Vec<_>>();
+            let envelope = upfirdn(&envelope_filter, &rectified, 1, 1);
+
+            let carrier = match self.carrier {
+                Carrier::Noise => {
+                    let mut rng = SplitMix64(self.seed.wrapping_add(i as u64));
+                    let noise = (0..signal.len())
+                        .map(|_| 2.0 * rng.next_f32() - 1.0)
+                        .collect::<Vec<_>>();
+                    upfirdn(&band_filter, &noise, 1, 1)
+                }
+                Carrier::Sine => {
+                    let center =
+                        greenwood_freq((greenwood_place(low) + greenwood_place(high)) / 2.0);
+                    (0..signal.len())
+                        .map(|n| (2.0 * PI * center * n as f32 / fs_f).sin())
+                        .collect()
+                }
+            };
+
+            let mut modulated = envelope
+                .iter()
+                .zip(&carrier)
+                .map(|(e, c)| e.max(0.0) * c)
+                .collect::<Vec<_>>();
+
+            // Restore the RMS level of the analysis band
+            let gain = (band.iter().map(|x| x * x).sum::<f32>()
+                / (modulated.iter().map(|x| x * x).sum::<f32>() + f32::EPSILON))
+                .sqrt();
+            modulated.iter_mut().for_each(|m| *m *= gain);
+            modulated
+        };
+
+        // Channels are summed in order for deterministic outputs
+        #[cfg(feature = "rayon")]
+        let channels = {
+            use rayon::prelude::*;
+
+            (0..self.channels)
+                .into_par_iter()
+                .map(channel)
+                .collect::<Vec<_>>()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let channels = (0..self.channels).map(channel).collect::<Vec<_>>();
+
+        let mut output = vec![0.0; signal.len()];
+        for channel in &channels {
+            output.iter_mut().zip(channel).for_each(|(o, c)| *o += c);
+        }
+
+        output
+    }
+}
+
+/// Zero-phase band-pass filter between normalized frequencies `low` and `high`,
+/// as the difference of two low-pass filters of the same length.
+fn bandpass_filter(low: f32, high: f32, roll_off_width: f32) -> Vec<f32> {
+    let low = lowpass_filter(low, roll_off_width);
+    let high = lowpass_filter(high, roll_off_width);
+
+    high.iter().zip(&low).map(|(h, l)| h - l).collect()
+}
+
+/// Frequency in Hz at a relative place along the human cochlea (Greenwood, 1990)
+fn greenwood_freq(place: f32) -> f32 {
+    165.4 * (10.0_f32.powf(2.1 * place) - 0.88)
+}
+
+/// Relative place along the human cochlea of a frequency in Hz (Greenwood, 1990)
+fn greenwood_place(freq: f32) -> f32 {
+    (freq / 165.4 + 0.88).log10() / 2.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic white noise in [-1, 1]
+    fn noise(len: usize, seed: u64) -> Vec<f32> {
+        let mut rng = SplitMix64(seed);
+        (0..len).map(|_| 2.0 * rng.next_f32() - 1.0).collect()
+    }
+
+    #[test]
+    fn edges_follow_the_greenwood_map() {
+        let vocoder = Vocoder::new(8, 160.0, Carrier::Noise);
+        let edges = vocoder.channel_edges(16_000);
+        assert_eq!(edges.len(), 9);
+        assert!((edges[0] - 100.0).abs() < 1e-2, "{edges:?}");
+        assert!((edges[8] - 7900.0).abs() < 1.0, "{edges:?}");
+
+        // Equal spacing along the cochlea
+        let places = edges
+            .iter()
+            .map(|&f| greenwood_place(f))
+            .collect::<Vec<_>>();
+        let step = places[1] - places[0];
+        for w in places.windows(2) {
+            assert!((w[1] - w[0] - step).abs() < 1e-4, "{places:?}");
+        }
+
+        // Greenwood (1990): the apex maps to about 20Hz and the base to about 20kHz
+        assert!((greenwood_freq(0.0) - 19.8).abs() < 0.1);
+        assert!((greenwood_freq(1.0) - 20_677.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn edges_stay_below_nyquist() {
+        for fs in [8000, 10_000, 16_000] {
+            let edges = Vocoder::new(16, 160.0, Carrier::Sine).channel_edges(fs);
+            let nyquist = fs as f32 / 2.0;
+            assert!(edges.iter().all(|&f| f < nyquist), "{fs}: {edges:?}");
+            assert!(edges.windows(2).all(|w| w[0] < w[1]), "{fs}: {edges:?}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "above the Nyquist frequency")]
+    fn range_above_nyquist() {
+        Vocoder::new(4, 160.0, Carrier::Noise)
+            .with_frequency_range(5000.0, 8000.0)
+            .channel_edges(8000);
+    }
+
+    #[test]
+    fn output_length_and_seed() {
+        let signal = noise(4000, 1);
+
+        for carrier in [Carrier::Noise, Carrier::Sine] {
+            let vocoder = Vocoder::new(4, 160.0, carrier).with_seed(7);
+            let output = vocoder.process(&signal, 10_000);
+            assert_eq!(output.len(), signal.len());
+            assert_eq!(output, vocoder.process(&signal, 10_000));
+        }
+
+        // Noise carriers depend on the seed
+        let vocoder = Vocoder::new(4, 160.0, Carrier::Noise);
+        assert_ne!(
+            vocoder.clone().with_seed(1).process(&signal, 10_000),
+            vocoder.with_seed(2).process(&signal, 10_000)
+        );
+    }
+}
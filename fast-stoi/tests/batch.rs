@@ -0,0 +1,84 @@
+//! Batch-lane scores must be identical to scoring each clip on its own
+
+mod common;
+
+use common::{degrade, speech};
+
+const FS: usize = 16_000;
+
+/// Clips of different durations, so that their amount of segments differs
+fn clips(lengths: &[usize]) -> (Vec<Vec<f32>>, Vec<Vec<f32>>) {
+    lengths
+        .iter()
+        .enumerate()
+        .map(|(i, &len)| {
+            let x = speech(len, FS, i as u64 + 1);
+            let y = degrade(&x, 0.05 * (i + 1) as f32, i as u64 + 100);
+            (x, y)
+        })
+        .unzip()
+}
+
+fn assert_batch_matches(lengths: &[usize], extended: bool) {
+    let (x, y) = clips(lengths);
+    let x = x.iter().map(Vec::as_slice).collect::<Vec<_>>();
+    let y = y.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+    let batch = fast_stoi::batch_stoi(&x, &y, FS, extended);
+    assert_eq!(batch.len(), lengths.len());
+
+    for (i, score) in batch.into_iter().enumerate() {
+        let single = fast_stoi::stoi(x[i], y[i], FS, extended);
+        match (score, single) {
+            (Ok(score), Ok(single)) => {
+                assert_eq!(
+                    score.to_bits(),
+                    single.to_bits(),
+                    "clip {i}: {score} != {single}"
+                )
+            }
+            (Err(_), Err(_)) => {}
+            (score, single) => panic!("clip {i}: {score:?} != {single:?}"),
+        }
+    }
+}
+
+#[test]
+fn ragged_segment_counts() {
+    // Two lane groups, with a partial second group
+    let lengths = [
+        FS,
+        3 * FS,
+        FS / 2,
+        2 * FS + 123,
+        5 * FS,
+        FS + 7,
+        4 * FS,
+        2 * FS,
+        3 * FS / 2,
+        FS,
+    ];
+
+    assert_batch_matches(&lengths, false);
+    assert_batch_matches(&lengths, true);
+}
+
+#[test]
+fn short_clip_inside_a_lane_group() {
+    // The fourth clip has fewer frames than a segment
+    let lengths = [2 * FS, FS, 3 * FS, FS / 10, 2 * FS, FS, 3 * FS / 2, 3 * FS];
+    let (x, y) = clips(&lengths);
+    let x = x.iter().map(Vec::as_slice).collect::<Vec<_>>();
+    let y = y.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+    let batch = fast_stoi::batch_stoi(&x, &y, FS, false);
+    assert!(batch[3].is_err());
+    assert!(batch.iter().enumerate().all(|(i, s)| i == 3 || s.is_ok()));
+
+    assert_batch_matches(&lengths, false);
+}
+
+#[test]
+fn empty_batch() {
+    assert!(fast_stoi::batch_stoi(&[], &[], FS, false).is_empty());
+}
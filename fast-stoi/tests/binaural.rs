@@ -0,0 +1,55 @@
+//! DBSTOI against an independent evaluation of its definition
+//!
+//! No output of the authors' `dbstoi.m` is checked in yet: `reference/dbstoi.py --wav DIR`
+//! writes the signals below as WAV files so that its values can be added to these tests.
+
+mod common;
+
+use common::noise;
+
+const FS: usize = 10_000;
+
+/// Clean binaural signals with a 3-sample interaural delay and a 6 dB level difference,
+/// and processed signals with noise of the given gain, independent or identical in both ears.
+/// Must match `signals` in `reference/dbstoi.py`.
+fn signals(len: usize, gain: f32, diotic: bool) -> [Vec<f32>; 4] {
+    let (n1, n2, n3) = (noise(len, 1), noise(len, 2), noise(len, 3));
+    let xl = n1
+        .iter()
+        .enumerate()
+        .map(|(i, n)| if (i / 500) % 2 == 0 { 0.25 * n } else { *n })
+        .collect::<Vec<_>>();
+    let xr = (0..len)
+        .map(|i| if i < 3 { 0.0 } else { 0.5 * xl[i - 3] })
+        .collect::<Vec<_>>();
+    let right_noise = if diotic { &n2 } else { &n3 };
+    let yl = xl.iter().zip(&n2).map(|(x, n)| x + gain * n).collect();
+    let yr = xr
+        .iter()
+        .zip(right_noise)
+        .map(|(x, n)| x + gain * n)
+        .collect();
+
+    [xl, xr, yl, yr]
+}
+
+/// Double precision evaluation of the DBSTOI definition by `reference/dbstoi.py`,
+/// a port written for these tests (not the authors' code)
+const PYTHON_PORT_SCORES: [(f32, bool, f32); 2] =
+    [(0.3, false, 0.958_484_8), (0.5, true, 0.887_029_7)];
+
+#[test]
+fn matches_python_port() {
+    for (gain, diotic, expected) in PYTHON_PORT_SCORES {
+        let [xl, xr, yl, yr] = signals(6000, gain, diotic);
+        let score = fast_stoi::dbstoi(&xl, &xr, &yl, &yr, FS).unwrap();
+        assert!((score - expected).abs() < 1e-4, "{score} != {expected}");
+    }
+}
+
+#[test]
+fn identical_signals() {
+    let [xl, xr, ..] = signals(6000, 0.0, false);
+    let score = fast_stoi::dbstoi(&xl, &xr, &xl, &xr, FS).unwrap();
+    assert!(score > 0.99, "{score}");
+}
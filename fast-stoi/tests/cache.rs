@@ -0,0 +1,161 @@
+//! Reference caches (`cache` feature)
+
+#![cfg(feature = "cache")]
+
+mod common;
+
+use std::path::PathBuf;
+
+use common::{degrade, speech};
+use fast_stoi::{CACHE_VERSION, CacheError, Compatibility, Config, Engine, pipeline};
+
+const FS: usize = 16_000;
+
+/// Path of a cache file in a temporary directory
+fn cache_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fast-stoi-cache-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+/// Clean signals of a small corpus, by key
+fn corpus() -> Vec<(String, Vec<f32>)> {
+    ["b.wav", "a.wav", "c.wav"]
+        .into_iter()
+        .enumerate()
+        .map(|(i, key)| (key.to_string(), speech((2 + i) * FS, FS, i as u64 + 1)))
+        .collect()
+}
+
+/// Write the references of the corpus to a cache file
+fn write_cache(name: &str) -> PathBuf {
+    let references = corpus()
+        .into_iter()
+        .map(|(key, x)| (key, pipeline::reference(&x, FS).unwrap()))
+        .collect::<Vec<_>>();
+    let path = cache_path(name);
+    fast_stoi::write_reference_cache(&path, &references).unwrap();
+    path
+}
+
+/// Overwrite bytes of a file
+fn patch(path: &PathBuf, offset: usize, bytes: &[u8]) {
+    let mut data = std::fs::read(path).unwrap();
+    data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    std::fs::write(path, data).unwrap();
+}
+
+#[test]
+fn round_trip() {
+    let cache = fast_stoi::open_reference_cache(write_cache("round_trip.bin")).unwrap();
+    assert_eq!(cache.len(), 3);
+    assert_eq!(
+        cache.keys().collect::<Vec<_>>(),
+        ["a.wav", "b.wav", "c.wav"]
+    );
+    assert_eq!(cache.compatibility(), Compatibility::Classic);
+    assert_eq!(cache.config(), &Config::classic());
+    assert!(cache.get("missing.wav").is_none());
+
+    for (i, (key, x)) in corpus().into_iter().enumerate() {
+        let reference = cache.get(&key).unwrap().unwrap();
+        assert_eq!((reference.fs_sig(), reference.len()), (FS, x.len()));
+        assert_eq!(
+            reference.frames(),
+            pipeline::reference(&x, FS).unwrap().frames()
+        );
+
+        // Cached references score processed signals as `stoi` does
+        let y = degrade(&x, 0.3, i as u64 + 10);
+        for extended in [false, true] {
+            let score = pipeline::stoi_from_reference(&reference, &y, extended);
+            let expected = fast_stoi::stoi(&x, &y, FS, extended).unwrap();
+            assert_eq!(score.to_bits(), expected.to_bits(), "{key}");
+        }
+    }
+}
+
+#[test]
+fn invalid_files_are_rejected() {
+    let path = write_cache("magic.bin");
+    patch(&path, 0, b"RIFF");
+    assert!(matches!(
+        fast_stoi::open_reference_cache(&path),
+        Err(CacheError::Format(_))
+    ));
+
+    let path = cache_path("empty.bin");
+    std::fs::write(&path, b"FSTOIREF").unwrap();
+    assert!(matches!(
+        fast_stoi::open_reference_cache(&path),
+        Err(CacheError::Format(_))
+    ));
+
+    let path = write_cache("version.bin");
+    patch(&path, 8, &(CACHE_VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        fast_stoi::open_reference_cache(&path),
+        Err(CacheError::Version { found, expected })
+            if found == CACHE_VERSION + 1 && expected == CACHE_VERSION
+    ));
+
+    // Header longer than the file
+    let path = write_cache("header.bin");
+    patch(&path, 12, &u64::MAX.to_le_bytes());
+    assert!(matches!(
+        fast_stoi::open_reference_cache(&path),
+        Err(CacheError::Format(_))
+    ));
+
+    assert!(matches!(
+        fast_stoi::open_reference_cache(cache_path("missing.bin")),
+        Err(CacheError::Io(_))
+    ));
+}
+
+#[test]
+fn other_configurations_are_rejected() {
+    let path = write_cache("config.bin");
+
+    let wideband = Engine::new(Config::wideband(FS));
+    assert!(matches!(
+        wideband.open_reference_cache(&path),
+        Err(CacheError::Compatibility {
+            cache: Compatibility::Classic,
+            engine: Compatibility::NonStandard,
+        })
+    ));
+
+    // Non-standard caches must also have the same configuration
+    let x = speech(2 * FS, FS, 1);
+    let path = cache_path("wideband.bin");
+    wideband
+        .write_reference_cache(&path, &[("a.wav", wideband.reference(&x, FS).unwrap())])
+        .unwrap();
+    assert!(wideband.open_reference_cache(&path).is_ok());
+
+    let other = Engine::new(Config::wideband(2 * FS));
+    assert!(matches!(
+        other.open_reference_cache(&path),
+        Err(CacheError::Config { .. })
+    ));
+    assert!(matches!(
+        fast_stoi::open_reference_cache(&path),
+        Err(CacheError::Compatibility { .. })
+    ));
+}
+
+#[test]
+fn corrupted_entries_are_rejected() {
+    let path = write_cache("corrupted.bin");
+    // Cut the last entry ("c.wav")
+    let data = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &data[..data.len() - 100]).unwrap();
+
+    let cache = fast_stoi::open_reference_cache(&path).unwrap();
+    assert!(cache.get("a.wav").unwrap().is_ok());
+    assert!(matches!(
+        cache.get("c.wav").unwrap(),
+        Err(CacheError::Format(message)) if message.contains("\"c.wav\" is corrupted")
+    ));
+}
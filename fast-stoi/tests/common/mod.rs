@@ -0,0 +1,38 @@
+//! Deterministic test signals shared by the integration tests
+
+#![allow(dead_code)]
+
+/// Deterministic white noise in [-1, 1]
+pub fn noise(len: usize, seed: u64) -> Vec<f32> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (state >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+        })
+        .collect()
+}
+
+/// Speech-like signal: noise modulated at a syllabic rate, with pauses
+pub fn speech(len: usize, fs: usize, seed: u64) -> Vec<f32> {
+    noise(len, seed)
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let t = i as f32 / fs as f32;
+            let syllables = (2.0 * std::f32::consts::PI * 4.0 * t).sin().max(0.0);
+            let pause = if (t % 1.5) < 1.2 { 1.0 } else { 0.01 };
+            x * syllables * pause
+        })
+        .collect()
+}
+
+/// Mix a signal with noise scaled by `gain`
+pub fn degrade(x: &[f32], gain: f32, seed: u64) -> Vec<f32> {
+    x.iter()
+        .zip(noise(x.len(), seed))
+        .map(|(x, n)| x + gain * n)
+        .collect()
+}
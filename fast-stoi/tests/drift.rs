@@ -0,0 +1,79 @@
+//! Clock drift estimation and compensation
+
+use std::f64::consts::PI;
+
+use fast_stoi::{Config, CropPolicy, Engine};
+
+const FS: usize = 16_000;
+
+/// Speech-like signal defined in continuous time: incommensurate partials
+/// with a slowly varying envelope, at time `t` in seconds
+fn source(t: f64) -> f32 {
+    let envelope = (2.0 * PI * 3.7 * t).sin().max(0.0) * (0.6 + 0.4 * (2.0 * PI * 0.31 * t).sin());
+    let partials = (0..40)
+        .map(|k| {
+            let freq = 110.0 * 1.093_f64.powi(k);
+            (2.0 * PI * freq * t + k as f64 * 1.7).sin()
+        })
+        .sum::<f64>();
+
+    (envelope * partials / 10.0) as f32
+}
+
+/// Clean signal, and the same signal re-recorded by a device whose clock is skewed by `ppm`,
+/// with a lag of `offset` seconds at the start and `extra` more seconds of recording
+fn recordings(seconds: usize, ppm: f64, offset: f64, extra: f64) -> (Vec<f32>, Vec<f32>) {
+    let x = (0..seconds * FS)
+        .map(|n| source(n as f64 / FS as f64))
+        .collect();
+    let y_len = ((seconds as f64 + extra) * FS as f64) as usize;
+    let y = (0..y_len)
+        .map(|n| {
+            let t = n as f64 / FS as f64;
+            source(t - offset - ppm * 1e-6 * t)
+        })
+        .collect();
+
+    (x, y)
+}
+
+#[test]
+fn recovers_known_drifts() {
+    let engine = Engine::new(Config::classic());
+    for ppm in [-200.0, -100.0, 100.0, 200.0] {
+        let (x, y) = recordings(20, ppm, 0.005, 0.0);
+        let drift = engine.estimate_drift(&x, &y, FS);
+
+        assert!(drift.blocks > 12, "{ppm} ppm: {drift:?}");
+        assert!(
+            (drift.ppm as f64 - ppm).abs() < 10.0,
+            "{ppm} ppm: {drift:?}"
+        );
+        assert!(
+            (drift.offset as f64 - 0.005).abs() < 2e-4,
+            "{ppm} ppm: {drift:?}"
+        );
+    }
+}
+
+#[test]
+fn compensation_improves_stoi() {
+    // The second device recorded half a second more
+    let (x, y) = recordings(20, 200.0, 0.002, 0.5);
+
+    for crop in [CropPolicy::Shortest, CropPolicy::Reference] {
+        let compensated = fast_stoi::stoi_with_drift_compensation(&x, &y, FS, false, crop).unwrap();
+        let uncompensated = fast_stoi::stoi(&x, &y[..x.len()], FS, false).unwrap();
+
+        assert!(
+            (compensated.drift.ppm - 200.0).abs() < 10.0,
+            "{compensated:?}"
+        );
+        assert!(
+            compensated.stoi > uncompensated,
+            "{} <= {uncompensated}",
+            compensated.stoi
+        );
+        assert!(compensated.stoi > 0.9, "{compensated:?}");
+    }
+}
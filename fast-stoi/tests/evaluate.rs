@@ -0,0 +1,49 @@
+//! Single-pass evaluation must match the metrics computed on their own
+
+mod common;
+
+use common::{degrade, speech};
+use fast_stoi::Metric;
+
+const FS: usize = 16_000;
+
+#[test]
+fn matches_separate_metrics() {
+    let x = speech(3 * FS, FS, 1);
+    let y = degrade(&x, 0.3, 2);
+
+    let scores = fast_stoi::evaluate(
+        &x,
+        &y,
+        FS,
+        &[Metric::Stoi, Metric::Estoi, Metric::SiSdr, Metric::FwSegSnr],
+    )
+    .unwrap();
+
+    let stoi = fast_stoi::stoi(&x, &y, FS, false).unwrap();
+    let estoi = fast_stoi::stoi(&x, &y, FS, true).unwrap();
+    assert_eq!(scores.stoi.unwrap().to_bits(), stoi.to_bits());
+    assert_eq!(scores.estoi.unwrap().to_bits(), estoi.to_bits());
+
+    // Each metric on its own
+    for (metric, expected) in [(Metric::Stoi, stoi), (Metric::Estoi, estoi)] {
+        let scores = fast_stoi::evaluate(&x, &y, FS, &[metric]).unwrap();
+        let score = scores.stoi.or(scores.estoi).unwrap();
+        assert_eq!(score.to_bits(), expected.to_bits(), "{metric:?}");
+        assert!(scores.si_sdr.is_none() && scores.fw_seg_snr.is_none());
+    }
+}
+
+#[test]
+fn fw_seg_snr_keeps_silent_frames() {
+    // fwSegSNR keeps every frame: digital silence must not make the score NaN
+    let x = speech(3 * FS, FS, 1);
+    let silence = vec![0.0; x.len()];
+
+    let scores = fast_stoi::evaluate(&silence, &silence, FS, &[Metric::FwSegSnr]).unwrap();
+    assert!(scores.fw_seg_snr.unwrap().is_finite());
+
+    let clean = fast_stoi::evaluate(&x, &x, FS, &[Metric::FwSegSnr]).unwrap();
+    let noisy = fast_stoi::evaluate(&x, &degrade(&x, 0.3, 2), FS, &[Metric::FwSegSnr]).unwrap();
+    assert!(noisy.fw_seg_snr < clean.fw_seg_snr);
+}
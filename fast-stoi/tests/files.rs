@@ -0,0 +1,241 @@
+//! Scoring audio files (`io` feature)
+
+#![cfg(feature = "io")]
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use common::{degrade, speech};
+use fast_stoi::{Audio, ChannelPolicy, FileError, FileOptions};
+
+/// Path of a file in a temporary directory
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fast-stoi-files-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+/// Write interleaved samples to a 32-bit float WAV file in a temporary directory
+fn write_wav(name: &str, samples: &[f32], channels: u16, fs: u32) -> PathBuf {
+    let path = temp_path(name);
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate: fs,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    samples
+        .iter()
+        .for_each(|&s| writer.write_sample(s).unwrap());
+    writer.finalize().unwrap();
+
+    path
+}
+
+/// Write interleaved samples in [-1, 1) to a PCM WAV file in a temporary directory
+fn write_pcm_wav(name: &str, samples: &[f32], channels: u16, fs: u32, bits: u16) -> PathBuf {
+    let path = temp_path(name);
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate: fs,
+        bits_per_sample: bits,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let scale = (1u64 << (bits - 1)) as f64;
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    samples.iter().for_each(|&s| {
+        let sample = (s as f64 * scale).round().clamp(-scale, scale - 1.0) as i32;
+        writer.write_sample(sample).unwrap()
+    });
+    writer.finalize().unwrap();
+
+    path
+}
+
+/// Upper 16 bits of the deterministic noise of the tests
+fn noise_bits(len: usize, seed: u64) -> impl Iterator<Item = i32> {
+    let mut state = seed;
+    (0..len).map(move |_| {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (state >> 48) as i32
+    })
+}
+
+/// 16-bit samples of `speech.flac`, see `speech_samples` in `tests/reference/fixtures.py`
+fn fixture_speech(len: usize) -> Vec<i32> {
+    const FS: usize = 8000;
+    let period = FS / 4;
+    noise_bits(len, 1)
+        .enumerate()
+        .map(|(n, noise)| {
+            let mut envelope = 256 - ((n % period) * 512 / period).abs_diff(256) as i32;
+            if n % (3 * FS / 2) >= 6 * FS / 5 {
+                envelope /= 64;
+            }
+            noise * envelope / 256 - 128 * envelope
+        })
+        .collect()
+}
+
+/// 16-bit samples of `noisy.ogg`, see `noisy_samples` in `tests/reference/fixtures.py`
+fn fixture_noisy(len: usize) -> Vec<i32> {
+    fixture_speech(len)
+        .into_iter()
+        .zip(noise_bits(len, 2))
+        .map(|(s, noise)| (s + noise / 8 - 4096).clamp(-32768, 32767))
+        .collect()
+}
+
+/// Interleave channels
+fn interleaved(channels: &[Vec<f32>]) -> Vec<f32> {
+    (0..channels[0].len())
+        .flat_map(|i| channels.iter().map(move |channel| channel[i]))
+        .collect()
+}
+
+fn assert_mismatch(ref_path: &Path, deg_path: &Path, policy: ChannelPolicy, expected: &str) {
+    let options = FileOptions {
+        policy,
+        ..Default::default()
+    };
+    match fast_stoi::stoi_files(ref_path, deg_path, &options) {
+        Err(err @ FileError::Mismatch { .. }) => {
+            assert!(err.to_string().contains(expected), "{err}")
+        }
+        result => panic!("expected a mismatch error, got {result:?}"),
+    }
+}
+
+#[test]
+fn invalid_pairs_are_rejected() {
+    const FS: usize = 16_000;
+    let x = speech(3 * FS, FS, 1);
+    let y = degrade(&x, 0.1, 2);
+
+    let stereo = write_wav(
+        "stereo.wav",
+        &interleaved(&[x.clone(), x.clone()]),
+        2,
+        FS as u32,
+    );
+    let triple = write_wav(
+        "triple.wav",
+        &interleaved(&[y.clone(), y.clone(), y.clone()]),
+        3,
+        FS as u32,
+    );
+    let mono = write_wav("mono.wav", &y, 1, FS as u32);
+    let short = write_wav("short.wav", &y[..y.len() - FS / 10], 1, FS as u32);
+
+    assert_mismatch(&stereo, &triple, ChannelPolicy::Mean, "as many channels");
+    assert_mismatch(
+        &mono,
+        &mono,
+        ChannelPolicy::Channel(1),
+        "channel 1 does not exist",
+    );
+    assert_mismatch(
+        &stereo,
+        &triple,
+        ChannelPolicy::Reference(2),
+        "reference channel 2 does not exist",
+    );
+    assert_mismatch(&mono, &short, ChannelPolicy::Mean, "same duration");
+
+    // Valid pairs: mono reference of a stereo file, a stereo reference channel of a 3-channel
+    // file, and files at different rates
+    let options = FileOptions {
+        policy: ChannelPolicy::Channel(1),
+        ..Default::default()
+    };
+    assert!(
+        fast_stoi::stoi_files(&mono, &stereo, &options)
+            .unwrap()
+            .score
+            .is_ok()
+    );
+    let options = FileOptions {
+        policy: ChannelPolicy::Reference(1),
+        ..Default::default()
+    };
+    let result = fast_stoi::stoi_files(&stereo, &triple, &options).unwrap();
+    assert_eq!(result.channel_scores.len(), 3);
+    assert!(result.score.is_ok());
+
+    let x_48k = write_wav("clean_48k.wav", &speech(3 * 48_000, 48_000, 1), 1, 48_000);
+    let result = fast_stoi::stoi_files(&x_48k, &mono, &FileOptions::default()).unwrap();
+    assert!(result.score.is_ok());
+}
+
+#[test]
+fn wav_round_trips() {
+    const FS: usize = 16_000;
+    let left = speech(FS / 2, FS, 1);
+    let right = degrade(&left, 0.2, 2);
+    let samples = interleaved(&[left, right])
+        .into_iter()
+        .map(|s| 0.5 * s)
+        .collect::<Vec<_>>();
+
+    for bits in [8, 16, 24, 32] {
+        let path = write_pcm_wav(&format!("pcm_{bits}.wav"), &samples, 2, FS as u32, bits);
+        let audio = Audio::read(&path).unwrap();
+        assert_eq!((audio.channels, audio.fs), (2, FS));
+        assert_eq!(audio.samples.len(), samples.len());
+
+        // Half a quantization step, and the f32 precision of 32-bit samples
+        let tolerance = 0.5 / (1u64 << (bits - 1)) as f32 + 1e-7;
+        for (decoded, expected) in audio.samples.iter().zip(&samples) {
+            assert!(
+                (decoded - expected).abs() <= tolerance,
+                "{bits} bits: {decoded} != {expected}"
+            );
+        }
+    }
+
+    let path = write_wav("float.wav", &samples, 2, FS as u32);
+    let audio = Audio::read(&path).unwrap();
+    assert_eq!((audio.channels, audio.fs), (2, FS));
+    assert_eq!(audio.samples, samples);
+}
+
+#[test]
+fn flac_and_ogg_fixtures() {
+    let reference = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/reference");
+    let (speech_path, noisy_path) = (reference.join("speech.flac"), reference.join("noisy.ogg"));
+
+    let speech = Audio::read(&speech_path).unwrap();
+    let noisy = Audio::read(&noisy_path).unwrap();
+    for (audio, expected) in [
+        (&speech, fixture_speech(8000)),
+        (&noisy, fixture_noisy(8000)),
+    ] {
+        assert_eq!((audio.channels, audio.fs), (1, 8000));
+        let expected = expected
+            .into_iter()
+            .map(|s| s as f32 / 32768.0)
+            .collect::<Vec<_>>();
+        assert_eq!(audio.samples, expected);
+    }
+
+    // Files are scored as their decoded samples
+    for extended in [false, true] {
+        let options = FileOptions {
+            extended,
+            ..Default::default()
+        };
+        let result = fast_stoi::stoi_files(&speech_path, &noisy_path, &options).unwrap();
+        let expected = fast_stoi::stoi(&speech.samples, &noisy.samples, 8000, extended).unwrap();
+        assert_eq!(result.score.unwrap().to_bits(), expected.to_bits());
+    }
+}
+
+#[test]
+fn missing_files_are_decode_errors() {
+    let result = fast_stoi::stoi_files("missing.wav", "missing.wav", &FileOptions::default());
+    assert!(matches!(result, Err(FileError::Decode(_))));
+}
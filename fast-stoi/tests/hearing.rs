@@ -0,0 +1,59 @@
+//! STOI of clean speech heard through a simulated impaired ear
+
+mod common;
+
+use common::speech;
+use fast_stoi::{Config, Engine, HearingLoss};
+
+const FS: usize = 16_000;
+
+/// Flat audiogram at the audiometric frequencies
+fn flat(loss: f32) -> HearingLoss {
+    HearingLoss::new(&[250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0].map(|freq| (freq, loss)))
+}
+
+fn score(x: &[f32], hearing_loss: HearingLoss) -> f32 {
+    Engine::new(Config::classic().with_hearing_loss(hearing_loss))
+        .stoi(x, x, FS, false)
+        .unwrap()
+}
+
+#[test]
+fn stoi_falls_as_the_loss_grows() {
+    let x = speech(3 * FS, FS, 1);
+
+    let normal = score(&x, flat(0.0));
+    assert!(normal > 0.99, "{normal}");
+
+    let mut previous = normal;
+    for loss in [40.0, 60.0, 80.0] {
+        let impaired = score(&x, flat(loss));
+        assert!(
+            impaired < previous,
+            "{loss} dB HL: {impaired} >= {previous}"
+        );
+        previous = impaired;
+    }
+    assert!(previous < 0.9, "{previous}");
+}
+
+#[test]
+fn recruitment_and_smearing_change_the_output() {
+    let x = speech(3 * FS, FS, 1);
+    let plain = score(&x, flat(60.0));
+
+    for hearing_loss in [
+        flat(60.0).with_recruitment(true),
+        flat(60.0).with_smearing(3.0),
+    ] {
+        let modified = score(&x, hearing_loss.clone());
+        assert!(
+            (modified - plain).abs() > 1e-3,
+            "{hearing_loss:?}: {modified} == {plain}"
+        );
+    }
+
+    // Smearing alone lowers the score of normal hearing
+    let smeared = score(&x, flat(0.0).with_smearing(3.0));
+    assert!(smeared < score(&x, flat(0.0)), "{smeared}");
+}
@@ -0,0 +1,72 @@
+//! STOI improvement reports must match the scores computed on their own
+
+mod common;
+
+use common::{degrade, speech};
+
+const FS: usize = 16_000;
+
+/// Clean, noisy and enhanced (less noisy) signals
+fn signals() -> [Vec<f32>; 3] {
+    let clean = speech(3 * FS, FS, 1);
+    let noisy = degrade(&clean, 0.5, 2);
+    let enhanced = degrade(&clean, 0.1, 3);
+
+    [clean, noisy, enhanced]
+}
+
+#[test]
+fn matches_stoi() {
+    let [clean, noisy, enhanced] = signals();
+
+    for extended in [false, true] {
+        for detailed in [false, true] {
+            let report =
+                fast_stoi::stoi_improvement(&clean, &noisy, &enhanced, FS, extended, detailed)
+                    .unwrap();
+
+            let noisy_stoi = fast_stoi::stoi(&clean, &noisy, FS, extended).unwrap();
+            let enhanced_stoi = fast_stoi::stoi(&clean, &enhanced, FS, extended).unwrap();
+            assert_eq!(report.noisy.to_bits(), noisy_stoi.to_bits(), "{report:?}");
+            assert_eq!(
+                report.enhanced.to_bits(),
+                enhanced_stoi.to_bits(),
+                "{report:?}"
+            );
+            assert_eq!(report.delta, report.enhanced - report.noisy);
+            assert!(report.delta > 0.0, "{report:?}");
+            assert_eq!(report.noisy_details.is_some(), detailed);
+            assert_eq!(report.enhanced_details.is_some(), detailed);
+        }
+    }
+}
+
+#[test]
+fn details_average_to_the_score() {
+    let [clean, noisy, enhanced] = signals();
+
+    for extended in [false, true] {
+        let report =
+            fast_stoi::stoi_improvement(&clean, &noisy, &enhanced, FS, extended, true).unwrap();
+
+        for (score, details) in [
+            (report.noisy, report.noisy_details.unwrap()),
+            (report.enhanced, report.enhanced_details.unwrap()),
+        ] {
+            let (num_bands, n_segments) = details.as_mat().shape();
+            assert_eq!(num_bands, 15);
+
+            let per_band = details.per_band();
+            let per_segment = details.per_segment();
+            assert_eq!((per_band.len(), per_segment.len()), (num_bands, n_segments));
+
+            let band_mean = per_band.iter().sum::<f32>() / num_bands as f32;
+            let segment_mean = per_segment.iter().sum::<f32>() / n_segments as f32;
+            assert!((band_mean - score).abs() < 1e-5, "{band_mean} != {score}");
+            assert!(
+                (segment_mean - score).abs() < 1e-5,
+                "{segment_mean} != {score}"
+            );
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! STOI of labelled intervals inside long recordings
+
+mod common;
+
+use common::{degrade, noise, speech};
+use fast_stoi::{Interval, IntervalError};
+
+const FS: usize = 10_000;
+
+#[test]
+fn intervals_match_stoi_of_their_samples() {
+    let x = speech(6 * FS, FS, 1);
+    let y = degrade(&x, 0.3, 2);
+    let intervals = [Interval::new(0.5, 2.5, "a"), Interval::new(3.0, 5.5, "b")];
+
+    let scores = fast_stoi::stoi_intervals(&x, &y, FS, &intervals);
+    for score in &scores.intervals {
+        let range =
+            (score.interval.start * FS as f64) as usize..(score.interval.end * FS as f64) as usize;
+        let stoi = fast_stoi::stoi(&x[range.clone()], &y[range.clone()], FS, false).unwrap();
+        let estoi = fast_stoi::stoi(&x[range.clone()], &y[range], FS, true).unwrap();
+        assert_eq!(score.stoi.as_ref().unwrap().to_bits(), stoi.to_bits());
+        assert_eq!(score.estoi.as_ref().unwrap().to_bits(), estoi.to_bits());
+    }
+
+    let expected = (2.0 * scores.intervals[0].stoi.as_ref().unwrap()
+        + 2.5 * scores.intervals[1].stoi.as_ref().unwrap())
+        / 4.5;
+    assert!((scores.stoi.unwrap() - expected).abs() < 1e-6);
+    assert_eq!(scores.scored_duration, 4.5);
+}
+
+#[test]
+fn short_and_silent_intervals_are_reported() {
+    let mut x = noise(4 * FS, 1);
+    // Silent second half, except for a 100ms burst
+    x[2 * FS..]
+        .iter_mut()
+        .enumerate()
+        .filter(|(i, _)| !(FS / 2..FS / 2 + FS / 10).contains(i))
+        .for_each(|(_, v)| *v *= 1e-6);
+    let y = degrade(&x, 0.3, 2);
+    let intervals = [
+        Interval::new(0.0, 1.0, "scored"),
+        Interval::new(1.0, 1.2, "short"),
+        Interval::new(2.5, 3.5, "silent"),
+        Interval::new(5.0, 6.0, "outside"),
+    ];
+
+    let scores = fast_stoi::stoi_intervals(&x, &y, FS, &intervals);
+    let [scored, short, silent, outside] = &scores.intervals[..] else {
+        panic!("one score per interval");
+    };
+    assert!(scored.stoi.is_ok() && scored.estoi.is_ok());
+    assert!(matches!(
+        short.stoi,
+        Err(IntervalError::TooShort { frames: 13 })
+    ));
+    assert!(matches!(short.estoi, Err(IntervalError::TooShort { .. })));
+    assert!(matches!(
+        silent.stoi,
+        Err(IntervalError::NotEnoughFrames(_))
+    ));
+    assert!(matches!(
+        outside.stoi,
+        Err(IntervalError::TooShort { frames: 0 })
+    ));
+
+    assert_eq!(scores.stoi, scored.stoi.as_ref().ok().copied());
+    assert_eq!(scores.scored_duration, 1.0);
+}
@@ -0,0 +1,58 @@
+//! Signals at different sampling frequencies
+
+mod common;
+
+use common::{degrade, speech};
+use fast_stoi::{CropPolicy, MixedRateError, Resampler};
+
+/// Clean signal at 48kHz, and the same processed signal at 48kHz and resampled to 16kHz
+fn signals() -> (Vec<f32>, Vec<f32>, Vec<f32>) {
+    let x = speech(3 * 48_000, 48_000, 1);
+    let y = degrade(&x, 0.1, 2);
+    let y_16k = Resampler::new(48_000, 16_000).process(&y);
+
+    (x, y, y_16k)
+}
+
+#[test]
+fn matches_same_rate_stoi() {
+    let (x, y, y_16k) = signals();
+
+    for extended in [false, true] {
+        let same_rate = fast_stoi::stoi(&x, &y, 48_000, extended).unwrap();
+        assert!(same_rate > 0.8, "{same_rate}");
+
+        for crop in [CropPolicy::Shortest, CropPolicy::Reference] {
+            let score =
+                fast_stoi::stoi_mixed_rate(&x, 48_000, &y_16k, 16_000, extended, crop).unwrap();
+            assert!((score - same_rate).abs() < 0.01, "{score} != {same_rate}");
+
+            // Signals at the same rate are scored as `stoi` does
+            let score = fast_stoi::stoi_mixed_rate(&x, 48_000, &y, 48_000, extended, crop).unwrap();
+            assert_eq!(score.to_bits(), same_rate.to_bits());
+        }
+    }
+}
+
+#[test]
+fn durations_must_match() {
+    let (x, _, y_16k) = signals();
+
+    for crop in [CropPolicy::Shortest, CropPolicy::Reference] {
+        let error = fast_stoi::stoi_mixed_rate(
+            &x,
+            48_000,
+            &y_16k[..y_16k.len() - 800],
+            16_000,
+            false,
+            crop,
+        );
+        assert!(matches!(
+            error,
+            Err(MixedRateError::DurationMismatch {
+                clean: 3.0,
+                processed: 2.95
+            })
+        ));
+    }
+}
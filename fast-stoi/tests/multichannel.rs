@@ -0,0 +1,140 @@
+//! STOI of multichannel signals with each channel policy
+
+mod common;
+
+use common::{degrade, speech};
+use fast_stoi::{ChannelPolicy, Multichannel};
+
+const FS: usize = 10_000;
+
+/// Interleave channels of the same length
+fn interleaved(channels: &[Vec<f32>]) -> Vec<f32> {
+    (0..channels[0].len())
+        .flat_map(|i| channels.iter().map(move |channel| channel[i]))
+        .collect()
+}
+
+/// Clean and processed 3-channel signals, the processed channels getting noisier
+fn signals() -> (Vec<Vec<f32>>, Vec<Vec<f32>>) {
+    let x = (0..3)
+        .map(|seed| speech(2 * FS, FS, seed + 1))
+        .collect::<Vec<_>>();
+    let y = x
+        .iter()
+        .zip([0.4, 0.05, 1.0])
+        .enumerate()
+        .map(|(seed, (x, gain))| degrade(x, gain, seed as u64 + 10))
+        .collect();
+    (x, y)
+}
+
+fn scores(result: &fast_stoi::MultichannelStoi) -> Vec<f32> {
+    result
+        .channel_scores
+        .iter()
+        .map(|score| *score.as_ref().unwrap())
+        .collect()
+}
+
+#[test]
+fn layouts_give_identical_results() {
+    let (x, y) = signals();
+    let (x_planar, y_planar) = (x.concat(), y.concat());
+    let (x_interleaved, y_interleaved) = (interleaved(&x), interleaved(&y));
+
+    for policy in [
+        ChannelPolicy::PerChannel,
+        ChannelPolicy::Channel(2),
+        ChannelPolicy::Mean,
+        ChannelPolicy::Best,
+        ChannelPolicy::Reference(1),
+    ] {
+        let planar = fast_stoi::stoi_multichannel(
+            &Multichannel::planar(&x_planar, 3),
+            &Multichannel::planar(&y_planar, 3),
+            FS,
+            false,
+            policy,
+        );
+        let interleaved = fast_stoi::stoi_multichannel(
+            &Multichannel::interleaved(&x_interleaved, 3),
+            &Multichannel::interleaved(&y_interleaved, 3),
+            FS,
+            false,
+            policy,
+        );
+
+        assert_eq!(scores(&planar), scores(&interleaved), "{policy:?}");
+        assert_eq!(planar.score.unwrap(), interleaved.score.unwrap());
+        assert_eq!(planar.channel, interleaved.channel);
+    }
+}
+
+#[test]
+fn policies_select_their_channels() {
+    let (x, y) = signals();
+    let (x_planar, y_planar) = (x.concat(), y.concat());
+    let (x, y) = (
+        Multichannel::planar(&x_planar, 3),
+        Multichannel::planar(&y_planar, 3),
+    );
+    let stoi = |x: &[f32], y: &[f32]| fast_stoi::stoi(x, y, FS, false).unwrap();
+
+    let per_channel = fast_stoi::stoi_multichannel(&x, &y, FS, false, ChannelPolicy::PerChannel);
+    let expected = (0..3)
+        .map(|i| stoi(&x.channel(i), &y.channel(i)))
+        .collect::<Vec<_>>();
+    assert_eq!(scores(&per_channel), expected);
+    assert_eq!(per_channel.channel, None);
+    assert!((per_channel.score.unwrap() - expected.iter().sum::<f32>() / 3.0).abs() < 1e-6);
+
+    // The least noisy channel is the best one
+    let best = fast_stoi::stoi_multichannel(&x, &y, FS, false, ChannelPolicy::Best);
+    assert_eq!(best.channel, Some(1));
+    assert_eq!(best.score.unwrap(), expected[1]);
+
+    for (channel, &expected) in expected.iter().enumerate() {
+        let single =
+            fast_stoi::stoi_multichannel(&x, &y, FS, false, ChannelPolicy::Channel(channel));
+        assert_eq!(single.channel, Some(channel));
+        assert_eq!(single.score.unwrap(), expected);
+    }
+
+    let mean = fast_stoi::stoi_multichannel(&x, &y, FS, false, ChannelPolicy::Mean);
+    assert_eq!(mean.score.unwrap(), stoi(&x.mean(), &y.mean()));
+}
+
+#[test]
+fn reference_channel_is_scored_against_every_channel() {
+    let (x, y) = signals();
+    // Two clean channels for three processed channels
+    let x_planar = x[..2].concat();
+    let y_planar = y.concat();
+    let (x, y) = (
+        Multichannel::planar(&x_planar, 2),
+        Multichannel::planar(&y_planar, 3),
+    );
+
+    let result = fast_stoi::stoi_multichannel(&x, &y, FS, false, ChannelPolicy::Reference(1));
+    let expected = (0..3)
+        .map(|i| fast_stoi::stoi(&x.channel(1), &y.channel(i), FS, false).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(scores(&result), expected);
+
+    // Only the processed channel of the same recording is intelligible
+    assert!(expected[1] > expected[0] + 0.3 && expected[1] > expected[2] + 0.3);
+}
+
+#[test]
+#[should_panic(expected = "Reference channel 2 does not exist")]
+fn missing_reference_channel() {
+    let x = speech(FS, FS, 1);
+    let y = x.repeat(3);
+    fast_stoi::stoi_multichannel(
+        &Multichannel::planar(&x, 1),
+        &Multichannel::planar(&y, 3),
+        FS,
+        false,
+        ChannelPolicy::Reference(2),
+    );
+}
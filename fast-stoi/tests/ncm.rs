@@ -0,0 +1,26 @@
+//! NCM of whole signals
+
+mod common;
+
+use common::{degrade, speech};
+
+const FS: usize = 16_000;
+
+#[test]
+fn identical_signals() {
+    let x = speech(3 * FS, FS, 1);
+    let score = fast_stoi::ncm(&x, &x, FS).unwrap();
+    assert!((score - 1.0).abs() < 1e-4, "{score}");
+}
+
+#[test]
+fn noise_lowers_the_score() {
+    let x = speech(3 * FS, FS, 1);
+
+    let mut previous = 1.0;
+    for gain in [0.3, 0.6, 1.2, 2.4] {
+        let score = fast_stoi::ncm(&x, &degrade(&x, gain, 2), FS).unwrap();
+        assert!(score < previous, "gain {gain}: {score} >= {previous}");
+        previous = score;
+    }
+}
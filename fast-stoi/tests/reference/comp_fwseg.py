@@ -0,0 +1,104 @@
+"""Reference fwSegSNR values for the unit tests of `src/snr.rs`.
+
+Double precision port of `comp_fwseg.m` from Loizou's "Speech Enhancement: Theory and
+Practice", applied to the signals of the tests. The framing is the one of `comp_fwseg.m`
+(30ms hanning windows, 75% overlap, FFT of twice the window length).
+
+Only uses the standard library: `python3 comp_fwseg.py`
+"""
+
+import cmath
+import math
+import struct
+
+CENT_FREQ = [
+    50.0, 120.0, 190.0, 260.0, 330.0, 400.0, 470.0, 540.0, 617.372, 703.378, 798.717, 904.128,
+    1020.38, 1148.3, 1288.72, 1442.54, 1610.7, 1794.16, 1993.93, 2211.08, 2446.71, 2701.97,
+    2978.04, 3276.17, 3597.63,
+]
+BANDWIDTH = [
+    70.0, 70.0, 70.0, 70.0, 70.0, 70.0, 70.0, 77.3724, 86.0056, 95.3398, 105.411, 116.256,
+    127.914, 140.423, 153.823, 168.154, 183.457, 199.776, 217.153, 235.631, 255.255, 276.072,
+    298.126, 321.465, 346.136,
+]
+
+
+def f32(x):
+    return struct.unpack("f", struct.pack("f", x))[0]
+
+
+def noise(length, seed):
+    """Same deterministic noise as the tests"""
+    state = seed
+    out = []
+    for _ in range(length):
+        state = (state * 6_364_136_223_846_793_005 + 1_442_695_040_888_963_407) % 2**64
+        out.append(f32((state >> 40) / 2**23 - 1.0))
+    return out
+
+
+def fft(values):
+    n = len(values)
+    if n == 1:
+        return values
+    even, odd = fft(values[0::2]), fft(values[1::2])
+    twiddles = [cmath.exp(-2j * math.pi * k / n) * odd[k] for k in range(n // 2)]
+    return [even[k] + twiddles[k] for k in range(n // 2)] + [even[k] - twiddles[k] for k in range(n // 2)]
+
+
+def comp_fwseg(clean, processed, sample_rate):
+    winlength = round(30 * sample_rate / 1000)
+    skiprate = winlength // 4
+    max_freq = sample_rate / 2
+    n_fft = 2 ** math.ceil(math.log2(2 * winlength))
+    n_fftby2 = n_fft // 2
+    gamma = 0.2
+
+    bw_min = BANDWIDTH[0]
+    min_factor = math.exp(-30.0 / (2.0 * 2.303))
+    crit_filter = []
+    for cf, bandwidth in zip(CENT_FREQ, BANDWIDTH):
+        f0 = math.floor(cf / max_freq * n_fftby2)
+        bw = bandwidth / max_freq * n_fftby2
+        norm_factor = math.log(bw_min) - math.log(bandwidth)
+        row = [math.exp(-11 * ((j - f0) / bw) ** 2 + norm_factor) for j in range(n_fftby2)]
+        crit_filter.append([v if v > min_factor else 0.0 for v in row])
+
+    num_frames = len(clean) // skiprate - winlength // skiprate
+    window = [0.5 * (1 - math.cos(2 * math.pi * n / (winlength + 1))) for n in range(1, winlength + 1)]
+
+    distortion = []
+    for frame in range(num_frames):
+        start = frame * skiprate
+
+        def spectrum(signal):
+            windowed = [signal[start + n] * window[n] for n in range(winlength)]
+            spec = [abs(v) for v in fft(windowed + [0.0] * (n_fft - winlength))[:n_fftby2]]
+            area = sum(spec)
+            return [v / area for v in spec]
+
+        clean_spec, processed_spec = spectrum(clean), spectrum(processed)
+        weighted, weights = 0.0, 0.0
+        for band in crit_filter:
+            clean_energy = sum(s * f for s, f in zip(clean_spec, band))
+            processed_energy = sum(s * f for s, f in zip(processed_spec, band))
+            error_energy = max((clean_energy - processed_energy) ** 2, 2.220446049250313e-16)
+            w = clean_energy**gamma
+            weighted += w * 10 * math.log10(clean_energy**2 / error_energy)
+            weights += w
+        distortion.append(min(max(weighted / weights, -10.0), 35.0))
+
+    return sum(distortion) / len(distortion)
+
+
+def signals(length, gain):
+    """Same signals as the unit tests of `src/snr.rs`"""
+    n1, n2 = noise(length, 1), noise(length, 2)
+    x = [f32((0.25 if (i // 700) % 2 == 0 else 1.0) * v) for i, v in enumerate(n1)]
+    y = [f32(a + f32(gain * b)) for a, b in zip(x, n2)]
+    return x, y
+
+
+if __name__ == "__main__":
+    for gain in [0.1, 0.5]:
+        print(gain, comp_fwseg(*signals(3000, gain), 10_000))
@@ -0,0 +1,252 @@
+"""Reference DBSTOI values for `tests/binaural.rs`.
+
+Direct double precision evaluation of the DBSTOI of Andersen et al. (2018),
+following the structure of their `dbstoi.m`:
+1. silent frames are removed from all signals when they are silent in both clean ears,
+   and the remaining frames are overlap-added back into signals
+2. short time DFT (256-sample Hann frames, 512-point FFT) and third octave bands
+3. equalization-cancellation (EC) stage: for every band and 30-frame segment,
+   the expected correlation between the clean and processed EC output envelopes
+   is maximized over a grid of interaural time and level compensations.
+   The expectations over the time and level jitter are integrated numerically
+4. better-ear stage: the segment correlation of the ear whose clean envelope varies
+   the most replaces the EC correlation when that envelope varies more than the EC output
+
+These values check the Rust implementation against an independent evaluation of the same
+definition, not against the authors' code. To compare both with `dbstoi.m`, write the test
+signals as 32-bit float stereo WAV files with `python3 dbstoi.py --wav DIR` and run in MATLAB:
+
+    [x, fs] = audioread('DIR/clean_0.3_independent.wav');
+    y = audioread('DIR/processed_0.3_independent.wav');
+    dbstoi(x(:, 1), x(:, 2), y(:, 1), y(:, 2), fs)
+
+Only uses the standard library: `python3 dbstoi.py [--wav DIR]`
+"""
+
+import cmath
+import math
+import struct
+
+FS = 10_000
+N_FRAME = 256
+HOP = N_FRAME // 2
+K_FFT = 512
+J = 15
+MIN_FREQ = 150.0
+N = 30
+DYN_RANGE = 40.0
+
+TAUS = [-0.001 + 0.002 * i / 99 for i in range(100)]
+GAMMAS = [-20.0 + 40.0 * i / 39 for i in range(40)]
+SIGMA_DELTA_0 = 65e-6
+SIGMA_EPSILON_0 = 1.5
+ALPHA_0 = 13.0
+P = 1.6
+TAU_0 = 1.6e-3
+
+
+def f32(x):
+    return struct.unpack("f", struct.pack("f", x))[0]
+
+
+def noise(length, seed):
+    """Same deterministic noise as `tests/common/mod.rs`"""
+    state = seed
+    out = []
+    for _ in range(length):
+        state = (state * 6_364_136_223_846_793_005 + 1_442_695_040_888_963_407) % 2**64
+        out.append(f32((state >> 40) / 2**23 - 1.0))
+    return out
+
+
+def signals(length, gain, diotic):
+    """Same binaural signals as `tests/binaural.rs`"""
+    n1, n2, n3 = noise(length, 1), noise(length, 2), noise(length, 3)
+    xl = [f32((0.25 if (i // 500) % 2 == 0 else 1.0) * n1[i]) for i in range(length)]
+    xr = [0.0] * 3 + [f32(0.5 * v) for v in xl[:-3]]
+    right_noise = n2 if diotic else n3
+    yl = [f32(x + f32(gain * n)) for x, n in zip(xl, n2)]
+    yr = [f32(x + f32(gain * n)) for x, n in zip(xr, right_noise)]
+    return xl, xr, yl, yr
+
+
+HANN = [0.5 - 0.5 * math.cos(2 * math.pi * (k + 1) / (N_FRAME + 1)) for k in range(N_FRAME)]
+
+
+def remove_silent_frames(xl, xr, signals):
+    starts = list(range(0, len(xl) - N_FRAME, HOP))
+
+    def energies(x):
+        return [
+            20 * math.log10(math.sqrt(sum((HANN[k] * x[s + k]) ** 2 for k in range(N_FRAME))) + 2.220446049250313e-16)
+            for s in starts
+        ]
+
+    el, er = energies(xl), energies(xr)
+    ml, mr = max(el), max(er)
+    kept = [s for s, a, b in zip(starts, el, er) if a - ml + DYN_RANGE > 0 or b - mr + DYN_RANGE > 0]
+
+    out = []
+    for x in signals:
+        sil = [0.0] * ((len(kept) - 1) * HOP + N_FRAME)
+        for j, s in enumerate(kept):
+            for k in range(N_FRAME):
+                sil[j * HOP + k] += HANN[k] * x[s + k]
+        out.append(sil)
+    return out
+
+
+def fft(values):
+    n = len(values)
+    if n == 1:
+        return values
+    even, odd = fft(values[0::2]), fft(values[1::2])
+    twiddles = [cmath.exp(-2j * math.pi * k / n) * odd[k] for k in range(n // 2)]
+    return [even[k] + twiddles[k] for k in range(n // 2)] + [even[k] - twiddles[k] for k in range(n // 2)]
+
+
+def stdft(x):
+    frames = []
+    for s in range(0, len(x) - N_FRAME, HOP):
+        frame = [HANN[k] * x[s + k] for k in range(N_FRAME)] + [0.0] * (K_FFT - N_FRAME)
+        frames.append(fft(frame)[: K_FFT // 2 + 1])
+    return frames
+
+
+def thirdoct():
+    f = [i * FS / K_FFT for i in range(K_FFT // 2 + 1)]
+    bands = []
+    for k in range(J):
+        low = MIN_FREQ * 2 ** ((2 * k - 1) / 6)
+        high = MIN_FREQ * 2 ** ((2 * k + 1) / 6)
+        closest = lambda freq: min(range(len(f)), key=lambda i: ((f[i] - freq) ** 2, i))
+        bands.append((closest(low), closest(high)))
+    return bands
+
+
+def gaussian_mean(function, sigma, points=801):
+    """E[function(e)] for e ~ N(0, sigma^2), by trapezoidal integration over ±8 sigma"""
+    total = 0.0
+    weights = 0.0
+    for i in range(points):
+        e = -8 * sigma + 16 * sigma * i / (points - 1)
+        w = math.exp(-0.5 * (e / sigma) ** 2) * (0.5 if i in (0, points - 1) else 1.0)
+        total += w * function(e)
+        weights += w
+    return total / weights
+
+
+def centered(values):
+    mean = sum(values) / len(values)
+    return [v - mean for v in values]
+
+
+def correlation(x, y):
+    x, y = centered(x), centered(y)
+    return sum(a * b for a, b in zip(x, y)) / (math.sqrt(sum(a * a for a in x)) * math.sqrt(sum(b * b for b in y)))
+
+
+def gram(fa, fb):
+    """Sums of products of the centered features of two segments"""
+    return [[sum(a[n] * b[n] for n in range(N)) for b in fb] for a in fa]
+
+
+def dbstoi(xl, xr, yl, yr):
+    xl, xr, yl, yr = remove_silent_frames(xl, xr, [xl, xr, yl, yr])
+    spectra = [stdft(x) for x in (xl, xr, yl, yr)]
+    bands = thirdoct()
+    n_frames = len(spectra[0])
+    n_segments = n_frames - N + 1
+
+    # Level jitter moments E[a], E[1/a], E[a^2], E[1/a^2] with a = 10^((gamma + eps) / 20)
+    level = []
+    for gamma in GAMMAS:
+        sigma = math.sqrt(2) * SIGMA_EPSILON_0 * (1 + (abs(gamma) / ALPHA_0) ** P)
+        level.append([gaussian_mean(lambda e, p=p: 10 ** (p * (gamma + e) / 20), sigma) for p in (1, -1, 2, -2)])
+
+    total = 0.0
+    for j, (start, end) in enumerate(bands):
+        omega = 2 * math.pi * MIN_FREQ * 2 ** (j / 3)
+
+        def band(left, right):
+            powers_l = [sum(abs(b) ** 2 for b in frame[start:end]) for frame in left]
+            powers_r = [sum(abs(b) ** 2 for b in frame[start:end]) for frame in right]
+            cross = [sum(a * b.conjugate() for a, b in zip(fl[start:end], fr[start:end])) for fl, fr in zip(left, right)]
+            return powers_l, powers_r, cross
+
+        x = band(spectra[0], spectra[1])
+        y = band(spectra[2], spectra[3])
+
+        # Phase jitter moments of theta = omega (tau + delta)
+        phase = []
+        for tau in TAUS:
+            sigma = math.sqrt(2) * SIGMA_DELTA_0 * (1 + abs(tau) / TAU_0)
+            moments = {}
+            for name, function in {
+                "c": lambda t: math.cos(t),
+                "s": lambda t: math.sin(t),
+                "cc": lambda t: math.cos(t) ** 2,
+                "ss": lambda t: math.sin(t) ** 2,
+                "cs": lambda t: math.cos(t) * math.sin(t),
+            }.items():
+                moments[name] = gaussian_mean(lambda d: function(omega * (tau + d)), sigma)
+            phase.append(moments)
+
+        for m in range(n_segments):
+            def features(signal):
+                l, r, c = (v[m : m + N] for v in signal)
+                return [centered(l), centered(r), centered([v.real for v in c]), centered([v.imag for v in c])]
+
+            fx, fy = features(x), features(y)
+            g_xy, g_xx, g_yy = gram(fx, fy), gram(fx, fx), gram(fy, fy)
+
+            best, best_power = -math.inf, 0.0
+            for moments in phase:
+                for a, a_inv, a2, a_inv2 in level:
+                    # E[w_i w_j] for the EC output weights w = (a, 1/a, -2 cos, 2 sin)
+                    ew = [
+                        [a2, 1.0, -2 * a * moments["c"], 2 * a * moments["s"]],
+                        [1.0, a_inv2, -2 * a_inv * moments["c"], 2 * a_inv * moments["s"]],
+                        [-2 * a * moments["c"], -2 * a_inv * moments["c"], 4 * moments["cc"], -4 * moments["cs"]],
+                        [2 * a * moments["s"], 2 * a_inv * moments["s"], -4 * moments["cs"], 4 * moments["ss"]],
+                    ]
+                    e_xy, e_xx, e_yy = (
+                        sum(ew[i][k] * g[i][k] for i in range(4) for k in range(4)) for g in (g_xy, g_xx, g_yy)
+                    )
+                    rho = e_xy / math.sqrt(max(e_xx * e_yy, 0.0))
+                    if rho > best:
+                        best, best_power = rho, e_xx
+
+            # Better ear
+            power_l = g_xx[0][0]
+            power_r = g_xx[1][1]
+            if power_l > power_r:
+                power, d = power_l, correlation([math.sqrt(v) for v in x[0][m : m + N]], [math.sqrt(v) for v in y[0][m : m + N]])
+            else:
+                power, d = power_r, correlation([math.sqrt(v) for v in x[1][m : m + N]], [math.sqrt(v) for v in y[1][m : m + N]])
+            total += d if power > best_power else best
+
+    return total / (n_segments * J)
+
+
+def write_wav(path, left, right):
+    """Write a 32-bit float stereo WAV file"""
+    data = b"".join(struct.pack("<ff", l, r) for l, r in zip(left, right))
+    header = struct.pack("<4sI4s", b"RIFF", 36 + len(data), b"WAVE")
+    header += struct.pack("<4sIHHIIHH", b"fmt ", 16, 3, 2, FS, FS * 8, 8, 32)
+    header += struct.pack("<4sI", b"data", len(data))
+    with open(path, "wb") as file:
+        file.write(header + data)
+
+
+if __name__ == "__main__":
+    import os
+    import sys
+
+    for gain, diotic in [(0.3, False), (0.5, True)]:
+        xl, xr, yl, yr = signals(6000, gain, diotic)
+        if len(sys.argv) == 3 and sys.argv[1] == "--wav":
+            name = f"{gain}_{'diotic' if diotic else 'independent'}.wav"
+            write_wav(os.path.join(sys.argv[2], "clean_" + name), xl, xr)
+            write_wav(os.path.join(sys.argv[2], "processed_" + name), yl, yr)
+        print(gain, diotic, dbstoi(xl, xr, yl, yr))
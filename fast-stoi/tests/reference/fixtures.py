@@ -0,0 +1,153 @@
+"""Audio fixtures for the decoding tests of `tests/files.rs`.
+
+Writes one second of 16-bit mono speech-like signal at 8kHz to `speech.flac` (native FLAC)
+and the same signal with noise to `noisy.ogg` (FLAC in an Ogg container). Frames are stored
+verbatim, so that the tests can compare the decoded samples exactly with `speech_samples`
+and `noisy_samples` of `tests/files.rs`, which use the same integer arithmetic.
+
+Only uses the standard library: `python3 fixtures.py`
+"""
+
+import struct
+from pathlib import Path
+
+FS = 8000
+BLOCK_SIZE = 1024
+
+
+def lcg(seed):
+    """Upper 16 bits of the deterministic noise generator of the tests"""
+    state = seed
+    while True:
+        state = (state * 6_364_136_223_846_793_005 + 1_442_695_040_888_963_407) % 2**64
+        yield state >> 48
+
+
+def speech_samples(length):
+    """Noise modulated by a 4Hz triangle, with pauses"""
+    noise = lcg(1)
+    period = FS // 4
+    out = []
+    for n in range(length):
+        envelope = 256 - abs((n % period) * 512 // period - 256)
+        if n % (3 * FS // 2) >= 6 * FS // 5:
+            envelope //= 64
+        out.append(next(noise) * envelope // 256 - 128 * envelope)
+    return out
+
+
+def noisy_samples(length):
+    noise = lcg(2)
+    return [
+        max(-32768, min(32767, s + next(noise) // 8 - 4096)) for s in speech_samples(length)
+    ]
+
+
+class Bits:
+    def __init__(self):
+        self.bytes = bytearray()
+        self.value = 0
+        self.count = 0
+
+    def write(self, value, bits):
+        for i in reversed(range(bits)):
+            self.value = (self.value << 1) | ((value >> i) & 1)
+            self.count += 1
+            if self.count == 8:
+                self.bytes.append(self.value)
+                self.value, self.count = 0, 0
+
+    def align(self):
+        while self.count:
+            self.write(0, 1)
+
+
+def crc(data, poly, bits):
+    value = 0
+    top = 1 << (bits - 1)
+    mask = (1 << bits) - 1
+    for byte in data:
+        value ^= byte << (bits - 8)
+        for _ in range(8):
+            value = ((value << 1) ^ poly) & mask if value & top else (value << 1) & mask
+    return value
+
+
+def stream_info(length, last):
+    bits = Bits()
+    bits.write(1 if last else 0, 1)
+    bits.write(0, 7)  # STREAMINFO
+    bits.write(34, 24)
+    bits.write(BLOCK_SIZE, 16)
+    bits.write(BLOCK_SIZE, 16)
+    bits.write(0, 24)  # Unknown frame sizes
+    bits.write(0, 24)
+    bits.write(FS, 20)
+    bits.write(0, 3)  # Mono
+    bits.write(15, 5)  # 16 bits per sample
+    bits.write(length, 36)
+    bits.write(0, 128)  # No MD5 signature
+    return bytes(bits.bytes)
+
+
+def frames(samples):
+    """FLAC frames with a verbatim subframe"""
+    for number, start in enumerate(range(0, len(samples), BLOCK_SIZE)):
+        block = samples[start : start + BLOCK_SIZE]
+        bits = Bits()
+        bits.write(0xFFF8, 16)  # Sync code, fixed block size
+        bits.write(0b1010 if len(block) == BLOCK_SIZE else 0b0111, 4)
+        bits.write(0b0100, 4)  # 8kHz
+        bits.write(0, 4)  # Mono
+        bits.write(0b100, 3)  # 16 bits per sample
+        bits.write(0, 1)
+        bits.write(number, 8)  # UTF-8 coded frame number (< 128)
+        if len(block) != BLOCK_SIZE:
+            bits.write(len(block) - 1, 16)
+        bits.write(crc(bits.bytes, 0x07, 8), 8)
+
+        bits.write(0b00000010, 8)  # Verbatim subframe without wasted bits
+        for sample in block:
+            bits.write(sample & 0xFFFF, 16)
+        bits.align()
+        bits.write(crc(bits.bytes, 0x8005, 16), 16)
+        yield len(block), bytes(bits.bytes)
+
+
+def write_flac(path, samples):
+    data = b"fLaC" + stream_info(len(samples), last=True)
+    data += b"".join(frame for _, frame in frames(samples))
+    path.write_bytes(data)
+
+
+def ogg_page(packet, granule, sequence, flags):
+    lacing = [255] * (len(packet) // 255) + [len(packet) % 255]
+    header = struct.pack("<4sBBqIIIB", b"OggS", 0, flags, granule, 1, sequence, 0, len(lacing))
+    page = header + bytes(lacing) + packet
+    checksum = crc(page, 0x04C11DB7, 32)
+    return page[:22] + struct.pack("<I", checksum) + page[26:]
+
+
+def write_ogg(path, samples):
+    vendor = b"fast-stoi fixtures"
+    comments = struct.pack("<I", len(vendor)) + vendor + struct.pack("<I", 0)
+    packets = [
+        (b"\x7fFLAC\x01\x00" + struct.pack(">H", 1) + b"fLaC" + stream_info(len(samples), False), 0),
+        (bytes([0x84]) + len(comments).to_bytes(3, "big") + comments, 0),
+    ]
+    granule = 0
+    for length, frame in frames(samples):
+        granule += length
+        packets.append((frame, granule))
+
+    pages = []
+    for sequence, (packet, granule) in enumerate(packets):
+        flags = 0x02 if sequence == 0 else 0x04 if sequence == len(packets) - 1 else 0
+        pages.append(ogg_page(packet, granule, sequence, flags))
+    path.write_bytes(b"".join(pages))
+
+
+if __name__ == "__main__":
+    directory = Path(__file__).parent
+    write_flac(directory / "speech.flac", speech_samples(FS))
+    write_ogg(directory / "noisy.ogg", noisy_samples(FS))
@@ -0,0 +1,42 @@
+//! SIIB of whole signals
+
+mod common;
+
+use common::{degrade, speech};
+
+const FS: usize = 16_000;
+
+#[test]
+fn noise_lowers_the_information_rate() {
+    let x = speech(5 * FS, FS, 1);
+    let clean = fast_stoi::siib(&x, &x, FS).unwrap();
+
+    let mut previous = clean;
+    for gain in [0.1, 0.5, 2.0] {
+        let score = fast_stoi::siib(&x, &degrade(&x, gain, 2), FS).unwrap();
+        assert!(score < previous, "gain {gain}: {score} >= {previous}");
+        previous = score;
+    }
+}
+
+#[test]
+fn batch_matches_single_clips() {
+    let x = [speech(3 * FS, FS, 1), speech(4 * FS, FS, 2)];
+    let y = [degrade(&x[0], 0.2, 3), degrade(&x[1], 0.4, 4)];
+    let x = x.iter().map(Vec::as_slice).collect::<Vec<_>>();
+    let y = y.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+    let batch = fast_stoi::batch_siib(&x, &y, FS);
+    assert_eq!(batch.len(), x.len());
+
+    for (i, score) in batch.into_iter().enumerate() {
+        let single = fast_stoi::siib(x[i], y[i], FS).unwrap();
+        assert_eq!(score.unwrap().to_bits(), single.to_bits(), "clip {i}");
+    }
+}
+
+#[test]
+fn short_signals_are_rejected() {
+    let x = speech(FS / 4, FS, 1);
+    assert!(fast_stoi::siib(&x, &x, FS).is_err());
+}
@@ -0,0 +1,90 @@
+//! SNR sweeps with processings under test
+
+mod common;
+
+use common::{noise, speech};
+use fast_stoi::{Noise, ProcessedLengthError, SweepError};
+
+const FS: usize = 16_000;
+
+#[test]
+fn processing_must_preserve_the_length() {
+    let clean = speech(3 * FS, FS, 1);
+    let sweep = fast_stoi::snr_sweep(&clean, Noise::SpeechShaped, FS, false, 42).unwrap();
+
+    let truncate = |noisy: &[f32]| noisy[..noisy.len() - 1].to_vec();
+    let error = ProcessedLengthError {
+        expected: clean.len(),
+        found: clean.len() - 1,
+    };
+    assert_eq!(sweep.score(0.0, truncate), Err(error.clone()));
+    assert_eq!(sweep.sweep(&[-5.0, 0.0], truncate), Err(error.clone()));
+    assert_eq!(sweep.srt(0.75, -20.0, 20.0, 0.1, truncate), Err(error));
+
+    let scores = sweep.sweep(&[-5.0, 5.0], |noisy| noisy.to_vec()).unwrap();
+    assert!(scores[0] < scores[1], "{scores:?}");
+}
+
+#[test]
+fn short_noise_is_rejected() {
+    let clean = speech(3 * FS, FS, 1);
+    let noise = noise(clean.len() - 1, 2);
+
+    let result = fast_stoi::snr_sweep(&clean, Noise::Signal(&noise), FS, false, 42);
+    assert!(matches!(
+        result,
+        Err(SweepError::NoiseTooShort { noise, clean: len }) if noise == len - 1 && len == clean.len()
+    ));
+}
+
+#[test]
+fn seed_reproduces_the_mixture() {
+    let clean = speech(3 * FS, FS, 1);
+    let recorded = noise(5 * FS, 2);
+
+    for noise in [Noise::SpeechShaped, Noise::Signal(&recorded)] {
+        let mixture = |seed| {
+            let sweep = fast_stoi::snr_sweep(&clean, noise, FS, false, seed).unwrap();
+            sweep.mixture(0.0)
+        };
+        assert_eq!(mixture(42), mixture(42));
+        assert_ne!(mixture(42), mixture(43));
+    }
+}
+
+#[test]
+fn scores_match_stoi() {
+    let clean = speech(3 * FS, FS, 1);
+
+    for extended in [false, true] {
+        let sweep = fast_stoi::snr_sweep(&clean, Noise::SpeechShaped, FS, extended, 42).unwrap();
+        for snr in [-10.0, 0.0, 10.0] {
+            let score = sweep.score(snr, |noisy| noisy.to_vec()).unwrap();
+            let expected = fast_stoi::stoi(&clean, &sweep.mixture(snr), FS, extended).unwrap();
+            assert_eq!(score.to_bits(), expected.to_bits(), "{snr}dB");
+        }
+    }
+}
+
+#[test]
+fn srt_brackets_the_target() {
+    let clean = speech(3 * FS, FS, 1);
+    let sweep = fast_stoi::snr_sweep(&clean, Noise::SpeechShaped, FS, false, 42).unwrap();
+    let identity = |noisy: &[f32]| noisy.to_vec();
+
+    let tolerance = 0.1;
+    let srt = sweep
+        .srt(0.75, -30.0, 30.0, tolerance, identity)
+        .unwrap()
+        .unwrap();
+    let below = sweep.score(srt - tolerance, identity).unwrap();
+    let above = sweep.score(srt + tolerance, identity).unwrap();
+    assert!(below < 0.75 && above >= 0.75, "{srt}dB: {below} {above}");
+
+    // Targets that are not reached within the interval
+    assert_eq!(sweep.srt(0.75, 20.0, 30.0, tolerance, identity), Ok(None));
+    assert_eq!(
+        sweep.srt(0.99999, -30.0, -20.0, tolerance, identity),
+        Ok(None)
+    );
+}
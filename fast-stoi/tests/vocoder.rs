@@ -0,0 +1,55 @@
+//! STOI of clean speech heard through a cochlear implant vocoder
+
+mod common;
+
+use std::f32::consts::PI;
+
+use common::speech;
+use fast_stoi::{Carrier, Config, Engine, Vocoder};
+
+const FS: usize = 16_000;
+
+/// Speech-like signal whose frequency regions have different envelopes,
+/// so that vocoders with few channels merge them
+fn spectrally_modulated(len: usize) -> Vec<f32> {
+    let components = (0..24)
+        .map(|k| {
+            let freq = 200.0 * 2.0_f32.powf(k as f32 / 6.0);
+            let rate = 2.0 + (k * 7 % 11) as f32 / 2.0;
+            (freq, rate, k as f32)
+        })
+        .collect::<Vec<_>>();
+
+    speech(len, FS, 1)
+        .iter()
+        .enumerate()
+        .map(|(i, noise)| {
+            let t = i as f32 / FS as f32;
+            let voiced = components
+                .iter()
+                .map(|&(freq, rate, phase)| {
+                    (2.0 * PI * freq * t).sin() * (2.0 * PI * rate * t + phase).sin().max(0.0)
+                })
+                .sum::<f32>();
+            0.1 * voiced + noise
+        })
+        .collect()
+}
+
+#[test]
+fn stoi_rises_with_the_channel_count() {
+    let x = spectrally_modulated(2 * FS);
+
+    for carrier in [Carrier::Noise, Carrier::Sine] {
+        let scores = [1, 4, 16].map(|channels| {
+            let vocoder = Vocoder::new(channels, 160.0, carrier).with_seed(42);
+            Engine::new(Config::classic().with_vocoder(vocoder))
+                .stoi(&x, &x, FS, false)
+                .unwrap()
+        });
+        assert!(
+            scores.windows(2).all(|w| w[0] < w[1]),
+            "{carrier:?}: {scores:?}"
+        );
+    }
+}